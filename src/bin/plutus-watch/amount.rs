@@ -0,0 +1,105 @@
+//! Exact satoshi amounts.
+//!
+//! Snapshot dumps carry integer satoshis; hand-written rules name their unit,
+//! satoshis or BTC. Both are parsed without going through floating point, so
+//! a balance never rounds to a neighbouring satoshi.
+
+use std::collections::BTreeMap;
+
+pub const SATS_PER_BTC: u64 = 100_000_000;
+
+/// Parse a snapshot balance: an integer is satoshis, a decimal is BTC with at
+/// most eight fractional digits.
+pub fn parse_sats(value: &str) -> Option<u64> {
+    let value = value.trim();
+    match value.split_once('.') {
+        None => value.parse().ok(),
+        Some((whole, fraction)) => parse_btc(whole, fraction),
+    }
+}
+
+/// Parse a rule amount, which must name its unit: `sat`/`sats` or `btc`. A
+/// bare number is rejected, since `1` could mean either.
+pub fn parse_amount(value: &str) -> Option<u64> {
+    let lower = value.trim().to_ascii_lowercase();
+    if let Some(number) = lower
+        .strip_suffix("sats")
+        .or_else(|| lower.strip_suffix("sat"))
+    {
+        return number.trim().parse().ok();
+    }
+    if let Some(number) = lower.strip_suffix("btc") {
+        let number = number.trim();
+        return match number.split_once('.') {
+            None => number.parse::<u64>().ok()?.checked_mul(SATS_PER_BTC),
+            Some((whole, fraction)) => parse_btc(whole, fraction),
+        };
+    }
+    None
+}
+
+fn parse_btc(whole: &str, fraction: &str) -> Option<u64> {
+    if fraction.len() > 8 || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let whole: u64 = if whole.is_empty() {
+        0
+    } else {
+        whole.parse().ok()?
+    };
+    let fraction: u64 = if fraction.is_empty() {
+        0
+    } else {
+        format!("{fraction:0<8}").parse().ok()?
+    };
+    whole.checked_mul(SATS_PER_BTC)?.checked_add(fraction)
+}
+
+/// Total satoshis held by `addresses`; unlisted addresses count as empty.
+pub fn sum_balances<'a>(
+    balances: &BTreeMap<String, u64>,
+    addresses: impl IntoIterator<Item = &'a String>,
+) -> u64 {
+    addresses
+        .into_iter()
+        .filter_map(|address| balances.get(address))
+        .sum()
+}
+
+/// Signed difference, formatted with an explicit sign.
+pub fn format_delta(before: u64, after: u64) -> String {
+    if after >= before {
+        format!("+{}", after - before)
+    } else {
+        format!("-{}", before - after)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshot_balances_parse_exactly() {
+        assert_eq!(parse_sats("1"), Some(1));
+        assert_eq!(parse_sats("0.00000001"), Some(1));
+        assert_eq!(parse_sats("20999999.9769"), Some(2_099_999_997_690_000));
+        assert_eq!(parse_sats("0.1"), Some(10_000_000));
+        assert_eq!(parse_sats("0.0"), Some(0));
+        assert_eq!(parse_sats("0.000000001"), None);
+        assert_eq!(parse_sats("-1"), None);
+        assert_eq!(parse_sats(""), None);
+    }
+
+    #[test]
+    fn rule_amounts_accept_units() {
+        assert_eq!(parse_amount("0.5btc"), Some(50_000_000));
+        assert_eq!(parse_amount("2 BTC"), Some(200_000_000));
+        assert_eq!(parse_amount("1500sat"), Some(1500));
+        assert_eq!(parse_amount("1500 sats"), Some(1500));
+        assert_eq!(parse_amount("1500"), None);
+        assert_eq!(parse_amount("0.5"), None);
+        assert_eq!(format_delta(10, 3), "-7");
+        assert_eq!(format_delta(3, 10), "+7");
+    }
+}
@@ -0,0 +1,342 @@
+//! Where watched balances come from.
+//!
+//! The default is a local snapshot: a TSV dump, plain or compressed, read in
+//! full on every check, or the engine's PLH1/PLH2 hash160 file, probed for
+//! the watched addresses only. Live backends query an indexer for the watched
+//! addresses only.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use plutus_rustus::{compress, db};
+
+use crate::amount::parse_sats;
+use crate::bitcoind::{BitcoindBackend, DEFAULT_RPC_URL};
+use crate::consensus::{majority, Consensus, ConsensusBackend};
+use crate::electrum::ElectrumBackend;
+use crate::esplora::EsploraBackend;
+
+/// Settings only some backends use.
+#[derive(Debug, Default, Clone)]
+pub struct BackendOptions {
+    pub rpc_cookie: Option<PathBuf>,
+    pub rpc_user: Option<String>,
+    pub esplora_concurrency: Option<usize>,
+    pub esplora_requests_per_second: Option<f64>,
+    /// PEM certificates `electrums://` trusts instead of the public roots.
+    pub electrum_ca_file: Option<PathBuf>,
+}
+
+/// One check's view of the watched addresses.
+#[derive(Debug, Clone)]
+pub struct Scan {
+    /// Snapshot path or backend URL, for the report.
+    pub source: String,
+    /// Rows read from a snapshot; live backends leave this unset.
+    pub rows_scanned: Option<u64>,
+    /// Seconds since the data was produced, when the backend knows it.
+    pub age_seconds: Option<u64>,
+    /// Time the data describes: snapshot mtime, or the query time.
+    pub observed_unix: u64,
+    /// Funded watched addresses only, in satoshis, mempool included.
+    pub balances: BTreeMap<String, u64>,
+    /// Net unconfirmed change per address, for backends that can tell
+    /// confirmed and mempool funds apart. Absent addresses have none.
+    pub mempool: Option<BTreeMap<String, i64>>,
+    /// The source records which addresses are funded but not how much; every
+    /// balance is then a placeholder of one satoshi.
+    pub presence_only: bool,
+    /// Funded addresses whose balance is that one-satoshi placeholder: all
+    /// of them for a presence-only source, address-only or unreadable rows of a
+    /// dump, or addresses only presence-only sources voted funded. Amount
+    /// rules skip them.
+    pub unpriced: BTreeSet<String>,
+    /// Watched addresses this source cannot look up at all, such as P2SH or
+    /// P2TR against a hash160 snapshot. They always read as unfunded.
+    pub unmatchable: usize,
+    /// Set when several sources were reconciled.
+    pub consensus: Option<Consensus>,
+}
+
+impl Scan {
+    pub fn funded(&self) -> BTreeSet<String> {
+        self.balances.keys().cloned().collect()
+    }
+
+    /// Fold in a scan of further addresses from the same source. Row count,
+    /// age and observation time stay those of the first scan.
+    pub fn merge(&mut self, other: Scan) {
+        self.balances.extend(other.balances);
+        if let (Some(mempool), Some(more)) = (&mut self.mempool, other.mempool) {
+            mempool.extend(more);
+        }
+        self.unpriced.extend(other.unpriced);
+        self.unmatchable += other.unmatchable;
+        if let (Some(consensus), Some(more)) = (&mut self.consensus, other.consensus) {
+            consensus.undecided.extend(more.undecided);
+            consensus.divergence.extend(more.divergence);
+            consensus
+                .divergence
+                .sort_by(|a, b| a.address.cmp(&b.address));
+        }
+    }
+}
+
+pub trait Backend {
+    fn scan(&mut self, watched: &BTreeSet<String>) -> Result<Scan, Box<dyn Error>>;
+
+    /// Block until the next check is due. Backends with push notifications
+    /// may return early when a watched address changes.
+    fn wait(&mut self, interval: Duration) -> Result<(), Box<dyn Error>> {
+        thread::sleep(interval);
+        Ok(())
+    }
+}
+
+/// `electrum://host:port` (plain TCP), `electrums://host:port` (TLS),
+/// `bitcoind` or `bitcoind://host:port`, `esplora+https://host/api`;
+/// anything else is a snapshot path, optionally as `file://`.
+pub fn open(spec: &str, options: &BackendOptions) -> Result<Box<dyn Backend>, Box<dyn Error>> {
+    if spec == "bitcoind" {
+        return Ok(Box::new(BitcoindBackend::new(DEFAULT_RPC_URL, options)?));
+    }
+    if let Some(address) = spec.strip_prefix("bitcoind://") {
+        let url = format!("http://{address}");
+        return Ok(Box::new(BitcoindBackend::new(&url, options)?));
+    }
+    if let Some(url) = spec.strip_prefix("esplora+") {
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            return Err(format!("esplora backend needs an http(s) URL, got {url:?}").into());
+        }
+        return Ok(Box::new(EsploraBackend::new(url, options)?));
+    }
+    if let Some(address) = spec.strip_prefix("electrum://") {
+        return Ok(Box::new(ElectrumBackend::new(address, false, options)?));
+    }
+    if let Some(address) = spec.strip_prefix("electrums://") {
+        return Ok(Box::new(ElectrumBackend::new(address, true, options)?));
+    }
+    let path = spec.strip_prefix("file://").unwrap_or(spec);
+    if path.contains("://") {
+        return Err(format!("unsupported backend {spec:?}").into());
+    }
+    Ok(Box::new(SnapshotBackend::new(path)))
+}
+
+/// One source as is, or several behind a [`ConsensusBackend`] requiring
+/// `quorum` of them (default: a majority) to agree.
+pub fn open_all(
+    specs: &[String],
+    quorum: Option<usize>,
+    options: &BackendOptions,
+) -> Result<Box<dyn Backend>, Box<dyn Error>> {
+    if let [spec] = specs {
+        if quorum.is_some_and(|q| q != 1) {
+            return Err("a quorum needs more than one source".into());
+        }
+        return open(spec, options);
+    }
+    let sources = specs
+        .iter()
+        .map(|spec| open(spec, options))
+        .collect::<Result<Vec<_>, _>>()?;
+    let quorum = quorum.unwrap_or(majority(sources.len()));
+    Ok(Box::new(ConsensusBackend::new(sources, quorum)?))
+}
+
+pub struct SnapshotBackend {
+    path: PathBuf,
+}
+
+impl SnapshotBackend {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        SnapshotBackend { path: path.into() }
+    }
+}
+
+impl Backend for SnapshotBackend {
+    fn scan(&mut self, watched: &BTreeSet<String>) -> Result<Scan, Box<dyn Error>> {
+        let open =
+            || File::open(&self.path).map_err(|error| format!("{}: {error}", self.path.display()));
+        let mut magic = [0u8; 4];
+        let is_plh =
+            open()?.read_exact(&mut magic).is_ok() && (&magic == b"PLH1" || &magic == b"PLH2");
+        if is_plh {
+            return scan_plh(&self.path, watched);
+        }
+
+        let modified = open()?.metadata()?.modified()?;
+        let observed_unix = modified
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let age_seconds = SystemTime::now().duration_since(modified)?.as_secs();
+        let mut rows_scanned = 0;
+        let mut balances = BTreeMap::new();
+        let mut unpriced = BTreeSet::new();
+
+        let (_, reader) = compress::open_dump(&self.path)?;
+        for line in BufReader::new(reader).lines() {
+            let line = line?;
+            let mut columns = line.split('\t');
+            let Some(address) = columns.next() else {
+                continue;
+            };
+            let address = address.trim();
+            if address.eq_ignore_ascii_case("address") {
+                continue;
+            }
+            rows_scanned += 1;
+            if !watched.contains(address) {
+                continue;
+            }
+
+            // Address-only dumps list funded addresses without amounts, and a
+            // balance we cannot read exactly still means the address is in
+            // the dump; count both as one satoshi so presence registers.
+            match columns.next().map(parse_sats) {
+                Some(Some(0)) => {}
+                Some(Some(sats)) => {
+                    balances.insert(address.to_owned(), sats);
+                }
+                Some(None) | None => {
+                    balances.insert(address.to_owned(), 1);
+                    unpriced.insert(address.to_owned());
+                }
+            }
+        }
+
+        Ok(Scan {
+            source: self.path.display().to_string(),
+            rows_scanned: Some(rows_scanned),
+            age_seconds: Some(age_seconds),
+            observed_unix,
+            balances,
+            mempool: None,
+            presence_only: false,
+            unpriced,
+            unmatchable: 0,
+            consensus: None,
+        })
+    }
+}
+
+/// Probe a hash160 snapshot with `Db::contains`. Opened afresh on every check
+/// so a snapshot replaced by `data update` is picked up. The P2PKH and P2WPKH
+/// addresses of one key share a hash160, so both read as funded.
+fn scan_plh(path: &Path, watched: &BTreeSet<String>) -> Result<Scan, Box<dyn Error>> {
+    let header = db::read_header(path)?;
+    let snapshot = db::open_snapshot_read_only(path)
+        .map_err(|error| format!("{}: {error}", path.display()))?;
+    let mut balances = BTreeMap::new();
+    let mut unmatchable = 0;
+    for address in watched {
+        match header.chain.hash160(address) {
+            Some(hash) if snapshot.contains(&hash) => {
+                balances.insert(address.clone(), 1);
+            }
+            Some(_) => {}
+            None => unmatchable += 1,
+        }
+    }
+    Ok(Scan {
+        source: path.display().to_string(),
+        rows_scanned: Some(header.count),
+        age_seconds: Some(db::snapshot_age_secs(path)?),
+        observed_unix: header.created_unix,
+        unpriced: balances.keys().cloned().collect(),
+        balances,
+        mempool: None,
+        presence_only: true,
+        unmatchable,
+        consensus: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::unique_dir;
+    use plutus_rustus::config::Lookup;
+    use std::fs;
+    use std::io::Write;
+
+    const FUNDED: &str = "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH";
+    const ABSENT: &str = "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu";
+    const P2SH: &str = "3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy";
+
+    /// A PLH1 file: 64-byte header, then sorted hash160 records.
+    fn write_plh1(path: &Path, hashes: &[[u8; 20]]) {
+        let mut header = [0u8; 64];
+        header[0..4].copy_from_slice(b"PLH1");
+        header[4..6].copy_from_slice(&1u16.to_le_bytes());
+        header[8..16].copy_from_slice(&(hashes.len() as u64).to_le_bytes());
+        let mut file = File::create(path).unwrap();
+        file.write_all(&header).unwrap();
+        for hash in hashes {
+            file.write_all(hash).unwrap();
+        }
+    }
+
+    #[test]
+    fn plh_snapshots_report_presence_only() {
+        let dir = unique_dir("plutus-watch-plh");
+        let path = dir.join("addresses.h160");
+        let mut hashes = vec![db::address_hash160(FUNDED).unwrap(), [0x42; 20]];
+        hashes.sort_unstable();
+        write_plh1(&path, &hashes);
+        let watched: BTreeSet<String> = [FUNDED, ABSENT, P2SH].map(String::from).into();
+
+        let mut backend = open(path.to_str().unwrap(), &BackendOptions::default()).unwrap();
+        let scan = backend.scan(&watched).unwrap();
+        assert!(scan.presence_only);
+        assert_eq!(scan.rows_scanned, Some(2));
+        assert_eq!(scan.unmatchable, 1);
+        assert_eq!(scan.funded(), [FUNDED.to_owned()].into());
+
+        // The engine converts PLH1 in place; the watcher must keep working.
+        db::load_snapshot_with(&path, Lookup::Mmap, 16).unwrap();
+        assert_eq!(db::read_header(&path).unwrap().magic, "PLH2");
+        assert_eq!(backend.scan(&watched).unwrap().funded().len(), 1);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn unreadable_dump_balances_stay_funded() {
+        let dir = unique_dir("plutus-watch-unpriced");
+        let path = dir.join("balances.tsv");
+        fs::write(
+            &path,
+            "address\tbalance\n\
+             exact\t1500\n\
+             empty\t0\n\
+             huge\t340282366920938463463374607431768211455\n\
+             precise\t0.000000001\n\
+             exponent\t1e-8\n",
+        )
+        .unwrap();
+        let watched: BTreeSet<String> = ["exact", "empty", "huge", "precise", "exponent"]
+            .map(String::from)
+            .into();
+
+        let scan = SnapshotBackend::new(&path).scan(&watched).unwrap();
+        assert_eq!(scan.balances.get("exact"), Some(&1500));
+        assert!(!scan.balances.contains_key("empty"));
+        assert_eq!(
+            scan.funded(),
+            ["exact", "huge", "precise", "exponent"]
+                .map(String::from)
+                .into()
+        );
+        assert_eq!(
+            scan.unpriced,
+            ["huge", "precise", "exponent"].map(String::from).into()
+        );
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,265 @@
+//! Bitcoin Core `scantxoutset` backend.
+//!
+//! Scans the node's UTXO set for `addr(...)` descriptors covering the whole
+//! watchlist in one call, so totals are exact as of the node's tip. A scan
+//! walks the full UTXO set and takes a minute or more on mainnet; Core runs
+//! only one at a time.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+use bitcoin::Address;
+use plutus_rustus::status::unix_now;
+use reqwest::blocking::Client;
+use serde_json::{json, Value};
+
+use crate::backend::{Backend, BackendOptions, Scan};
+
+pub const DEFAULT_RPC_URL: &str = "http://127.0.0.1:8332";
+pub const PASSWORD_ENV: &str = "PLUTUS_RPC_PASSWORD";
+const SCAN_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+#[derive(Debug, Clone)]
+enum Auth {
+    /// Re-read on every call; Core rewrites the cookie when it restarts.
+    Cookie(PathBuf),
+    UserPass(String, String),
+}
+
+pub struct BitcoindBackend {
+    url: String,
+    auth: Auth,
+    client: Client,
+}
+
+impl BitcoindBackend {
+    /// `url` is the node's RPC endpoint. Credentials come from `--rpc-user`
+    /// plus the password environment variable, else from a cookie file
+    /// (`--rpc-cookie`, default `~/.bitcoin/.cookie`).
+    pub fn new(url: &str, options: &BackendOptions) -> Result<Self, Box<dyn Error>> {
+        let auth = match (&options.rpc_user, &options.rpc_cookie) {
+            (Some(_), Some(_)) => {
+                return Err("pass either --rpc-user or --rpc-cookie, not both".into())
+            }
+            (Some(user), None) => {
+                let password = env::var(PASSWORD_ENV)
+                    .map_err(|_| format!("--rpc-user needs {PASSWORD_ENV} to be set"))?;
+                Auth::UserPass(user.clone(), password)
+            }
+            (None, Some(cookie)) => Auth::Cookie(cookie.clone()),
+            (None, None) => {
+                let home = env::var_os("HOME").ok_or("HOME is not set; pass --rpc-cookie")?;
+                Auth::Cookie(PathBuf::from(home).join(".bitcoin/.cookie"))
+            }
+        };
+        Ok(BitcoindBackend {
+            url: url.trim_end_matches('/').to_owned(),
+            auth,
+            client: Client::builder().timeout(SCAN_TIMEOUT).build()?,
+        })
+    }
+
+    fn credentials(&self) -> Result<(String, String), Box<dyn Error>> {
+        match &self.auth {
+            Auth::UserPass(user, password) => Ok((user.clone(), password.clone())),
+            Auth::Cookie(path) => {
+                let cookie = fs::read_to_string(path)
+                    .map_err(|error| format!("cannot read {}: {error}", path.display()))?;
+                let (user, password) = cookie
+                    .trim()
+                    .split_once(':')
+                    .ok_or_else(|| format!("{} is not a cookie file", path.display()))?;
+                Ok((user.to_owned(), password.to_owned()))
+            }
+        }
+    }
+
+    fn call(&self, method: &str, params: Value) -> Result<Value, Box<dyn Error>> {
+        let (user, password) = self.credentials()?;
+        let request = json!({
+            "jsonrpc": "1.0",
+            "id": "plutus-watch",
+            "method": method,
+            "params": params,
+        });
+        let response = self
+            .client
+            .post(&self.url)
+            .basic_auth(user, Some(password))
+            .header("content-type", "application/json")
+            .body(serde_json::to_vec(&request)?)
+            .send()?;
+        let status = response.status();
+        if status == reqwest::StatusCode::UNAUTHORIZED {
+            return Err(format!("{}: RPC credentials rejected", self.url).into());
+        }
+        // Core answers RPC errors with 500 and a JSON body, so read it first.
+        let body: Value = serde_json::from_slice(&response.bytes()?)
+            .map_err(|error| format!("{}: HTTP {status}: {error}", self.url))?;
+        if let Some(error) = body.get("error").filter(|e| !e.is_null()) {
+            return Err(format!("{}: {method} failed: {error}", self.url).into());
+        }
+        Ok(body.get("result").cloned().unwrap_or(Value::Null))
+    }
+}
+
+impl Backend for BitcoindBackend {
+    fn scan(&mut self, watched: &BTreeSet<String>) -> Result<Scan, Box<dyn Error>> {
+        let mut by_script = BTreeMap::new();
+        let mut objects = Vec::new();
+        for address in watched {
+            let script = Address::from_str(address)?.assume_checked().script_pubkey();
+            by_script.insert(script.to_hex_string(), address.clone());
+            objects.push(json!({ "desc": format!("addr({address})") }));
+        }
+
+        let result = self.call("scantxoutset", json!(["start", objects]))?;
+        if result.get("success").and_then(Value::as_bool) != Some(true) {
+            return Err(format!("{}: scantxoutset did not complete: {result}", self.url).into());
+        }
+        let unspents = result
+            .get("unspents")
+            .and_then(Value::as_array)
+            .ok_or("scantxoutset result has no unspents")?;
+
+        let mut balances = BTreeMap::new();
+        for unspent in unspents {
+            let script = unspent.get("scriptPubKey").and_then(Value::as_str);
+            let Some(address) = script.and_then(|s| by_script.get(s)) else {
+                continue;
+            };
+            let amount = unspent
+                .get("amount")
+                .and_then(Value::as_f64)
+                .ok_or("scantxoutset unspent has no amount")?;
+            *balances.entry(address.clone()).or_insert(0) += btc_to_sats(amount);
+        }
+
+        Ok(Scan {
+            source: format!("bitcoind {}", self.url),
+            rows_scanned: None,
+            age_seconds: None,
+            observed_unix: unix_now(),
+            balances,
+            mempool: None,
+            presence_only: false,
+            unpriced: BTreeSet::new(),
+            unmatchable: 0,
+            consensus: None,
+        })
+    }
+}
+
+/// Core prints amounts as BTC with eight decimals. Every such value is within
+/// half a satoshi of its nearest f64, so rounding recovers it exactly.
+fn btc_to_sats(amount: f64) -> u64 {
+    (amount * 100_000_000.0).round() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_http;
+    use crate::test_util::unique_dir;
+
+    const FUNDED: &str = "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH";
+    const EMPTY: &str = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4";
+
+    /// Answers `scantxoutset` with two UTXOs for `FUNDED`, checking Basic
+    /// auth against `expected`.
+    fn stand_in(expected: &'static str) -> String {
+        test_http::serve(move |request| {
+            assert_eq!(
+                (request.method.as_str(), request.path.as_str()),
+                ("POST", "/")
+            );
+            let authorization = request.headers.get("authorization").cloned();
+            if authorization.as_deref() != Some(expected) {
+                return (401, String::new());
+            }
+            let call: Value = serde_json::from_slice(&request.body).unwrap();
+            assert_eq!(call["method"], "scantxoutset");
+            assert_eq!(call["params"][0], "start");
+            let descs: Vec<_> = call["params"][1]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|o| o["desc"].as_str().unwrap().to_owned())
+                .collect();
+            assert!(descs.contains(&format!("addr({FUNDED})")));
+            let script = Address::from_str(FUNDED)
+                .unwrap()
+                .assume_checked()
+                .script_pubkey()
+                .to_hex_string();
+            let body = json!({
+                "result": {
+                    "success": true,
+                    "unspents": [
+                        {"scriptPubKey": script, "amount": 0.1},
+                        {"scriptPubKey": script, "amount": 0.00000003},
+                    ],
+                    "total_amount": 0.10000003,
+                },
+                "error": null,
+                "id": call["id"],
+            });
+            (200, body.to_string())
+        })
+    }
+
+    fn watched() -> BTreeSet<String> {
+        [FUNDED, EMPTY].into_iter().map(String::from).collect()
+    }
+
+    #[test]
+    fn cookie_auth_and_exact_totals() {
+        let url = stand_in("Basic X19jb29raWVfXzpzZWNyZXQ=");
+        let dir = unique_dir("plutus-watch-cookie");
+        let cookie = dir.join(".cookie");
+        fs::write(&cookie, "__cookie__:secret\n").unwrap();
+        let options = BackendOptions {
+            rpc_cookie: Some(cookie.clone()),
+            ..BackendOptions::default()
+        };
+
+        let mut backend = BitcoindBackend::new(&url, &options).unwrap();
+        let scan = backend.scan(&watched()).unwrap();
+        assert_eq!(
+            scan.balances.into_iter().collect::<Vec<_>>(),
+            [(FUNDED.to_owned(), 10_000_003)]
+        );
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn user_password_auth_is_rejected_when_wrong() {
+        let url = stand_in("Basic d2F0Y2g6cmlnaHQ=");
+        let wrong = BitcoindBackend {
+            url: url.clone(),
+            auth: Auth::UserPass("watch".into(), "wrong".into()),
+            client: Client::new(),
+        };
+        let error = wrong.call("scantxoutset", json!([])).unwrap_err();
+        assert!(error.to_string().contains("credentials rejected"));
+
+        let mut right = BitcoindBackend {
+            url,
+            auth: Auth::UserPass("watch".into(), "right".into()),
+            client: Client::new(),
+        };
+        assert_eq!(right.scan(&watched()).unwrap().balances.len(), 1);
+    }
+
+    #[test]
+    fn amounts_round_to_exact_satoshis() {
+        assert_eq!(btc_to_sats(0.1), 10_000_000);
+        assert_eq!(btc_to_sats(0.29), 29_000_000);
+        assert_eq!(btc_to_sats(20_999_999.976_9), 2_099_999_997_690_000);
+    }
+}
@@ -0,0 +1,304 @@
+//! Quorum across several snapshots or backends.
+//!
+//! Dumps sometimes lag or arrive truncated, so one source dropping an address
+//! is not proof that it was emptied. Each source votes funded or empty per
+//! watched address; an address takes a side only when `quorum` sources agree.
+//! Undecided addresses keep their previous state, and every disagreement,
+//! on funded versus empty or on the amount, is reported as a divergence
+//! whether or not it was outvoted.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::backend::{Backend, Scan};
+
+pub struct ConsensusBackend {
+    sources: Vec<Box<dyn Backend>>,
+    quorum: usize,
+}
+
+/// The consensus view of one check, attached to its [`Scan`].
+#[derive(Debug, Default, Clone)]
+pub struct Consensus {
+    /// Addresses short of a quorum either way; the caller carries their
+    /// previous balance forward.
+    pub undecided: BTreeSet<String>,
+    pub divergence: Vec<Divergence>,
+}
+
+/// Sources disagreed on whether `address` is funded, or on how much it holds.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Divergence {
+    pub address: String,
+    pub funded_by: Vec<String>,
+    pub empty_by: Vec<String>,
+    /// `None` when neither side reached the quorum.
+    pub funded: Option<bool>,
+    /// Amount each funded source reported, for sources that report one.
+    #[serde(default)]
+    pub amounts: BTreeMap<String, u64>,
+}
+
+impl Divergence {
+    /// Funded sources with amounts named different ones.
+    pub fn amounts_differ(&self) -> bool {
+        let mut amounts = self.amounts.values();
+        amounts
+            .next()
+            .is_some_and(|first| amounts.any(|amount| amount != first))
+    }
+}
+
+impl ConsensusBackend {
+    pub fn new(sources: Vec<Box<dyn Backend>>, quorum: usize) -> Result<Self, Box<dyn Error>> {
+        if quorum == 0 || quorum > sources.len() {
+            return Err(format!(
+                "quorum must be between 1 and the {} configured sources",
+                sources.len()
+            )
+            .into());
+        }
+        Ok(ConsensusBackend { sources, quorum })
+    }
+}
+
+/// Default quorum: a strict majority.
+pub fn majority(sources: usize) -> usize {
+    sources / 2 + 1
+}
+
+impl Backend for ConsensusBackend {
+    fn scan(&mut self, watched: &BTreeSet<String>) -> Result<Scan, Box<dyn Error>> {
+        let mut scans = Vec::new();
+        for source in &mut self.sources {
+            match source.scan(watched) {
+                Ok(scan) => scans.push(scan),
+                // A failing source abstains rather than voting everything empty.
+                Err(error) => eprintln!("plutus-watch: source skipped: {error}"),
+            }
+        }
+        if scans.len() < self.quorum {
+            return Err(format!(
+                "only {} of {} sources answered; quorum is {}",
+                scans.len(),
+                self.sources.len(),
+                self.quorum
+            )
+            .into());
+        }
+        Ok(reconcile(scans, watched, self.quorum))
+    }
+
+    /// The first source sets the pace, so an Electrum source listed first
+    /// still wakes the loop early on a notification.
+    fn wait(&mut self, interval: Duration) -> Result<(), Box<dyn Error>> {
+        self.sources[0].wait(interval)
+    }
+}
+
+fn reconcile(scans: Vec<Scan>, watched: &BTreeSet<String>, quorum: usize) -> Scan {
+    let mut consensus = Consensus::default();
+    let mut balances = BTreeMap::new();
+    let mut unpriced = BTreeSet::new();
+    for address in watched {
+        let (funded, empty): (Vec<&Scan>, Vec<&Scan>) = scans
+            .iter()
+            .partition(|scan| scan.balances.contains_key(address));
+        let decision = if funded.len() >= quorum {
+            Some(true)
+        } else if empty.len() >= quorum {
+            Some(false)
+        } else {
+            None
+        };
+        let names = |side: &[&Scan]| side.iter().map(|s| s.source.clone()).collect();
+        let divergence = Divergence {
+            address: address.clone(),
+            funded_by: names(&funded),
+            empty_by: names(&empty),
+            funded: decision,
+            amounts: funded
+                .iter()
+                .filter(|scan| !scan.unpriced.contains(address))
+                .map(|scan| (scan.source.clone(), scan.balances[address]))
+                .collect(),
+        };
+        if (!funded.is_empty() && !empty.is_empty()) || divergence.amounts_differ() {
+            consensus.divergence.push(divergence);
+        }
+        match decision {
+            Some(true) => {
+                let balance = agreed_balance(&funded, address);
+                if balance.is_none() {
+                    unpriced.insert(address.clone());
+                }
+                balances.insert(address.clone(), balance.unwrap_or(1));
+            }
+            Some(false) => {}
+            None => {
+                consensus.undecided.insert(address.clone());
+            }
+        }
+    }
+
+    // Only the sources that answered are named.
+    let sources: Vec<&str> = scans.iter().map(|s| s.source.as_str()).collect();
+    Scan {
+        source: format!("consensus[{}] quorum={quorum}", sources.join("|")),
+        rows_scanned: None,
+        age_seconds: scans.iter().filter_map(|s| s.age_seconds).max(),
+        observed_unix: scans.iter().map(|s| s.observed_unix).min().unwrap_or(0),
+        balances,
+        mempool: None,
+        presence_only: scans.iter().all(|s| s.presence_only),
+        unpriced,
+        unmatchable: scans.iter().map(|s| s.unmatchable).max().unwrap_or(0),
+        consensus: Some(consensus),
+    }
+}
+
+/// Lower median of the funded votes that carry real amounts; sources without
+/// an amount for `address` count toward the quorum but not the amount.
+/// `None` when no funded vote has one.
+fn agreed_balance(funded: &[&Scan], address: &str) -> Option<u64> {
+    let mut amounts: Vec<u64> = funded
+        .iter()
+        .filter(|scan| !scan.unpriced.contains(address))
+        .map(|scan| scan.balances[address])
+        .collect();
+    if amounts.is_empty() {
+        return None;
+    }
+    amounts.sort_unstable();
+    Some(amounts[(amounts.len() - 1) / 2])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const A: &str = "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH";
+    const B: &str = "3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy";
+
+    struct Fixed(Option<Scan>);
+
+    impl Backend for Fixed {
+        fn scan(&mut self, _: &BTreeSet<String>) -> Result<Scan, Box<dyn Error>> {
+            Ok(self.0.clone().ok_or("source down")?)
+        }
+    }
+
+    fn scan(name: &str, balances: &[(&str, u64)]) -> Scan {
+        Scan {
+            source: name.to_owned(),
+            rows_scanned: None,
+            age_seconds: None,
+            observed_unix: 0,
+            balances: balances.iter().map(|(a, s)| (a.to_string(), *s)).collect(),
+            mempool: None,
+            presence_only: false,
+            unpriced: BTreeSet::new(),
+            unmatchable: 0,
+            consensus: None,
+        }
+    }
+
+    fn source(name: &str, balances: &[(&str, u64)]) -> Box<dyn Backend> {
+        Box::new(Fixed(Some(scan(name, balances))))
+    }
+
+    /// A PLH snapshot: funded addresses with placeholder amounts.
+    fn presence(name: &str, funded: &[&str]) -> Box<dyn Backend> {
+        let mut scan = scan(name, &funded.iter().map(|a| (*a, 1)).collect::<Vec<_>>());
+        scan.presence_only = true;
+        scan.unpriced = scan.funded();
+        Box::new(Fixed(Some(scan)))
+    }
+
+    fn watched() -> BTreeSet<String> {
+        [A, B].map(String::from).into()
+    }
+
+    #[test]
+    fn a_lagging_source_is_outvoted_and_reported() {
+        let sources = vec![
+            source("one", &[(A, 500), (B, 70)]),
+            source("two", &[(A, 900), (B, 70)]),
+            source("lagging", &[(A, 700)]),
+        ];
+        let mut backend = ConsensusBackend::new(sources, majority(3)).unwrap();
+        let scan = backend.scan(&watched()).unwrap();
+        assert_eq!(scan.balances[A], 700);
+        assert_eq!(scan.balances[B], 70);
+        let consensus = scan.consensus.unwrap();
+        assert!(consensus.undecided.is_empty());
+        assert_eq!(
+            consensus.divergence,
+            [
+                Divergence {
+                    address: A.to_owned(),
+                    funded_by: vec!["one".to_owned(), "two".to_owned(), "lagging".to_owned()],
+                    empty_by: vec![],
+                    funded: Some(true),
+                    amounts: [("one", 500), ("two", 900), ("lagging", 700)]
+                        .map(|(s, a)| (s.to_owned(), a))
+                        .into(),
+                },
+                Divergence {
+                    address: B.to_owned(),
+                    funded_by: vec!["one".to_owned(), "two".to_owned()],
+                    empty_by: vec!["lagging".to_owned()],
+                    funded: Some(true),
+                    amounts: [("one", 70), ("two", 70)]
+                        .map(|(s, a)| (s.to_owned(), a))
+                        .into(),
+                },
+            ]
+        );
+        assert!(consensus.divergence[0].amounts_differ());
+        assert!(!consensus.divergence[1].amounts_differ());
+    }
+
+    #[test]
+    fn presence_only_votes_agree_on_funded_but_not_on_an_amount() {
+        let sources = vec![
+            presence("plh", &[A, B]),
+            source("dump", &[(B, 900)]),
+            presence("plh2", &[A, B]),
+        ];
+        let mut backend = ConsensusBackend::new(sources, 2).unwrap();
+        let scan = backend.scan(&watched()).unwrap();
+        assert_eq!(scan.balances[B], 900);
+        assert_eq!(scan.unpriced, [A.to_owned()].into());
+    }
+
+    #[test]
+    fn without_quorum_an_address_is_undecided() {
+        let sources = vec![source("one", &[(A, 500)]), source("two", &[])];
+        let mut backend = ConsensusBackend::new(sources, 2).unwrap();
+        let scan = backend.scan(&watched()).unwrap();
+        assert!(scan.balances.is_empty());
+        let consensus = scan.consensus.unwrap();
+        assert_eq!(consensus.undecided, [A.to_owned()].into());
+        assert_eq!(consensus.divergence[0].funded, None);
+    }
+
+    #[test]
+    fn failing_sources_abstain_until_quorum_is_lost() {
+        let down = || -> Box<dyn Backend> { Box::new(Fixed(None)) };
+        let sources = vec![
+            source("one", &[(A, 500)]),
+            source("two", &[(A, 500)]),
+            down(),
+        ];
+        let mut backend = ConsensusBackend::new(sources, 2).unwrap();
+        assert_eq!(backend.scan(&watched()).unwrap().balances[A], 500);
+
+        let mut backend = ConsensusBackend::new(vec![source("one", &[]), down()], 2).unwrap();
+        assert!(backend.scan(&watched()).is_err());
+        assert!(ConsensusBackend::new(vec![down()], 2).is_err());
+    }
+}
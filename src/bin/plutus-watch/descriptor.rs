@@ -0,0 +1,166 @@
+//! Output descriptor watchlists.
+//!
+//! One descriptor per line, as exported by wallets, with its `#checksum` and
+//! an optional label after whitespace, or tab-separated label and group
+//! columns as in the watchlist:
+//!
+//! ```text
+//! wpkh([73c5da0a/84'/0'/0']xpub.../0/*)#wc3n3van  savings
+//! ```
+//!
+//! `pkh`, `sh(wpkh)`, `wpkh` and `tr` are supported, as are multipath
+//! (`<0;1>/*`) keys. Ranged descriptors are expanded to a fixed depth; there
+//! is no gap extension because a descriptor may cover several chains at once.
+
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::str::FromStr;
+
+use bitcoin::Network;
+use miniscript::descriptor::{DescriptorPublicKey, DescriptorType};
+use miniscript::{Descriptor, ForEachKey};
+
+use crate::watchlist::split_columns;
+
+#[derive(Debug, Clone)]
+pub struct DescriptorWatch {
+    pub label: String,
+    pub group: Option<String>,
+    pub addresses: Vec<String>,
+}
+
+impl DescriptorWatch {
+    pub fn parse(line: &str, label: Option<&str>, depth: u32) -> Result<Self, Box<dyn Error>> {
+        if !has_checksum(line) {
+            return Err("descriptor is missing its #checksum".into());
+        }
+        let descriptor = Descriptor::<DescriptorPublicKey>::from_str(line)?;
+        match descriptor.desc_type() {
+            DescriptorType::Pkh
+            | DescriptorType::Wpkh
+            | DescriptorType::ShWpkh
+            | DescriptorType::Tr => {}
+            other => return Err(format!("unsupported descriptor type {other:?}").into()),
+        }
+        let network = descriptor_network(&descriptor);
+        let count = if descriptor.has_wildcard() { depth } else { 1 };
+
+        let mut addresses = Vec::new();
+        for single in descriptor.into_single_descriptors()? {
+            for index in 0..count {
+                let address = single.at_derivation_index(index)?.address(network)?;
+                addresses.push(address.to_string());
+            }
+        }
+        Ok(DescriptorWatch {
+            label: label
+                .map(str::to_owned)
+                .unwrap_or_else(|| line.rsplit('#').next().unwrap_or(line).to_owned()),
+            group: None,
+            addresses,
+        })
+    }
+
+    pub fn funded_count(&self, funded: &BTreeSet<String>) -> usize {
+        self.addresses
+            .iter()
+            .filter(|address| funded.contains(*address))
+            .count()
+    }
+}
+
+pub fn load_descriptors(path: &Path, depth: u32) -> Result<Vec<DescriptorWatch>, Box<dyn Error>> {
+    let file = File::open(path)?;
+    let mut descriptors = Vec::new();
+    let mut labels = BTreeSet::new();
+
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let at = || format!("{}:{}", path.display(), index + 1);
+        let (entry, label, group) =
+            split_columns(line).map_err(|error| format!("{error} at {}", at()))?;
+        let mut watch = DescriptorWatch::parse(entry, label, depth)
+            .map_err(|error| format!("invalid descriptor at {}: {error}", at()))?;
+        watch.group = group.map(str::to_owned);
+        if !labels.insert(watch.label.clone()) {
+            return Err(format!("duplicate descriptor label {:?} at {}", watch.label, at()).into());
+        }
+        descriptors.push(watch);
+    }
+
+    Ok(descriptors)
+}
+
+fn has_checksum(line: &str) -> bool {
+    line.rsplit_once('#')
+        .is_some_and(|(_, checksum)| checksum.len() == 8)
+}
+
+/// Extended keys carry their network; bare public keys do not and default to
+/// mainnet.
+fn descriptor_network(descriptor: &Descriptor<DescriptorPublicKey>) -> Network {
+    let testnet = descriptor.for_any_key(|key| match key {
+        DescriptorPublicKey::XPub(xpub) => xpub.xkey.network != Network::Bitcoin,
+        DescriptorPublicKey::MultiXPub(xpub) => xpub.xkey.network != Network::Bitcoin,
+        DescriptorPublicKey::Single(_) => false,
+    });
+    if testnet {
+        Network::Testnet
+    } else {
+        Network::Bitcoin
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // BIP84 and BIP49 account keys for the "abandon ... about" test mnemonic.
+    const WPKH: &str = "wpkh([73c5da0a/84'/0'/0']xpub6CatWdiZiodmUeTDp8LT5or8nmbKNcuyvz7WyksVFkKB4RHwCD3XyuvPEbvqAQY3rAPshWcMLoP2fMFMKHPJ4ZeZXYVUhLv1VMrjPC7PW6V/0/*)#wc3n3van";
+    const SH_WPKH: &str = "sh(wpkh([73c5da0a/49'/0'/0']xpub6C6nQwHaWbSrzs5tZ1q7m5R9cPK9eYpNMFesiXsYrgc1P8bvLLAet9JfHjYXKjToD8cBRswJXXbbFpXgwsswVPAZzKMa1jUp2kVkGVUaJa7/0/*))#gvfpdstz";
+    const MULTIPATH: &str = "wpkh([73c5da0a/84'/0'/0']xpub6CatWdiZiodmUeTDp8LT5or8nmbKNcuyvz7WyksVFkKB4RHwCD3XyuvPEbvqAQY3rAPshWcMLoP2fMFMKHPJ4ZeZXYVUhLv1VMrjPC7PW6V/<0;1>/*)#hpg6d6w2";
+    const TR: &str = "tr(xpub6CatWdiZiodmUeTDp8LT5or8nmbKNcuyvz7WyksVFkKB4RHwCD3XyuvPEbvqAQY3rAPshWcMLoP2fMFMKHPJ4ZeZXYVUhLv1VMrjPC7PW6V/1/*)#xqm7mqdl";
+
+    #[test]
+    fn expands_ranged_descriptors_to_depth() {
+        let wpkh = DescriptorWatch::parse(WPKH, Some("savings"), 5).unwrap();
+        assert_eq!(wpkh.addresses.len(), 5);
+        assert_eq!(
+            wpkh.addresses[0],
+            "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu"
+        );
+        let sh = DescriptorWatch::parse(SH_WPKH, None, 1).unwrap();
+        assert_eq!(sh.addresses, ["37VucYSaXLCAsxYyAPfbSi9eh4iEcbShgf"]);
+        assert_eq!(sh.label, "gvfpdstz");
+        let tr = DescriptorWatch::parse(TR, None, 1).unwrap();
+        assert_eq!(
+            tr.addresses,
+            ["bc1pvccnny70dq8hhdu8qen0vkcenwlhxfqvgpzuqh792lhlycj3kzusrap9dt"]
+        );
+    }
+
+    #[test]
+    fn multipath_descriptors_cover_both_chains() {
+        let watch = DescriptorWatch::parse(MULTIPATH, None, 2).unwrap();
+        assert_eq!(watch.addresses.len(), 4);
+        assert_eq!(
+            watch.addresses[2],
+            "bc1q8c6fshw2dlwun7ekn9qwf37cu2rn755upcp6el"
+        );
+    }
+
+    #[test]
+    fn checksum_is_required_and_verified() {
+        let bare = WPKH.split('#').next().unwrap();
+        assert!(DescriptorWatch::parse(bare, None, 1).is_err());
+        let corrupted = WPKH.replace("#wc3n3van", "#wc3n3vam");
+        assert!(DescriptorWatch::parse(&corrupted, None, 1).is_err());
+    }
+}
@@ -0,0 +1,306 @@
+//! Esplora REST backend (Blockstream Esplora, mempool.space).
+//!
+//! `/address/:addr` gives the confirmed and mempool split for every watched
+//! address; `/address/:addr/utxo` is then fetched for the non-empty ones and
+//! its sum is the reported balance. Public instances throttle hard, so
+//! requests go through a shared rate limiter, a fixed pool of workers, and
+//! retry with backoff on 429, 5xx and transport errors.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use plutus_rustus::status::unix_now;
+use reqwest::blocking::Client;
+use reqwest::StatusCode;
+use serde_json::Value;
+
+use crate::backend::{Backend, BackendOptions, Scan};
+
+pub const DEFAULT_CONCURRENCY: usize = 4;
+pub const DEFAULT_REQUESTS_PER_SECOND: f64 = 10.0;
+const ATTEMPTS: u32 = 4;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub struct EsploraBackend {
+    base: String,
+    client: Client,
+    concurrency: usize,
+    limiter: RateLimiter,
+    /// First retry delay; doubles on each further attempt.
+    backoff: Duration,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct AddressBalance {
+    confirmed: u64,
+    /// Net effect of unconfirmed transactions; negative while a spend waits.
+    mempool: i64,
+}
+
+impl EsploraBackend {
+    pub fn new(base: &str, options: &BackendOptions) -> Result<Self, Box<dyn Error>> {
+        let concurrency = options.esplora_concurrency.unwrap_or(DEFAULT_CONCURRENCY);
+        let rate = options
+            .esplora_requests_per_second
+            .unwrap_or(DEFAULT_REQUESTS_PER_SECOND);
+        if concurrency == 0 || !rate.is_finite() || rate <= 0.0 {
+            return Err("esplora concurrency and request rate must be positive".into());
+        }
+        Ok(EsploraBackend {
+            base: base.trim_end_matches('/').to_owned(),
+            client: Client::builder().timeout(REQUEST_TIMEOUT).build()?,
+            concurrency,
+            limiter: RateLimiter::new(rate),
+            backoff: Duration::from_millis(500),
+        })
+    }
+
+    fn get_json(&self, path: &str) -> Result<Value, String> {
+        let url = format!("{}{path}", self.base);
+        let mut delay = self.backoff;
+        for attempt in 1..=ATTEMPTS {
+            self.limiter.acquire();
+            let retry_after = match self.client.get(&url).send() {
+                Ok(response) if response.status().is_success() => {
+                    let body = response.bytes().map_err(|e| format!("{url}: {e}"))?;
+                    return serde_json::from_slice(&body).map_err(|e| format!("{url}: {e}"));
+                }
+                Ok(response)
+                    if response.status() == StatusCode::TOO_MANY_REQUESTS
+                        || response.status().is_server_error() =>
+                {
+                    if attempt == ATTEMPTS {
+                        return Err(format!("{url}: HTTP {}", response.status()));
+                    }
+                    response
+                        .headers()
+                        .get("retry-after")
+                        .and_then(|value| value.to_str().ok())
+                        .and_then(|value| value.parse().ok())
+                        .map(Duration::from_secs)
+                }
+                Ok(response) => return Err(format!("{url}: HTTP {}", response.status())),
+                Err(error) if attempt == ATTEMPTS => return Err(format!("{url}: {error}")),
+                Err(_) => None,
+            };
+            thread::sleep(retry_after.unwrap_or(delay));
+            delay *= 2;
+        }
+        unreachable!("the last attempt always returns")
+    }
+
+    fn address_balance(&self, address: &str) -> Result<AddressBalance, String> {
+        let stats = self.get_json(&format!("/address/{address}"))?;
+        let net = |section: &str| -> Result<i64, String> {
+            let field = |name: &str| {
+                stats[section][name]
+                    .as_i64()
+                    .ok_or_else(|| format!("/address/{address}: missing {section}.{name}"))
+            };
+            Ok(field("funded_txo_sum")? - field("spent_txo_sum")?)
+        };
+        let confirmed = net("chain_stats")?.max(0) as u64;
+        let mempool = net("mempool_stats")?;
+        if confirmed == 0 && mempool <= 0 {
+            return Ok(AddressBalance { confirmed, mempool });
+        }
+
+        let utxos = self.get_json(&format!("/address/{address}/utxo"))?;
+        let total: u64 = utxos
+            .as_array()
+            .ok_or_else(|| format!("/address/{address}/utxo: expected a list"))?
+            .iter()
+            .filter_map(|utxo| utxo["value"].as_u64())
+            .sum();
+        Ok(AddressBalance {
+            confirmed,
+            mempool: total as i64 - confirmed as i64,
+        })
+    }
+}
+
+impl Backend for EsploraBackend {
+    fn scan(&mut self, watched: &BTreeSet<String>) -> Result<Scan, Box<dyn Error>> {
+        let addresses: Vec<&String> = watched.iter().collect();
+        let next = AtomicUsize::new(0);
+        let results = Mutex::new(BTreeMap::new());
+        let failure = Mutex::new(None);
+
+        thread::scope(|scope| {
+            for _ in 0..self.concurrency.min(addresses.len()) {
+                scope.spawn(|| loop {
+                    if failure.lock().unwrap().is_some() {
+                        return;
+                    }
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(address) = addresses.get(index) else {
+                        return;
+                    };
+                    match self.address_balance(address) {
+                        Ok(balance) => {
+                            results.lock().unwrap().insert((*address).clone(), balance);
+                        }
+                        Err(error) => {
+                            failure.lock().unwrap().get_or_insert(error);
+                            return;
+                        }
+                    }
+                });
+            }
+        });
+        if let Some(error) = failure.into_inner().unwrap() {
+            return Err(error.into());
+        }
+
+        let mut balances = BTreeMap::new();
+        let mut mempool = BTreeMap::new();
+        for (address, balance) in results.into_inner().unwrap() {
+            let total = (balance.confirmed as i64 + balance.mempool).max(0) as u64;
+            if total > 0 {
+                balances.insert(address.clone(), total);
+            }
+            if balance.mempool != 0 {
+                mempool.insert(address, balance.mempool);
+            }
+        }
+        Ok(Scan {
+            source: format!("esplora {}", self.base),
+            rows_scanned: None,
+            age_seconds: None,
+            observed_unix: unix_now(),
+            balances,
+            mempool: Some(mempool),
+            presence_only: false,
+            unpriced: BTreeSet::new(),
+            unmatchable: 0,
+            consensus: None,
+        })
+    }
+}
+
+/// Spaces requests evenly at `rate` per second across all workers.
+struct RateLimiter {
+    spacing: Duration,
+    next: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    fn new(rate: f64) -> Self {
+        RateLimiter {
+            spacing: Duration::from_secs_f64(1.0 / rate),
+            next: Mutex::new(Instant::now()),
+        }
+    }
+
+    fn acquire(&self) {
+        let slot = {
+            let mut next = self.next.lock().unwrap();
+            let slot = (*next).max(Instant::now());
+            *next = slot + self.spacing;
+            slot
+        };
+        let now = Instant::now();
+        if slot > now {
+            thread::sleep(slot - now);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_http;
+    use serde_json::json;
+
+    const FUNDED: &str = "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH";
+    const SPENDING: &str = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4";
+    const EMPTY: &str = "3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy";
+
+    fn stats(chain: (i64, i64), mempool: (i64, i64)) -> String {
+        json!({
+            "chain_stats": {"funded_txo_sum": chain.0, "spent_txo_sum": chain.1},
+            "mempool_stats": {"funded_txo_sum": mempool.0, "spent_txo_sum": mempool.1},
+        })
+        .to_string()
+    }
+
+    /// FUNDED holds 7000 confirmed plus 500 incoming; SPENDING has 4000
+    /// confirmed of which 4000 is being spent. The first request of all is
+    /// answered 429 to exercise retry.
+    fn stand_in() -> String {
+        let requests = AtomicUsize::new(0);
+        test_http::serve(move |request| {
+            if requests.fetch_add(1, Ordering::SeqCst) == 0 {
+                return (429, String::new());
+            }
+            assert_eq!(request.method, "GET");
+            let path = request.path.as_str();
+            let body = match path.strip_prefix("/api/address/") {
+                Some(FUNDED) => stats((10_000, 3_000), (500, 0)),
+                Some(SPENDING) => stats((4_000, 0), (0, 4_000)),
+                Some(EMPTY) => stats((0, 0), (0, 0)),
+                Some(rest) if rest == format!("{FUNDED}/utxo") => {
+                    json!([{"value": 7_000, "status": {"confirmed": true}},
+                           {"value": 500, "status": {"confirmed": false}}])
+                    .to_string()
+                }
+                Some(rest) if rest == format!("{SPENDING}/utxo") => "[]".to_owned(),
+                _ => return (404, String::new()),
+            };
+            (200, body)
+        })
+    }
+
+    fn backend(base: &str, rate: f64) -> EsploraBackend {
+        let options = BackendOptions {
+            esplora_concurrency: Some(2),
+            esplora_requests_per_second: Some(rate),
+            ..BackendOptions::default()
+        };
+        let mut backend = EsploraBackend::new(&format!("{base}/api"), &options).unwrap();
+        backend.backoff = Duration::from_millis(10);
+        backend
+    }
+
+    #[test]
+    fn reports_confirmed_and_mempool_separately() {
+        let base = stand_in();
+        let mut backend = backend(&base, 1_000.0);
+        let watched = [FUNDED, SPENDING, EMPTY].map(String::from).into();
+
+        let scan = backend.scan(&watched).unwrap();
+        assert_eq!(
+            scan.balances.into_iter().collect::<Vec<_>>(),
+            [(FUNDED.to_owned(), 7_500)]
+        );
+        assert_eq!(
+            scan.mempool.unwrap().into_iter().collect::<Vec<_>>(),
+            [(FUNDED.to_owned(), 500), (SPENDING.to_owned(), -4_000)]
+        );
+    }
+
+    #[test]
+    fn requests_are_rate_limited() {
+        let base = test_http::serve(|_| (200, stats((0, 0), (0, 0))));
+        let mut backend = backend(&base, 20.0);
+        let watched: BTreeSet<String> = [FUNDED, SPENDING, EMPTY].map(String::from).into();
+
+        let started = Instant::now();
+        backend.scan(&watched).unwrap();
+        // Three requests at 20/s need at least two 50 ms gaps.
+        assert!(started.elapsed() >= Duration::from_millis(100));
+    }
+
+    #[test]
+    fn gives_up_after_repeated_failures() {
+        let base = test_http::serve(|_| (503, String::new()));
+        let mut backend = backend(&base, 1_000.0);
+        let watched: BTreeSet<String> = [FUNDED.to_owned()].into();
+        let error = backend.scan(&watched).unwrap_err();
+        assert!(error.to_string().contains("503"));
+    }
+}
@@ -0,0 +1,474 @@
+//! Funded timelines from archived snapshots.
+//!
+//! `history build <dir>` reads every dump (`.tsv` or `.txt`, optionally
+//! compressed, or a `.h160` snapshot) whose file name carries a date
+//! (`2026-03-17`, `2026_03_17` or `20260317`), oldest first, and records for
+//! each watched address the runs of consecutive dump dates that listed it as
+//! funded. Several files from one date count as a single dump. Dumps are
+//! taken on a schedule, not on every block, so each run also notes the
+//! neighbouring dumps where the address was absent: the funding and the spend
+//! happened somewhere in between.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use plutus_rustus::status::{self, unix_now};
+use serde::{Deserialize, Serialize};
+
+use crate::backend::{Backend, SnapshotBackend};
+use crate::watchlist::Watchlist;
+
+pub const HISTORY_FILE: &str = "watch-history.json";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct History {
+    pub built_unix: u64,
+    pub dumps: Vec<Dump>,
+    pub labels: BTreeMap<String, String>,
+    /// Funded runs per watched address; never-funded addresses map to `[]`.
+    pub addresses: BTreeMap<String, Vec<Interval>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Dump {
+    /// `YYYY-MM-DD`, from the file names.
+    pub date: String,
+    /// Every file carrying that date; an address listed by any of them
+    /// counts as funded on it.
+    #[serde(default)]
+    pub paths: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Interval {
+    /// Latest earlier dump that did not list the address.
+    pub absent_before: Option<String>,
+    pub first_seen: String,
+    pub last_seen: String,
+    /// First later dump without the address; `None` while the newest dump
+    /// still lists it.
+    pub absent_after: Option<String>,
+    /// The balance at `first_seen` and at every dump where it changed.
+    /// Dumps that only record presence, such as `.h160` snapshots, add none.
+    pub balances: Vec<(String, u64)>,
+}
+
+impl Interval {
+    /// Date of the last dump that shows this run changing.
+    fn last_change(&self) -> &str {
+        self.absent_after
+            .as_deref()
+            .or_else(|| self.balances.last().map(|(date, _)| date.as_str()))
+            .unwrap_or(&self.first_seen)
+    }
+}
+
+pub fn history_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(HISTORY_FILE)
+}
+
+/// Scan every dated dump under `dir` for the watched addresses. Whenever an
+/// xpub window grows, only the newly derived addresses are matched against
+/// the dumps again.
+pub fn build(dir: &Path, watchlist: &mut Watchlist, gap: u32) -> Result<History, Box<dyn Error>> {
+    let dumps = dated_dumps(dir)?;
+    if dumps.is_empty() {
+        return Err(format!("no dated snapshots under {}", dir.display()).into());
+    }
+    // Per dump: listed addresses, with the amount when the file has one.
+    let mut observed: Vec<BTreeMap<String, Option<u64>>> = vec![BTreeMap::new(); dumps.len()];
+    let mut funded = BTreeSet::new();
+    let mut pending = watchlist.all_addresses();
+    while !pending.is_empty() {
+        for (dump, listed) in dumps.iter().zip(&mut observed) {
+            for path in &dump.paths {
+                let scan = SnapshotBackend::new(path).scan(&pending)?;
+                for (address, sats) in scan.balances {
+                    let sats = (!scan.unpriced.contains(&address)).then_some(sats);
+                    let amount = listed.entry(address.clone()).or_insert(sats);
+                    if amount.is_none() {
+                        *amount = sats;
+                    }
+                    funded.insert(address);
+                }
+            }
+        }
+        let before = watchlist.all_addresses();
+        if !watchlist.extend_for_funded(&funded, gap)? {
+            break;
+        }
+        pending = &watchlist.all_addresses() - &before;
+    }
+
+    let watched = watchlist.all_addresses();
+    let mut addresses = BTreeMap::new();
+    for address in &watched {
+        let listed = observed.iter().map(|listed| listed.get(address).copied());
+        addresses.insert(address.clone(), intervals(&dumps, listed));
+    }
+    let labels = watched
+        .iter()
+        .filter_map(|a| Some((a.clone(), watchlist.label_of(a)?.to_owned())))
+        .collect();
+    Ok(History {
+        built_unix: unix_now(),
+        dumps,
+        labels,
+        addresses,
+    })
+}
+
+/// `listed` has one entry per dump: `None` when the address is absent,
+/// `Some(None)` when it is listed without an amount.
+fn intervals(dumps: &[Dump], listed: impl Iterator<Item = Option<Option<u64>>>) -> Vec<Interval> {
+    let mut runs: Vec<Interval> = Vec::new();
+    let mut open = false;
+    let mut previous: Option<&str> = None;
+    for (dump, amount) in dumps.iter().zip(listed) {
+        let date = dump.date.clone();
+        match (amount, open) {
+            (Some(sats), false) => {
+                runs.push(Interval {
+                    absent_before: previous.map(str::to_owned),
+                    first_seen: date.clone(),
+                    last_seen: date.clone(),
+                    absent_after: None,
+                    balances: sats.map(|sats| (date, sats)).into_iter().collect(),
+                });
+                open = true;
+            }
+            (Some(sats), true) => {
+                let run = runs.last_mut().unwrap();
+                run.last_seen = date.clone();
+                if let Some(sats) = sats {
+                    if run.balances.last().map(|(_, last)| *last) != Some(sats) {
+                        run.balances.push((date, sats));
+                    }
+                }
+            }
+            (None, true) => {
+                runs.last_mut().unwrap().absent_after = Some(date);
+                open = false;
+            }
+            (None, false) => {}
+        }
+        previous = Some(&dump.date);
+    }
+    runs
+}
+
+/// Dated dump files under `dir`, recursively, grouped by date, oldest first.
+/// Anything that is not a dump, such as checksums or a saved history, is
+/// ignored.
+fn dated_dumps(dir: &Path) -> Result<Vec<Dump>, Box<dyn Error>> {
+    let mut pending = vec![dir.to_path_buf()];
+    let mut files = Vec::new();
+    while let Some(dir) = pending.pop() {
+        for entry in fs::read_dir(&dir).map_err(|e| format!("{}: {e}", dir.display()))? {
+            let path = entry?.path();
+            if path.is_dir() {
+                pending.push(path);
+                continue;
+            }
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            if !is_dump(&name) {
+                continue;
+            }
+            match date_in_name(&name) {
+                Some(date) => files.push((date, path)),
+                None => eprintln!("plutus-watch: {} has no date, skipped", path.display()),
+            }
+        }
+    }
+    files.sort();
+    let mut dumps: Vec<Dump> = Vec::new();
+    for (date, path) in files {
+        match dumps.last_mut() {
+            Some(dump) if dump.date == date => dump.paths.push(path),
+            _ => dumps.push(Dump {
+                date,
+                paths: vec![path],
+            }),
+        }
+    }
+    Ok(dumps)
+}
+
+/// `.tsv` or `.txt`, optionally `.gz`, `.zst`, `.xz` or `.bz2`, or `.h160`.
+fn is_dump(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    let plain = [".gz", ".zst", ".xz", ".bz2"]
+        .iter()
+        .find_map(|suffix| name.strip_suffix(suffix))
+        .unwrap_or(&name);
+    plain.ends_with(".tsv") || plain.ends_with(".txt") || name.ends_with(".h160")
+}
+
+/// The first `YYYY-MM-DD`, `YYYY_MM_DD` or `YYYYMMDD` in `name`.
+fn date_in_name(name: &str) -> Option<String> {
+    let bytes = name.as_bytes();
+    let digits = |from: usize, len: usize| -> Option<u32> {
+        let part = bytes.get(from..from + len)?;
+        part.iter()
+            .all(u8::is_ascii_digit)
+            .then(|| std::str::from_utf8(part).ok()?.parse().ok())?
+    };
+    for start in 0..bytes.len() {
+        if start > 0 && bytes[start - 1].is_ascii_digit() {
+            continue;
+        }
+        let Some(year) = digits(start, 4).filter(|y| (2009..=2199).contains(y)) else {
+            continue;
+        };
+        let separated = matches!(bytes.get(start + 4), Some(b'-' | b'_'))
+            && bytes.get(start + 7) == bytes.get(start + 4);
+        let (month_at, day_at, end) = if separated {
+            (start + 5, start + 8, start + 10)
+        } else {
+            (start + 4, start + 6, start + 8)
+        };
+        let (Some(month), Some(day)) = (digits(month_at, 2), digits(day_at, 2)) else {
+            continue;
+        };
+        if bytes.get(end).is_some_and(u8::is_ascii_digit) {
+            continue;
+        }
+        if (1..=12).contains(&month) && (1..=31).contains(&day) {
+            return Some(format!("{year:04}-{month:02}-{day:02}"));
+        }
+    }
+    None
+}
+
+pub fn save(history: &History, path: &Path) -> Result<(), Box<dyn Error>> {
+    status::write_atomic(path, history)?;
+    Ok(())
+}
+
+pub fn load(path: &Path) -> Result<History, Box<dyn Error>> {
+    let body = fs::read(path).map_err(|error| {
+        format!(
+            "{}: {error}; run `plutus-watch history build <dir>` first",
+            path.display()
+        )
+    })?;
+    Ok(serde_json::from_slice(&body)?)
+}
+
+/// One summary line per address matching `target` (an address or a label),
+/// then one line per funded run.
+pub fn show(history: &History, target: &str) -> Result<String, Box<dyn Error>> {
+    let addresses: Vec<&String> = if history.addresses.contains_key(target) {
+        history.addresses.keys().filter(|a| *a == target).collect()
+    } else {
+        let labelled: BTreeSet<&String> = history
+            .labels
+            .iter()
+            .filter(|(_, label)| *label == target)
+            .map(|(address, _)| address)
+            .collect();
+        labelled.into_iter().collect()
+    };
+    if addresses.is_empty() {
+        return Err(format!("{target:?} is not a watched address or label in the history").into());
+    }
+    let (Some(first), Some(last)) = (history.dumps.first(), history.dumps.last()) else {
+        return Err("history has no dumps".into());
+    };
+
+    let mut lines = Vec::new();
+    for address in addresses {
+        let runs = &history.addresses[address];
+        let mut summary = format!("address={address}");
+        if let Some(label) = history.labels.get(address) {
+            summary.push_str(&format!(" label={label}"));
+        }
+        summary.push_str(&format!(
+            " dumps={}..{} intervals={}",
+            first.date,
+            last.date,
+            runs.len()
+        ));
+        if let Some(touched) = runs.iter().map(Interval::last_change).max() {
+            summary.push_str(&format!(" last_touched={touched}"));
+        }
+        lines.push(summary);
+        for run in runs {
+            let mut line = format!("  funded={}..{}", run.first_seen, run.last_seen);
+            if let Some(before) = &run.absent_before {
+                line.push_str(&format!(" absent_before={before}"));
+            }
+            match &run.absent_after {
+                Some(after) => line.push_str(&format!(" absent_after={after}")),
+                None => line.push_str(" still_funded=true"),
+            }
+            let balances: Vec<_> = run
+                .balances
+                .iter()
+                .map(|(date, sats)| format!("{date}:{sats}"))
+                .collect();
+            if !balances.is_empty() {
+                line.push_str(&format!(" balances={}", balances.join(",")));
+            }
+            lines.push(line);
+        }
+    }
+    Ok(lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::unique_dir;
+    use crate::watchlist::XpubWatch;
+    use plutus_rustus::{chain, db};
+
+    const COLD: &str = "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH";
+    const HOT: &str = "3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy";
+    // BIP84 account key of the "abandon ... about" test mnemonic.
+    const ZPUB: &str = "zpub6rFR7y4Q2AijBEqTUquhVz398htDFrtymD9xYYfG1m4wAcvPhXNfE3EfH1r1ADqtfSdVCToUG868RvUUkgDKf31mGDtKsAYz2oz2AGutZYs";
+
+    #[test]
+    fn dates_come_from_file_names() {
+        assert_eq!(
+            date_in_name("Bitcoin_addresses_2026-03-17.tsv.gz").as_deref(),
+            Some("2026-03-17")
+        );
+        assert_eq!(
+            date_in_name("dump_20260317.tsv").as_deref(),
+            Some("2026-03-17")
+        );
+        assert_eq!(
+            date_in_name("v2_2026_01_05.txt").as_deref(),
+            Some("2026-01-05")
+        );
+        assert_eq!(date_in_name("Bitcoin_addresses_LATEST.txt.gz"), None);
+        assert_eq!(date_in_name("block-202603170.tsv"), None);
+    }
+
+    #[test]
+    fn only_dump_files_count() {
+        assert!(is_dump("Bitcoin_addresses_2026-03-17.tsv.gz"));
+        assert!(is_dump("dump_20260317.TXT.zst"));
+        assert!(is_dump("addresses-2026-03-17.h160"));
+        assert!(!is_dump("Bitcoin_addresses_2026-03-17.tsv.gz.sha256"));
+        assert!(!is_dump("2026-03-17.json"));
+        assert!(!is_dump("addresses-2026-03-17.h160.gz"));
+    }
+
+    #[test]
+    fn builds_funded_intervals_across_dumps() {
+        let dir = unique_dir("plutus-watch-history");
+        fs::create_dir_all(dir.join("2026")).unwrap();
+        let dumps = [
+            ("2026-01-01.tsv", vec![]),
+            ("2026-01-08.tsv", vec![(COLD, 5_000)]),
+            ("2026/2026-01-15.tsv", vec![(COLD, 5_000), (HOT, 10)]),
+            ("2026-01-22.tsv", vec![(COLD, 4_000)]),
+            ("2026-01-29.tsv", vec![(HOT, 20)]),
+        ];
+        for (name, rows) in &dumps {
+            let mut body = "address\tbalance\n".to_owned();
+            for (address, sats) in rows {
+                body.push_str(&format!("{address}\t{sats}\n"));
+            }
+            fs::write(dir.join(name), body).unwrap();
+        }
+        let mut watchlist = Watchlist {
+            addresses: [COLD, HOT].map(String::from).into(),
+            labels: [(COLD.to_owned(), "cold".to_owned())].into(),
+            ..Watchlist::default()
+        };
+
+        let history = build(&dir, &mut watchlist, 20).unwrap();
+        assert_eq!(history.dumps.len(), 5);
+        assert_eq!(
+            history.addresses[COLD],
+            [Interval {
+                absent_before: Some("2026-01-01".to_owned()),
+                first_seen: "2026-01-08".to_owned(),
+                last_seen: "2026-01-22".to_owned(),
+                absent_after: Some("2026-01-29".to_owned()),
+                balances: vec![
+                    ("2026-01-08".to_owned(), 5_000),
+                    ("2026-01-22".to_owned(), 4_000)
+                ],
+            }]
+        );
+        assert_eq!(history.addresses[HOT].len(), 2);
+        assert_eq!(history.addresses[HOT][1].absent_after, None);
+
+        let path = history_path(&dir);
+        save(&history, &path).unwrap();
+        let shown = show(&load(&path).unwrap(), "cold").unwrap();
+        assert_eq!(
+            shown,
+            format!(
+                "address={COLD} label=cold dumps=2026-01-01..2026-01-29 intervals=1 \
+                 last_touched=2026-01-29\n  funded=2026-01-08..2026-01-22 \
+                 absent_before=2026-01-01 absent_after=2026-01-29 \
+                 balances=2026-01-08:5000,2026-01-22:4000"
+            )
+        );
+        assert!(show(&history, "nobody").is_err());
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn one_date_is_one_dump_and_presence_adds_no_balance() {
+        let dir = unique_dir("plutus-watch-history-dates");
+        // Index 3 is derived only after index 1 turns up funded, and must
+        // then still be matched against the first dump.
+        let xpub = XpubWatch::parse(ZPUB, None, 4).unwrap();
+        let (near, far) = (xpub.derived[0][1].clone(), xpub.derived[0][3].clone());
+        fs::write(
+            dir.join("2026-02-01.tsv"),
+            format!("address\tbalance\n{COLD}\t500\n{near}\t1\n{far}\t7\n"),
+        )
+        .unwrap();
+        fs::write(dir.join("2026-02-02.tsv"), format!("{far}\t7\n")).unwrap();
+        fs::write(dir.join("2026-02-02.tsv.sha256"), "not a dump\n").unwrap();
+        let cold = chain::BITCOIN.hash160(COLD).unwrap();
+        db::write_snapshot(
+            &dir.join("2026-02-02.h160"),
+            &db::Db::Sorted(vec![cold]),
+            16,
+            chain::BITCOIN,
+        )
+        .unwrap();
+
+        let mut watchlist = Watchlist {
+            addresses: [COLD.to_owned()].into(),
+            xpubs: vec![XpubWatch::parse(ZPUB, None, 2).unwrap()],
+            ..Watchlist::default()
+        };
+        let history = build(&dir, &mut watchlist, 2).unwrap();
+        assert_eq!(
+            history
+                .dumps
+                .iter()
+                .map(|d| d.paths.len())
+                .collect::<Vec<_>>(),
+            [1, 2]
+        );
+        assert_eq!(
+            history.addresses[COLD],
+            [Interval {
+                absent_before: None,
+                first_seen: "2026-02-01".to_owned(),
+                last_seen: "2026-02-02".to_owned(),
+                absent_after: None,
+                balances: vec![("2026-02-01".to_owned(), 500)],
+            }]
+        );
+        let far_run = &history.addresses[&far][0];
+        assert_eq!(
+            (far_run.first_seen.as_str(), far_run.last_seen.as_str()),
+            ("2026-02-01", "2026-02-02")
+        );
+        assert_eq!(watchlist.xpubs[0].derived[0].len(), 6);
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,664 @@
+//! Watch-only funded-address snapshot monitor.
+//!
+//! This binary deliberately operates only on a caller-supplied watchlist and a
+//! local TSV snapshot or a live backend. It does not generate, import,
+//! transmit, or act on private keys; extended keys in the watchlist must be
+//! public (xpub family).
+//!
+//! Settings come from the `[watch]` section of the shared config file, with
+//! flags taking precedence; notifications go through the same providers as
+//! `plutus-rustus`.
+
+mod amount;
+mod backend;
+mod bitcoind;
+mod consensus;
+mod descriptor;
+mod electrum;
+mod esplora;
+mod history;
+mod report;
+mod rules;
+mod state;
+#[cfg(test)]
+mod test_http;
+#[cfg(test)]
+#[path = "../../test_util.rs"]
+mod test_util;
+mod watchlist;
+
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+
+use clap::{Parser, Subcommand};
+
+use plutus_rustus::config::{self, Config};
+use plutus_rustus::notify::Notifier;
+use plutus_rustus::status;
+
+use backend::{Backend, BackendOptions, Scan};
+use consensus::Divergence;
+use descriptor::load_descriptors;
+use report::{Format, Report, DATA_ERROR, USAGE_ERROR};
+use rules::{evaluate, load_rules};
+use state::{state_path, WatchState};
+use watchlist::{load_watchlist, Watchlist};
+
+#[derive(Parser)]
+#[command(
+    name = "plutus-watch",
+    version,
+    about = "Watch-only monitor for a list of authorised addresses.",
+    after_help = "Flags override the [watch] section of the config file \
+                  (PLUTUS_CONFIG, default config.toml). Notifications use its \
+                  [notify] section; tokens are never printed."
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Addresses and xpubs to watch, one per line with an optional label.
+    #[arg(long, value_name = "FILE", global = true)]
+    watchlist: Option<PathBuf>,
+    /// Output descriptors with checksums, one per line.
+    #[arg(long, value_name = "FILE", global = true)]
+    descriptors: Option<PathBuf>,
+    /// Addresses expanded per ranged descriptor (default: 1000).
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..), global = true)]
+    descriptor_depth: Option<u32>,
+    /// Alert rules: `<address|label> below <amount>sat|btc` or `<address|label> outflow`.
+    /// Skipped, with a warning, while a source has no amounts for the target.
+    #[arg(long, value_name = "FILE")]
+    rules: Option<PathBuf>,
+    /// Local TSV dump, or the engine's PLH1/PLH2 hash160 snapshot for
+    /// presence-only checks (default: `data.snapshot`). Repeatable.
+    #[arg(long, value_name = "FILE")]
+    snapshot: Vec<PathBuf>,
+    /// electrum(s)://host:port, bitcoind[://host:port], esplora+https://host/api,
+    /// or a snapshot path.
+    /// Repeatable; with several sources, see --quorum.
+    #[arg(long, value_name = "URL")]
+    backend: Vec<String>,
+    /// Sources that must agree before an address counts as funded or empty
+    /// (default: a majority).
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    quorum: Option<u64>,
+    /// bitcoind cookie (default: ~/.bitcoin/.cookie).
+    #[arg(long, value_name = "FILE")]
+    rpc_cookie: Option<PathBuf>,
+    /// bitcoind user; the password comes from PLUTUS_RPC_PASSWORD.
+    #[arg(long, value_name = "USER")]
+    rpc_user: Option<String>,
+    /// Parallel requests for esplora backends (default: 4).
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    esplora_concurrency: Option<u64>,
+    /// Esplora requests per second (default: 10).
+    #[arg(long, value_name = "N", value_parser = positive_rate)]
+    esplora_rps: Option<f64>,
+    /// PEM certificates electrums:// trusts instead of the public roots,
+    /// such as a self-hosted server's self-signed certificate.
+    #[arg(long, value_name = "FILE")]
+    electrum_ca_file: Option<PathBuf>,
+    /// Where watch-state.json and watch-history.json are kept (default:
+    /// `data.dir`).
+    #[arg(long, value_name = "DIR", global = true)]
+    data_dir: Option<PathBuf>,
+    /// Heartbeat interval (default: 21600).
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    interval_seconds: Option<u64>,
+    /// Check once and exit: 0 no change, 10 funds appeared, 11 funds
+    /// disappeared, 2 data error, 64 usage error. Without saved state the
+    /// check only records a baseline and exits 0.
+    #[arg(long)]
+    once: bool,
+    /// Report format for stdout and --report-file.
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+    /// Also write each report here, replacing it atomically.
+    #[arg(long, value_name = "FILE")]
+    report_file: Option<PathBuf>,
+    /// Include changed addresses in stdout and notifications.
+    #[arg(long)]
+    include_addresses: bool,
+    /// Unused addresses scanned past the last funded one on each xpub chain
+    /// (default: 20).
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..), global = true)]
+    gap_limit: Option<u32>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Funded timelines built from archived dated snapshots.
+    History {
+        #[command(subcommand)]
+        command: HistoryCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum HistoryCommand {
+    /// Scan every dated dump under DIR and save the funded intervals of each
+    /// watched address to watch-history.json.
+    Build { dir: PathBuf },
+    /// Print the funded timeline of an address or label.
+    Show { target: String },
+}
+
+#[derive(Debug)]
+struct Options {
+    watchlist: Option<PathBuf>,
+    descriptors: Option<PathBuf>,
+    rules: Option<PathBuf>,
+    /// Snapshot paths or live backend URLs; see [`backend::open`].
+    sources: Vec<String>,
+    quorum: Option<usize>,
+    backend_options: BackendOptions,
+    data_dir: PathBuf,
+    interval: Duration,
+    once: bool,
+    format: Format,
+    report_file: Option<PathBuf>,
+    include_addresses: bool,
+    gap_limit: u32,
+    descriptor_depth: u32,
+}
+
+fn main() -> ExitCode {
+    let cli = match Cli::try_parse() {
+        Ok(cli) => cli,
+        Err(error) => {
+            let _ = error.print();
+            // --help and --version are not errors.
+            return ExitCode::from(if error.use_stderr() { USAGE_ERROR } else { 0 });
+        }
+    };
+    let cfg = match config::load() {
+        Ok(cfg) => cfg,
+        Err(error) => {
+            eprintln!("plutus-watch: {error}");
+            return ExitCode::from(DATA_ERROR);
+        }
+    };
+    match run(cli, &cfg) {
+        Ok(code) => ExitCode::from(code),
+        Err(error) => {
+            eprintln!("plutus-watch: {error}");
+            ExitCode::from(DATA_ERROR)
+        }
+    }
+}
+
+/// Returns the `--once` exit status; a watch loop only returns on error.
+fn run(mut cli: Cli, cfg: &Config) -> Result<u8, Box<dyn Error>> {
+    if let Some(Command::History { command }) = cli.command.take() {
+        history_command(command, cli, cfg)?;
+        return Ok(0);
+    }
+    let options = resolve(cli, cfg)?;
+    let mut watchlist = load_watched(&options)?;
+    let rules = match &options.rules {
+        Some(path) => load_rules(path, &watchlist)?,
+        None => Vec::new(),
+    };
+
+    let mut backend =
+        backend::open_all(&options.sources, options.quorum, &options.backend_options)?;
+    let notifier = Notifier::from_config(&cfg.notify);
+    let groups = watchlist.group_names();
+    let mut routes = Vec::new();
+    for (group, notify) in &cfg.watch.groups {
+        if groups.contains(group.as_str()) {
+            let notifier = Notifier::for_route(notify)
+                .map_err(|error| format!("[watch.groups.{group}]: {error}"))?;
+            routes.push((group.clone(), notifier));
+        } else {
+            eprintln!("plutus-watch: [watch.groups.{group}] matches no watchlist group");
+        }
+    }
+    let state_file = state_path(&options.data_dir);
+    // With nothing saved, the first check is the baseline: funds already
+    // there are recorded, not reported as having appeared.
+    let mut baseline = !state_file.exists();
+    let mut state = WatchState::load(&state_file)?;
+    let mut warned_unpriced = BTreeSet::new();
+
+    loop {
+        let mut scan = scan_with_gap(backend.as_mut(), &mut watchlist, options.gap_limit)?;
+        let watched = watchlist.all_addresses();
+        if std::mem::take(&mut baseline) {
+            eprintln!(
+                "plutus-watch: no {}, this check is the baseline",
+                state_file.display()
+            );
+            state.record(scan.observed_unix, &watched, &scan.balances);
+        }
+        let before = state.balances();
+        if let Some(consensus) = &scan.consensus {
+            for address in &consensus.undecided {
+                if let Some(&balance) = before.get(address) {
+                    scan.balances.insert(address.clone(), balance);
+                }
+            }
+        }
+        let previous: BTreeSet<String> = state.funded().intersection(&watched).cloned().collect();
+        let funded = scan.funded();
+        let added: Vec<_> = funded.difference(&previous).cloned().collect();
+        let removed: Vec<_> = previous.difference(&funded).cloned().collect();
+
+        let (alerts, skipped) =
+            evaluate(&rules, &watchlist, &before, &scan.balances, &scan.unpriced);
+        for rule in skipped {
+            if warned_unpriced.insert(rule.target.clone()) {
+                eprintln!(
+                    "plutus-watch: rules for {} skipped while {} reports presence, not amounts",
+                    rule.target, scan.source
+                );
+            }
+        }
+
+        let report = Report::build(
+            &scan,
+            &watchlist,
+            &before,
+            &added,
+            &removed,
+            &alerts,
+            options.include_addresses,
+        );
+        let rendered = report.render(options.format);
+        println!("{rendered}");
+        if let Some(path) = &options.report_file {
+            status::write_atomic_bytes(path, format!("{rendered}\n").as_bytes())
+                .map_err(|error| format!("{}: {error}", path.display()))?;
+        }
+        let divergence = scan
+            .consensus
+            .as_ref()
+            .map(|consensus| consensus.divergence.clone())
+            .unwrap_or_default();
+        let diverged = divergence_changed(&state.divergence, &divergence, &watched);
+        deliver(&notifier, None, &report, diverged);
+        for (group, notifier) in &routes {
+            let members = watchlist.subset(group);
+            let watched = members.all_addresses();
+            let diverged = divergence_changed(&state.divergence, &divergence, &watched);
+            let only = |addresses: &[String]| -> Vec<String> {
+                addresses
+                    .iter()
+                    .filter(|a| watched.contains(*a))
+                    .cloned()
+                    .collect()
+            };
+            let alerts: Vec<_> = alerts
+                .iter()
+                .filter(|alert| members.resolve(&alert.target).is_some())
+                .cloned()
+                .collect();
+            let report = Report::build(
+                &scan,
+                &members,
+                &before,
+                &only(&added),
+                &only(&removed),
+                &alerts,
+                options.include_addresses,
+            );
+            deliver(notifier, Some(group), &report, diverged);
+        }
+
+        state.record(scan.observed_unix, &watched, &scan.balances);
+        state.divergence = divergence;
+        state.save(&state_file)?;
+        if options.once {
+            return Ok(report.change.exit_code());
+        }
+        backend.wait(options.interval)?;
+    }
+}
+
+fn history_command(command: HistoryCommand, cli: Cli, cfg: &Config) -> Result<(), Box<dyn Error>> {
+    match command {
+        HistoryCommand::Build { dir } => {
+            let options = resolve(cli, cfg)?;
+            let mut watchlist = load_watched(&options)?;
+            let built = history::build(&dir, &mut watchlist, options.gap_limit)?;
+            let path = history::history_path(&options.data_dir);
+            history::save(&built, &path)?;
+            let funded = built.addresses.values().filter(|runs| !runs.is_empty());
+            println!(
+                "dumps={} watched={} ever_funded={} history={}",
+                built.dumps.len(),
+                built.addresses.len(),
+                funded.count(),
+                path.display()
+            );
+        }
+        HistoryCommand::Show { target } => {
+            let data_dir = cli.data_dir.unwrap_or_else(|| cfg.data_dir.clone());
+            let built = history::load(&history::history_path(&data_dir))?;
+            println!("{}", history::show(&built, &target)?);
+        }
+    }
+    Ok(())
+}
+
+fn load_watched(options: &Options) -> Result<Watchlist, Box<dyn Error>> {
+    let mut watchlist = match &options.watchlist {
+        Some(path) => load_watchlist(path, options.gap_limit)?,
+        None => Watchlist::default(),
+    };
+    if let Some(path) = &options.descriptors {
+        watchlist.descriptors = load_descriptors(path, options.descriptor_depth)?;
+    }
+    if watchlist.is_empty() {
+        return Err("watchlist contains no addresses".into());
+    }
+    Ok(watchlist)
+}
+
+/// Send `report` as a status, or as a time-sensitive alert when rules fired.
+/// Source disagreements follow as a separate event when `diverged` says they
+/// changed since the last check.
+/// `group` tags the title of copies routed to `[watch.groups.<name>]`.
+fn deliver(notifier: &Notifier, group: Option<&str>, report: &Report, diverged: bool) {
+    let message = report.render(Format::Text);
+    let scope = group.map(|g| format!(" [{g}]")).unwrap_or_default();
+    if report.alerts.is_empty() {
+        notifier.send(&format!("Plutus watch status{scope}"), &message);
+    } else if notifier.enabled() {
+        if let Err(error) =
+            notifier.send_watch_alert(&format!("Plutus watch alert{scope}"), &message)
+        {
+            eprintln!("notify failed: {error}");
+        }
+    }
+    if diverged && !report.divergence.is_empty() {
+        notifier.send(
+            &format!("Plutus watch source divergence{scope}"),
+            &format!(
+                "source={} undecided={} divergence={}",
+                report.source,
+                report.undecided,
+                report.divergence_summary()
+            ),
+        );
+    }
+}
+
+/// Whether the disagreements over `watched` differ from the last check's:
+/// which addresses, which sources on each side, and whether the amounts
+/// differ. A lagging source that stays behind is announced once.
+fn divergence_changed(
+    before: &[Divergence],
+    now: &[Divergence],
+    watched: &BTreeSet<String>,
+) -> bool {
+    let shape = |list: &[Divergence]| -> Vec<_> {
+        list.iter()
+            .filter(|d| watched.contains(&d.address))
+            .map(|d| {
+                (
+                    d.address.clone(),
+                    d.funded_by.clone(),
+                    d.empty_by.clone(),
+                    d.amounts_differ(),
+                )
+            })
+            .collect()
+    };
+    shape(before) != shape(now)
+}
+
+/// Merge flags over the `[watch]` config section.
+fn resolve(cli: Cli, cfg: &Config) -> Result<Options, Box<dyn Error>> {
+    let watch = &cfg.watch;
+    let watchlist = cli.watchlist.or_else(|| watch.watchlist.clone());
+    let descriptors = cli.descriptors.or_else(|| watch.descriptors.clone());
+    if watchlist.is_none() && descriptors.is_none() {
+        return Err(
+            "--watchlist or --descriptors is required; pass a list of authorised addresses".into(),
+        );
+    }
+    let mut sources: Vec<String> = cli
+        .snapshot
+        .iter()
+        .map(|path| path.display().to_string())
+        .chain(cli.backend)
+        .collect();
+    if sources.is_empty() {
+        sources = watch.backends.clone();
+    }
+    if sources.is_empty() {
+        sources.push(cfg.snapshot.display().to_string());
+    }
+
+    Ok(Options {
+        watchlist,
+        descriptors,
+        rules: cli.rules.or_else(|| watch.rules.clone()),
+        sources,
+        quorum: cli.quorum.map(|q| q as usize).or(watch.quorum),
+        backend_options: BackendOptions {
+            rpc_cookie: cli.rpc_cookie.or_else(|| watch.rpc_cookie.clone()),
+            rpc_user: cli.rpc_user.or_else(|| watch.rpc_user.clone()),
+            esplora_concurrency: Some(
+                cli.esplora_concurrency
+                    .map_or(watch.esplora_concurrency, |n| n as usize),
+            ),
+            esplora_requests_per_second: Some(
+                cli.esplora_rps.unwrap_or(watch.esplora_requests_per_second),
+            ),
+            electrum_ca_file: cli
+                .electrum_ca_file
+                .or_else(|| watch.electrum_ca_file.clone()),
+        },
+        data_dir: cli.data_dir.unwrap_or_else(|| cfg.data_dir.clone()),
+        interval: Duration::from_secs(cli.interval_seconds.unwrap_or(watch.interval_secs)),
+        once: cli.once,
+        format: cli.format,
+        report_file: cli.report_file,
+        include_addresses: cli.include_addresses || watch.include_addresses,
+        gap_limit: cli.gap_limit.unwrap_or(watch.gap_limit),
+        descriptor_depth: cli.descriptor_depth.unwrap_or(watch.descriptor_depth),
+    })
+}
+
+fn positive_rate(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(rate) if rate.is_finite() && rate > 0.0 => Ok(rate),
+        _ => Err("must be a number greater than zero".into()),
+    }
+}
+
+/// Scan the backend, deriving further xpub addresses whenever a funded one
+/// lands inside the gap window, until every chain ends in `gap` unused
+/// addresses. Each round asks the backend about the new addresses only.
+fn scan_with_gap(
+    backend: &mut dyn Backend,
+    watchlist: &mut Watchlist,
+    gap: u32,
+) -> Result<Scan, Box<dyn Error>> {
+    let mut scan = backend.scan(&watchlist.all_addresses())?;
+    loop {
+        let before = watchlist.all_addresses();
+        if !watchlist.extend_for_funded(&scan.funded(), gap)? {
+            return Ok(scan);
+        }
+        scan.merge(backend.scan(&(&watchlist.all_addresses() - &before))?);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn options_require_explicit_paths() {
+        let cfg = Config::default();
+        let parse = |args: &[&str]| {
+            let cli = Cli::try_parse_from(["plutus-watch"].iter().chain(args))?;
+            resolve(cli, &cfg)
+        };
+        assert!(parse(&[]).is_err());
+        assert!(parse(&["--watchlist", "w.txt", "--interval-seconds", "0"]).is_err());
+        let options = parse(&["--watchlist", "w.txt", "--snapshot", "s.tsv"]).unwrap();
+        assert_eq!(options.sources, ["s.tsv"]);
+        let options = parse(&[
+            "--watchlist",
+            "w.txt",
+            "--snapshot",
+            "s.tsv",
+            "--backend",
+            "bitcoind",
+            "--quorum",
+            "2",
+        ])
+        .unwrap();
+        assert_eq!(options.sources, ["s.tsv", "bitcoind"]);
+        assert_eq!(options.quorum, Some(2));
+        assert_eq!(options.data_dir, cfg.data_dir);
+        assert_eq!(options.gap_limit, cfg.watch.gap_limit);
+    }
+
+    #[test]
+    fn divergence_is_announced_when_it_changes() {
+        const A: &str = "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH";
+        const B: &str = "3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy";
+        let lagging = |address: &str, amount: u64| Divergence {
+            address: address.to_owned(),
+            funded_by: vec!["live".to_owned()],
+            empty_by: vec!["dump".to_owned()],
+            funded: None,
+            amounts: [("live".to_owned(), amount)].into(),
+        };
+        let all: BTreeSet<String> = [A, B].map(String::from).into();
+        let only_b: BTreeSet<String> = [B.to_owned()].into();
+
+        assert!(divergence_changed(&[], &[lagging(A, 5)], &all));
+        // Still behind, with a new live amount: already announced.
+        assert!(!divergence_changed(
+            &[lagging(A, 5)],
+            &[lagging(A, 7)],
+            &all
+        ));
+        assert!(!divergence_changed(&[], &[lagging(A, 5)], &only_b));
+        assert!(divergence_changed(
+            &[lagging(A, 5)],
+            &[lagging(A, 5), lagging(B, 1)],
+            &only_b
+        ));
+    }
+
+    #[test]
+    fn flags_override_watch_section() {
+        let mut cfg = Config::default();
+        cfg.watch.watchlist = Some(PathBuf::from("from-config.txt"));
+        cfg.watch.backends = vec!["electrum://127.0.0.1:50001".into()];
+        cfg.watch.gap_limit = 50;
+        let cli = Cli::try_parse_from(["plutus-watch", "--gap-limit", "5"]).unwrap();
+        let options = resolve(cli, &cfg).unwrap();
+        assert_eq!(options.watchlist, Some(PathBuf::from("from-config.txt")));
+        assert_eq!(options.sources, ["electrum://127.0.0.1:50001"]);
+        assert_eq!(options.gap_limit, 5);
+        assert_eq!(
+            options.interval,
+            Duration::from_secs(cfg.watch.interval_secs)
+        );
+    }
+
+    /// Reports balances from a fixed table and records every query's size.
+    struct Recording {
+        balances: BTreeMap<String, u64>,
+        asked: Vec<usize>,
+    }
+
+    impl Backend for Recording {
+        fn scan(&mut self, watched: &BTreeSet<String>) -> Result<Scan, Box<dyn Error>> {
+            self.asked.push(watched.len());
+            Ok(Scan {
+                source: "recording".to_owned(),
+                rows_scanned: None,
+                age_seconds: None,
+                observed_unix: 1,
+                balances: self
+                    .balances
+                    .iter()
+                    .filter(|(address, _)| watched.contains(*address))
+                    .map(|(address, sats)| (address.clone(), *sats))
+                    .collect(),
+                mempool: None,
+                presence_only: false,
+                unpriced: BTreeSet::new(),
+                unmatchable: 0,
+                consensus: None,
+            })
+        }
+    }
+
+    #[test]
+    fn gap_rounds_ask_only_about_new_addresses() {
+        // BIP84 account key of the "abandon ... about" test mnemonic.
+        let zpub = "zpub6rFR7y4Q2AijBEqTUquhVz398htDFrtymD9xYYfG1m4wAcvPhXNfE3EfH1r1ADqtfSdVCToUG868RvUUkgDKf31mGDtKsAYz2oz2AGutZYs";
+        let probe = watchlist::XpubWatch::parse(zpub, None, 8).unwrap();
+        let mut backend = Recording {
+            balances: [
+                (probe.derived[0][2].clone(), 5),
+                (probe.derived[0][6].clone(), 7),
+            ]
+            .into(),
+            asked: Vec::new(),
+        };
+        let mut watchlist = Watchlist {
+            xpubs: vec![watchlist::XpubWatch::parse(zpub, None, 4).unwrap()],
+            ..Watchlist::default()
+        };
+
+        let scan = scan_with_gap(&mut backend, &mut watchlist, 4).unwrap();
+        assert_eq!(scan.balances.len(), 2);
+        // Both chains up front, then receive 4..=6, then receive 7..=10.
+        assert_eq!(backend.asked, [8, 3, 4]);
+        assert_eq!(watchlist.xpubs[0].derived[0].len(), 11);
+    }
+
+    #[test]
+    fn first_once_check_is_a_baseline() {
+        const FUNDED: &str = "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH";
+        const LATER: &str = "3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy";
+        let dir = crate::test_util::unique_dir("plutus-watch-once");
+        let (list, dump, report) = (
+            dir.join("watchlist.txt"),
+            dir.join("funded.tsv"),
+            dir.join("report.txt"),
+        );
+        std::fs::write(&list, format!("{FUNDED}\n{LATER}\n")).unwrap();
+        let check = |rows: &str| {
+            std::fs::write(&dump, format!("address\tbalance\n{rows}")).unwrap();
+            let args = [
+                "--watchlist",
+                list.to_str().unwrap(),
+                "--snapshot",
+                dump.to_str().unwrap(),
+                "--data-dir",
+                dir.to_str().unwrap(),
+                "--report-file",
+                report.to_str().unwrap(),
+                "--once",
+            ];
+            let cli = Cli::try_parse_from(["plutus-watch"].iter().chain(&args)).unwrap();
+            run(cli, &Config::default()).unwrap()
+        };
+
+        assert_eq!(check(&format!("{FUNDED}\t500\n")), 0);
+        assert!(state_path(&dir).exists());
+        assert!(std::fs::read_to_string(&report)
+            .unwrap()
+            .contains("funded=1 added=0"));
+        assert_eq!(check(&format!("{FUNDED}\t500\n{LATER}\t100\n")), 10);
+        assert_eq!(check(&format!("{LATER}\t100\n")), 11);
+        assert_eq!(check(&format!("{LATER}\t100\n")), 0);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,608 @@
+//! One check's result, rendered for people (`text`) or tools (`json`, `csv`).
+//!
+//! The JSON field set and CSV columns are stable: fields a source cannot
+//! provide are `null` (JSON) or empty (CSV) rather than missing.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::amount::sum_balances;
+use crate::backend::Scan;
+use crate::consensus::Divergence;
+use crate::rules::Alert;
+use crate::watchlist::Watchlist;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// `key=value` pairs on one line.
+    Text,
+    /// One JSON object.
+    Json,
+    /// A header, a `total` row, then one row per xpub, descriptor or group.
+    Csv,
+}
+
+/// What happened to the watched funds since the previous check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Change {
+    None,
+    Appeared,
+    Disappeared,
+}
+
+impl Change {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Change::None => "none",
+            Change::Appeared => "appeared",
+            Change::Disappeared => "disappeared",
+        }
+    }
+
+    /// `--once` exit status. A check where some funds arrived and others
+    /// left counts as `Disappeared`, the one that needs attention.
+    pub fn exit_code(self) -> u8 {
+        match self {
+            Change::None => 0,
+            Change::Appeared => 10,
+            Change::Disappeared => 11,
+        }
+    }
+}
+
+/// Exit status for errors reading the watchlist, snapshot or backend.
+pub const DATA_ERROR: u8 = 2;
+/// Exit status for bad command-line usage (`EX_USAGE`), so it never reads as
+/// a data error.
+pub const USAGE_ERROR: u8 = 64;
+
+#[derive(Debug, Serialize)]
+pub struct Report {
+    /// `snapshot` or `backend`.
+    pub source_kind: &'static str,
+    pub source: String,
+    pub age_seconds: Option<u64>,
+    pub rows_scanned: Option<u64>,
+    pub observed_unix: u64,
+    pub change: Change,
+    pub watchlist: usize,
+    pub funded: usize,
+    pub added: usize,
+    pub removed: usize,
+    pub unmatchable: usize,
+    pub presence_only: bool,
+    /// `None` for presence-only sources.
+    pub balance_sats: Option<u64>,
+    pub delta_sats: Option<i64>,
+    /// Set only by backends that split confirmed and mempool funds.
+    pub confirmed_sats: Option<u64>,
+    pub mempool_sats: Option<i64>,
+    pub groups: Vec<Group>,
+    pub alerts: Vec<String>,
+    /// Labels of newly funded and emptied addresses, and the net change per
+    /// label. Unlabelled addresses are left out.
+    pub added_labels: Vec<String>,
+    pub removed_labels: Vec<String>,
+    pub label_deltas: BTreeMap<String, i64>,
+    /// Addresses short of a source quorum, kept at their previous state.
+    pub undecided: usize,
+    /// Source disagreements. The address is replaced by its label, or by
+    /// `address`, unless addresses were asked for.
+    pub divergence: Vec<Divergence>,
+    /// Only with `--include-addresses`.
+    pub addresses: Option<AddressDetail>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Group {
+    /// `xpub`, `descriptor`, or `group` for a watchlist group column.
+    pub kind: &'static str,
+    pub label: String,
+    pub funded: usize,
+    pub derived: usize,
+    pub balance_sats: Option<u64>,
+    pub delta_sats: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AddressDetail {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    /// Addresses whose balance changed; empty for presence-only sources.
+    pub balance_deltas: BTreeMap<String, i64>,
+}
+
+impl Report {
+    pub fn build(
+        scan: &Scan,
+        watchlist: &Watchlist,
+        before: &BTreeMap<String, u64>,
+        added: &[String],
+        removed: &[String],
+        alerts: &[Alert],
+        include_addresses: bool,
+    ) -> Report {
+        let watched = watchlist.all_addresses();
+        let funded = scan.funded();
+        let balances = !scan.presence_only;
+        let totals = |addresses: Vec<&String>| {
+            let was = sum_balances(before, addresses.iter().copied());
+            let now = sum_balances(&scan.balances, addresses.iter().copied());
+            (now, delta(was, now))
+        };
+
+        let mut deltas = BTreeMap::new();
+        for address in &watched {
+            let was = before.get(address).copied().unwrap_or(0);
+            let now = scan.balances.get(address).copied().unwrap_or(0);
+            if was != now {
+                deltas.insert(address.clone(), delta(was, now));
+            }
+        }
+        let change = if deltas.values().any(|&d| d < 0) {
+            Change::Disappeared
+        } else if deltas.values().any(|&d| d > 0) {
+            Change::Appeared
+        } else {
+            Change::None
+        };
+
+        let (now, total_delta) = totals(watched.iter().collect());
+        let pending = scan
+            .mempool
+            .as_ref()
+            .map(|mempool| watched.iter().filter_map(|a| mempool.get(a)).sum::<i64>());
+
+        let mut groups = Vec::new();
+        for xpub in &watchlist.xpubs {
+            let (now, delta) = totals(xpub.derived.iter().flatten().collect());
+            groups.push(Group {
+                kind: "xpub",
+                label: xpub.label.clone(),
+                funded: xpub.funded_count(&funded),
+                derived: xpub.derived_count(),
+                balance_sats: balances.then_some(now),
+                delta_sats: balances.then_some(delta),
+            });
+        }
+        for descriptor in &watchlist.descriptors {
+            let (now, delta) = totals(descriptor.addresses.iter().collect());
+            groups.push(Group {
+                kind: "descriptor",
+                label: descriptor.label.clone(),
+                funded: descriptor.funded_count(&funded),
+                derived: descriptor.addresses.len(),
+                balance_sats: balances.then_some(now),
+                delta_sats: balances.then_some(delta),
+            });
+        }
+
+        for group in watchlist.group_names() {
+            let members = watchlist.subset(group);
+            let addresses = members.all_addresses();
+            let (now, delta) = totals(addresses.iter().collect());
+            groups.push(Group {
+                kind: "group",
+                label: group.to_owned(),
+                funded: addresses.intersection(&funded).count(),
+                derived: addresses.len(),
+                balance_sats: balances.then_some(now),
+                delta_sats: balances.then_some(delta),
+            });
+        }
+
+        // Address targets stay private unless addresses were asked for; a
+        // labelled address shows its label instead.
+        let alerts = alerts
+            .iter()
+            .map(|alert| {
+                if include_addresses || watchlist.is_label(&alert.target) {
+                    return alert.to_string();
+                }
+                let target = watchlist.label_of(&alert.target).unwrap_or("address");
+                Alert {
+                    target: target.to_owned(),
+                    ..alert.clone()
+                }
+                .to_string()
+            })
+            .collect();
+        let labels = |addresses: &[String]| -> Vec<String> {
+            let labels: BTreeSet<&str> = addresses
+                .iter()
+                .filter_map(|address| watchlist.label_of(address))
+                .collect();
+            labels.into_iter().map(str::to_owned).collect()
+        };
+        // A group's copy covers its own addresses only.
+        let (undecided, divergence) = match &scan.consensus {
+            Some(consensus) => (
+                consensus.undecided.intersection(&watched).count(),
+                consensus
+                    .divergence
+                    .iter()
+                    .filter(|divergence| watched.contains(&divergence.address))
+                    .map(|divergence| Divergence {
+                        address: if include_addresses {
+                            divergence.address.clone()
+                        } else {
+                            watchlist
+                                .label_of(&divergence.address)
+                                .unwrap_or("address")
+                                .to_owned()
+                        },
+                        ..divergence.clone()
+                    })
+                    .collect(),
+            ),
+            None => (0, Vec::new()),
+        };
+        let mut label_deltas = BTreeMap::new();
+        if balances {
+            for (address, delta) in &deltas {
+                if let Some(label) = watchlist.label_of(address) {
+                    *label_deltas.entry(label.to_owned()).or_insert(0) += delta;
+                }
+            }
+        }
+
+        Report {
+            source_kind: match (scan.age_seconds, scan.rows_scanned) {
+                (Some(_), Some(_)) => "snapshot",
+                _ => "backend",
+            },
+            source: scan.source.clone(),
+            age_seconds: scan.age_seconds,
+            rows_scanned: scan.rows_scanned,
+            observed_unix: scan.observed_unix,
+            change,
+            watchlist: watchlist.len(),
+            funded: funded.len(),
+            added: added.len(),
+            removed: removed.len(),
+            unmatchable: scan.unmatchable,
+            presence_only: scan.presence_only,
+            balance_sats: balances.then_some(now),
+            delta_sats: balances.then_some(total_delta),
+            confirmed_sats: pending.map(|pending| (now as i64 - pending).max(0) as u64),
+            mempool_sats: pending,
+            groups,
+            alerts,
+            added_labels: labels(added),
+            removed_labels: labels(removed),
+            label_deltas,
+            undecided,
+            divergence,
+            addresses: include_addresses.then(|| AddressDetail {
+                added: added.to_vec(),
+                removed: removed.to_vec(),
+                balance_deltas: if balances { deltas } else { BTreeMap::new() },
+            }),
+        }
+    }
+
+    pub fn render(&self, format: Format) -> String {
+        match format {
+            Format::Text => self.text(),
+            Format::Json => serde_json::to_string(self).expect("report serializes"),
+            Format::Csv => self.csv(),
+        }
+    }
+
+    fn text(&self) -> String {
+        let mut message = match (self.age_seconds, self.rows_scanned) {
+            (Some(age), Some(rows)) => format!(
+                "snapshot={} age_seconds={age} rows_scanned={rows}",
+                self.source
+            ),
+            _ => format!("backend={}", self.source),
+        };
+        message.push_str(&format!(
+            " watchlist={} funded={} added={} removed={}",
+            self.watchlist, self.funded, self.added, self.removed,
+        ));
+        if self.unmatchable > 0 {
+            message.push_str(&format!(" unmatchable={}", self.unmatchable));
+        }
+        if let (Some(balance), Some(delta)) = (self.balance_sats, self.delta_sats) {
+            message.push_str(&format!(" balance_sats={balance} delta_sats={delta:+}"));
+        }
+        if let (Some(confirmed), Some(pending)) = (self.confirmed_sats, self.mempool_sats) {
+            message.push_str(&format!(
+                " confirmed_sats={confirmed} mempool_sats={pending:+}"
+            ));
+        }
+        for group in &self.groups {
+            message.push_str(&format!(
+                " {}[{}]=funded:{}/derived:{}",
+                group.kind, group.label, group.funded, group.derived
+            ));
+            if let (Some(balance), Some(delta)) = (group.balance_sats, group.delta_sats) {
+                message.push_str(&format!("/balance:{balance}/delta:{delta:+}"));
+            }
+        }
+        if !self.alerts.is_empty() {
+            message.push_str(&format!(" alerts={}", self.alerts.join(",")));
+        }
+        if self.undecided > 0 {
+            message.push_str(&format!(" undecided={}", self.undecided));
+        }
+        if !self.divergence.is_empty() {
+            message.push_str(&format!(" divergence={}", self.divergence_summary()));
+        }
+        if !self.added_labels.is_empty() {
+            message.push_str(&format!(" added_labels={}", self.added_labels.join(",")));
+        }
+        if !self.removed_labels.is_empty() {
+            message.push_str(&format!(
+                " removed_labels={}",
+                self.removed_labels.join(",")
+            ));
+        }
+        if !self.label_deltas.is_empty() {
+            let changed: Vec<_> = self
+                .label_deltas
+                .iter()
+                .map(|(label, delta)| format!("{label}:{delta:+}"))
+                .collect();
+            message.push_str(&format!(" label_deltas={}", changed.join(",")));
+        }
+        if let Some(detail) = &self.addresses {
+            if !detail.added.is_empty() {
+                message.push_str(&format!(" added_addresses={}", detail.added.join(",")));
+            }
+            if !detail.removed.is_empty() {
+                message.push_str(&format!(" removed_addresses={}", detail.removed.join(",")));
+            }
+            if !detail.balance_deltas.is_empty() {
+                let changed: Vec<_> = detail
+                    .balance_deltas
+                    .iter()
+                    .map(|(address, delta)| format!("{address}:{delta:+}"))
+                    .collect();
+                message.push_str(&format!(" balance_deltas={}", changed.join(",")));
+            }
+        }
+        message
+    }
+
+    /// `target:funded:N/empty:M` per disagreement, followed by
+    /// `/amounts:A|B` when the funded sources named different amounts, for
+    /// the text report and the divergence notification.
+    pub fn divergence_summary(&self) -> String {
+        let entries: Vec<_> = self
+            .divergence
+            .iter()
+            .map(|d| {
+                let mut entry = format!(
+                    "{}:funded:{}/empty:{}",
+                    d.address,
+                    d.funded_by.len(),
+                    d.empty_by.len()
+                );
+                if d.amounts_differ() {
+                    let amounts: BTreeSet<String> =
+                        d.amounts.values().map(u64::to_string).collect();
+                    let amounts: Vec<String> = amounts.into_iter().collect();
+                    entry.push_str(&format!("/amounts:{}", amounts.join("|")));
+                }
+                entry
+            })
+            .collect();
+        entries.join(",")
+    }
+
+    fn csv(&self) -> String {
+        let number = |value: Option<u64>| value.map(|v| v.to_string()).unwrap_or_default();
+        let signed = |value: Option<i64>| value.map(|v| format!("{v:+}")).unwrap_or_default();
+        let mut rows = vec![
+            "scope,label,observed_unix,change,watched,funded,added,removed,balance_sats,delta_sats,alerts"
+                .to_owned(),
+        ];
+        let total = [
+            "total".to_owned(),
+            self.source.clone(),
+            self.observed_unix.to_string(),
+            self.change.as_str().to_owned(),
+            self.watchlist.to_string(),
+            self.funded.to_string(),
+            self.added.to_string(),
+            self.removed.to_string(),
+            number(self.balance_sats),
+            signed(self.delta_sats),
+            self.alerts.join(";"),
+        ];
+        rows.push(csv_row(&total));
+        for group in &self.groups {
+            let row = [
+                group.kind.to_owned(),
+                group.label.clone(),
+                self.observed_unix.to_string(),
+                String::new(),
+                group.derived.to_string(),
+                group.funded.to_string(),
+                String::new(),
+                String::new(),
+                number(group.balance_sats),
+                signed(group.delta_sats),
+                String::new(),
+            ];
+            rows.push(csv_row(&row));
+        }
+        rows.join("\n")
+    }
+}
+
+fn delta(before: u64, after: u64) -> i64 {
+    after as i64 - before as i64
+}
+
+/// RFC 4180: quote fields holding a separator, quote or line break.
+fn csv_row(fields: &[String]) -> String {
+    fields
+        .iter()
+        .map(|field| {
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.clone()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    const FUNDED: &str = "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH";
+    const SPENT: &str = "3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy";
+
+    fn scan(balances: &[(&str, u64)]) -> Scan {
+        Scan {
+            source: "dump, 2026.tsv".to_owned(),
+            rows_scanned: Some(10),
+            age_seconds: Some(5),
+            observed_unix: 1_700_000_000,
+            balances: balances.iter().map(|(a, s)| (a.to_string(), *s)).collect(),
+            mempool: None,
+            presence_only: false,
+            unpriced: BTreeSet::new(),
+            unmatchable: 0,
+            consensus: None,
+        }
+    }
+
+    fn watchlist() -> Watchlist {
+        Watchlist {
+            addresses: BTreeSet::from([FUNDED.to_owned(), SPENT.to_owned()]),
+            ..Watchlist::default()
+        }
+    }
+
+    #[test]
+    fn change_follows_balance_direction() {
+        let before = BTreeMap::from([(SPENT.to_owned(), 700)]);
+        let build = |scan: &Scan| Report::build(scan, &watchlist(), &before, &[], &[], &[], false);
+
+        assert_eq!(build(&scan(&[(SPENT, 700)])).change, Change::None);
+        assert_eq!(
+            build(&scan(&[(SPENT, 900)])).change.exit_code(),
+            Change::Appeared.exit_code()
+        );
+        let mixed = build(&scan(&[(SPENT, 100), (FUNDED, 5_000)]));
+        assert_eq!(mixed.change, Change::Disappeared);
+        assert_eq!(mixed.change.exit_code(), 11);
+    }
+
+    #[test]
+    fn labels_stand_in_for_addresses() {
+        let mut watchlist = watchlist();
+        watchlist
+            .labels
+            .insert(SPENT.to_owned(), "payroll".to_owned());
+        watchlist
+            .groups
+            .insert(SPENT.to_owned(), "finance".to_owned());
+        let before = BTreeMap::from([(SPENT.to_owned(), 700)]);
+        let alert = Alert {
+            target: SPENT.to_owned(),
+            condition: crate::rules::Condition::Outflow,
+            before: 700,
+            after: 0,
+        };
+        let report = Report::build(
+            &scan(&[]),
+            &watchlist,
+            &before,
+            &[],
+            &[SPENT.to_owned()],
+            &[alert],
+            false,
+        );
+        let text = report.render(Format::Text);
+        assert!(!text.contains(SPENT), "{text}");
+        assert!(text.contains(" group[finance]=funded:0/derived:1/balance:0/delta:-700"));
+        assert!(text.contains(" alerts=payroll:outflow:"));
+        assert!(text.contains(" removed_labels=payroll label_deltas=payroll:-700"));
+    }
+
+    #[test]
+    fn group_copies_carry_only_their_own_divergence() {
+        use crate::consensus::Consensus;
+
+        let mut consensus = Consensus::default();
+        consensus.undecided.insert(SPENT.to_owned());
+        consensus.divergence.push(Divergence {
+            address: FUNDED.to_owned(),
+            funded_by: vec!["one".to_owned(), "two".to_owned()],
+            empty_by: vec![],
+            funded: Some(true),
+            amounts: [("one".to_owned(), 900), ("two".to_owned(), 500)].into(),
+        });
+        let mut scan = scan(&[(FUNDED, 500)]);
+        scan.consensus = Some(consensus);
+
+        let whole = Report::build(&scan, &watchlist(), &BTreeMap::new(), &[], &[], &[], true);
+        assert_eq!(whole.undecided, 1);
+        assert_eq!(
+            whole.divergence_summary(),
+            format!("{FUNDED}:funded:2/empty:0/amounts:500|900")
+        );
+
+        let spent_only = Watchlist {
+            addresses: BTreeSet::from([SPENT.to_owned()]),
+            ..Watchlist::default()
+        };
+        let group = Report::build(&scan, &spent_only, &BTreeMap::new(), &[], &[], &[], true);
+        assert!(group.divergence.is_empty());
+        assert_eq!(group.undecided, 1);
+        let funded_only = Watchlist {
+            addresses: BTreeSet::from([FUNDED.to_owned()]),
+            ..Watchlist::default()
+        };
+        let group = Report::build(&scan, &funded_only, &BTreeMap::new(), &[], &[], &[], true);
+        assert_eq!(group.divergence.len(), 1);
+        assert_eq!(group.undecided, 0);
+    }
+
+    #[test]
+    fn formats_share_one_set_of_fields() {
+        let report = Report::build(
+            &scan(&[(FUNDED, 5_000)]),
+            &watchlist(),
+            &BTreeMap::new(),
+            &[FUNDED.to_owned()],
+            &[],
+            &[],
+            true,
+        );
+        assert_eq!(
+            report.render(Format::Text),
+            format!(
+                "snapshot=dump, 2026.tsv age_seconds=5 rows_scanned=10 watchlist=2 funded=1 \
+                 added=1 removed=0 balance_sats=5000 delta_sats=+5000 \
+                 added_addresses={FUNDED} balance_deltas={FUNDED}:+5000"
+            )
+        );
+
+        let json: serde_json::Value = serde_json::from_str(&report.render(Format::Json)).unwrap();
+        assert_eq!(json["change"], "appeared");
+        assert_eq!(json["delta_sats"], 5_000);
+        assert!(json["confirmed_sats"].is_null());
+        assert_eq!(json["addresses"]["balance_deltas"][FUNDED], 5_000);
+
+        let csv = report.render(Format::Csv);
+        let mut lines = csv.lines();
+        assert!(lines.next().unwrap().starts_with("scope,label,"));
+        assert_eq!(
+            lines.next().unwrap(),
+            "total,\"dump, 2026.tsv\",1700000000,appeared,2,1,1,0,5000,+5000,"
+        );
+    }
+}
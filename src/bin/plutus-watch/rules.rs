@@ -0,0 +1,232 @@
+//! Balance alert rules.
+//!
+//! One rule per line, targeting a watched address or an xpub/descriptor label:
+//!
+//! ```text
+//! cold-storage  below    1.5btc
+//! 1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH  outflow
+//! ```
+//!
+//! Amounts carry a `sat`/`sats` or `btc` suffix; a bare number is an error.
+//! A label rule applies to the summed balance of every address under it.
+//! Rules fire on the check where the condition becomes true, not on every
+//! check while it stays true. A rule is skipped on checks where one of its
+//! funded addresses has no known amount (see [`Scan::unpriced`]).
+//!
+//! [`Scan::unpriced`]: crate::backend::Scan::unpriced
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use crate::amount::{format_delta, parse_amount, sum_balances};
+use crate::watchlist::Watchlist;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Condition {
+    /// Total balance fell under the threshold.
+    Below(u64),
+    /// Total balance decreased at all.
+    Outflow,
+}
+
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub target: String,
+    pub condition: Condition,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Alert {
+    pub target: String,
+    pub condition: Condition,
+    pub before: u64,
+    pub after: u64,
+}
+
+impl fmt::Display for Alert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let delta = format_delta(self.before, self.after);
+        match self.condition {
+            Condition::Below(threshold) => write!(
+                f,
+                "{}:below:{threshold}:balance={}:delta={delta}",
+                self.target, self.after
+            ),
+            Condition::Outflow => write!(
+                f,
+                "{}:outflow:balance={}:delta={delta}",
+                self.target, self.after
+            ),
+        }
+    }
+}
+
+pub fn load_rules(path: &Path, watchlist: &Watchlist) -> Result<Vec<Rule>, Box<dyn Error>> {
+    let file = File::open(path)?;
+    let mut rules = Vec::new();
+
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let at = || format!("{}:{}", path.display(), index + 1);
+        let mut words = line.split_whitespace();
+        let target = words.next().unwrap_or_default().to_owned();
+        let condition = match words.next() {
+            Some("below") => {
+                let amount = words.collect::<Vec<_>>().join(" ");
+                Condition::Below(parse_amount(&amount).ok_or_else(|| {
+                    format!(
+                        "invalid amount {amount:?} at {} (use a sat or btc suffix)",
+                        at()
+                    )
+                })?)
+            }
+            Some("outflow") => Condition::Outflow,
+            other => {
+                return Err(format!(
+                    "expected `below <amount>` or `outflow` at {}, found {other:?}",
+                    at()
+                )
+                .into())
+            }
+        };
+        if watchlist.resolve(&target).is_none() {
+            return Err(format!("rule target {target:?} at {} is not watched", at()).into());
+        }
+        rules.push(Rule { target, condition });
+    }
+
+    Ok(rules)
+}
+
+/// Alerts for the rules whose condition became true between `before` and
+/// `after`. Rules over an address in `unpriced` are returned in the second
+/// list instead of being evaluated against placeholder amounts.
+pub fn evaluate<'r>(
+    rules: &'r [Rule],
+    watchlist: &Watchlist,
+    before: &BTreeMap<String, u64>,
+    after: &BTreeMap<String, u64>,
+    unpriced: &BTreeSet<String>,
+) -> (Vec<Alert>, Vec<&'r Rule>) {
+    let mut alerts = Vec::new();
+    let mut skipped = Vec::new();
+    for rule in rules {
+        let Some(addresses) = watchlist.resolve(&rule.target) else {
+            continue;
+        };
+        if addresses.iter().any(|address| unpriced.contains(*address)) {
+            skipped.push(rule);
+            continue;
+        }
+        let was = sum_balances(before, addresses.iter().copied());
+        let now = sum_balances(after, addresses.iter().copied());
+        let fired = match rule.condition {
+            Condition::Below(threshold) => was >= threshold && now < threshold,
+            Condition::Outflow => now < was,
+        };
+        if fired {
+            alerts.push(Alert {
+                target: rule.target.clone(),
+                condition: rule.condition,
+                before: was,
+                after: now,
+            });
+        }
+    }
+    (alerts, skipped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const A: &str = "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH";
+
+    fn watchlist() -> Watchlist {
+        Watchlist {
+            addresses: [A.to_owned()].into_iter().collect::<BTreeSet<_>>(),
+            ..Watchlist::default()
+        }
+    }
+
+    fn balances(sats: u64) -> BTreeMap<String, u64> {
+        [(A.to_owned(), sats)].into_iter().collect()
+    }
+
+    fn alerts(rules: &[Rule], before: u64, after: u64) -> Vec<Alert> {
+        evaluate(
+            rules,
+            &watchlist(),
+            &balances(before),
+            &balances(after),
+            &BTreeSet::new(),
+        )
+        .0
+    }
+
+    #[test]
+    fn below_fires_once_on_crossing() {
+        let rules = [Rule {
+            target: A.to_owned(),
+            condition: Condition::Below(1_000),
+        }];
+        let fired = alerts(&rules, 10_000, 100);
+        assert_eq!(fired.len(), 1);
+        assert_eq!(
+            fired[0].to_string(),
+            format!("{A}:below:1000:balance=100:delta=-9900")
+        );
+        assert!(alerts(&rules, 100, 50).is_empty());
+    }
+
+    #[test]
+    fn outflow_fires_on_any_decrease() {
+        let rules = [Rule {
+            target: A.to_owned(),
+            condition: Condition::Outflow,
+        }];
+        assert_eq!(alerts(&rules, 10, 9).len(), 1);
+        assert!(alerts(&rules, 9, 10).is_empty());
+        let list = watchlist();
+        let (fired, _) = evaluate(
+            &rules,
+            &list,
+            &BTreeMap::new(),
+            &balances(10),
+            &BTreeSet::new(),
+        );
+        assert!(fired.is_empty());
+    }
+
+    #[test]
+    fn placeholder_amounts_skip_the_rule() {
+        let rules = [
+            Rule {
+                target: A.to_owned(),
+                condition: Condition::Below(1_000),
+            },
+            Rule {
+                target: A.to_owned(),
+                condition: Condition::Outflow,
+            },
+        ];
+        let unpriced: BTreeSet<String> = [A.to_owned()].into();
+        let (fired, skipped) = evaluate(
+            &rules,
+            &watchlist(),
+            &balances(10_000),
+            &balances(1),
+            &unpriced,
+        );
+        assert!(fired.is_empty());
+        assert_eq!(skipped.len(), 2);
+    }
+}
@@ -0,0 +1,151 @@
+//! Per-address state persisted between runs.
+//!
+//! Without it every funded address would be reported as "added" after a
+//! restart, and anything that changed while the watcher was down would be
+//! lost. The file is rewritten atomically after every check.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use plutus_rustus::status;
+use serde::{Deserialize, Serialize};
+
+use crate::consensus::Divergence;
+
+pub const STATE_FILE: &str = "watch-state.json";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct WatchState {
+    /// Modification time of the snapshot behind the last saved check.
+    pub snapshot_unix: u64,
+    pub addresses: BTreeMap<String, AddressState>,
+    /// Source disagreements at the last check, so the divergence
+    /// notification goes out only when they change.
+    #[serde(default)]
+    pub divergence: Vec<Divergence>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AddressState {
+    pub funded: bool,
+    /// Satoshis at the last check; zero once the address is emptied.
+    pub last_balance: u64,
+    pub first_seen_unix: u64,
+    pub last_seen_unix: u64,
+}
+
+pub fn state_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(STATE_FILE)
+}
+
+impl WatchState {
+    /// A missing file is a first run, not an error.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        match fs::read(path) {
+            Ok(body) => serde_json::from_slice(&body).map_err(|error| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}: {error}", path.display()),
+                )
+            }),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(error) => Err(error),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        status::write_atomic(path, self)
+    }
+
+    pub fn funded(&self) -> BTreeSet<String> {
+        self.addresses
+            .iter()
+            .filter(|(_, state)| state.funded)
+            .map(|(address, _)| address.clone())
+            .collect()
+    }
+
+    pub fn balances(&self) -> BTreeMap<String, u64> {
+        self.addresses
+            .iter()
+            .map(|(address, state)| (address.clone(), state.last_balance))
+            .collect()
+    }
+
+    /// Fold one check into the state. `balances` holds every funded watched
+    /// address; watched addresses absent from it are recorded as empty.
+    /// Entries for addresses no longer watched are dropped.
+    pub fn record(
+        &mut self,
+        snapshot_unix: u64,
+        watched: &BTreeSet<String>,
+        balances: &BTreeMap<String, u64>,
+    ) {
+        self.snapshot_unix = snapshot_unix;
+        self.addresses
+            .retain(|address, _| watched.contains(address));
+        for (address, balance) in balances {
+            let entry = self
+                .addresses
+                .entry(address.clone())
+                .or_insert_with(|| AddressState {
+                    funded: true,
+                    last_balance: *balance,
+                    first_seen_unix: snapshot_unix,
+                    last_seen_unix: snapshot_unix,
+                });
+            entry.funded = true;
+            entry.last_balance = *balance;
+            entry.last_seen_unix = snapshot_unix;
+        }
+        for (address, entry) in &mut self.addresses {
+            if entry.funded && !balances.contains_key(address) {
+                entry.funded = false;
+                entry.last_balance = 0;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::unique_dir;
+
+    const A: &str = "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH";
+    const B: &str = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4";
+
+    fn balances(entries: &[(&str, u64)]) -> BTreeMap<String, u64> {
+        entries.iter().map(|(a, b)| (a.to_string(), *b)).collect()
+    }
+
+    #[test]
+    fn state_survives_restart_and_tracks_seen_times() {
+        let dir = unique_dir("plutus-watch-state");
+        let path = state_path(&dir);
+        assert!(WatchState::load(&path).unwrap().addresses.is_empty());
+
+        let watched: BTreeSet<String> = [A, B].into_iter().map(String::from).collect();
+        let mut state = WatchState::default();
+        state.record(100, &watched, &balances(&[(A, 5)]));
+        state.save(&path).unwrap();
+
+        let mut restarted = WatchState::load(&path).unwrap();
+        assert_eq!(restarted.funded(), [A.to_owned()].into_iter().collect());
+        restarted.record(200, &watched, &balances(&[(B, 7)]));
+
+        let a = &restarted.addresses[A];
+        assert!(!a.funded);
+        assert_eq!(a.last_balance, 0);
+        assert_eq!((a.first_seen_unix, a.last_seen_unix), (100, 100));
+        let b = &restarted.addresses[B];
+        assert_eq!((b.funded, b.first_seen_unix), (true, 200));
+
+        let narrowed: BTreeSet<String> = [B.to_owned()].into_iter().collect();
+        restarted.record(300, &narrowed, &balances(&[(B, 7)]));
+        assert!(!restarted.addresses.contains_key(A));
+        let _ = fs::remove_dir_all(dir);
+    }
+}
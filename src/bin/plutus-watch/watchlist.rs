@@ -0,0 +1,463 @@
+//! Watchlist parsing and extended-public-key derivation.
+//!
+//! A watchlist line is either a literal address or an account-level extended
+//! public key with an optional label:
+//!
+//! ```text
+//! 1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH
+//! zpub6rFR7y4Q2Aij...   cold-storage
+//! ```
+//!
+//! Tab-separated lines may add a group after the label, which routes
+//! notifications (`address<TAB>label<TAB>group`):
+//!
+//! ```text
+//! 1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH<TAB>payroll<TAB>finance
+//! zpub6rFR7y4Q2Aij...<TAB>cold-storage<TAB>treasury
+//! ```
+//!
+//! `xpub`/`tpub` derive P2PKH, `ypub`/`upub` P2SH-P2WPKH and `zpub`/`vpub`
+//! P2WPKH, on the receive (`/0/i`) and change (`/1/i`) chains. Only public
+//! derivation is used; no private key material is ever accepted.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::str::FromStr;
+
+use bitcoin::bip32::{ChildNumber, ExtendedPubKey};
+use bitcoin::secp256k1::{Secp256k1, VerifyOnly};
+use bitcoin::{Address, Network};
+
+use crate::descriptor::DescriptorWatch;
+
+const XPUB: [u8; 4] = [0x04, 0x88, 0xb2, 0x1e];
+const YPUB: [u8; 4] = [0x04, 0x9d, 0x7c, 0xb2];
+const ZPUB: [u8; 4] = [0x04, 0xb2, 0x47, 0x46];
+const TPUB: [u8; 4] = [0x04, 0x35, 0x87, 0xcf];
+const UPUB: [u8; 4] = [0x04, 0x4a, 0x52, 0x62];
+const VPUB: [u8; 4] = [0x04, 0x5f, 0x1c, 0xf6];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptKind {
+    P2pkh,
+    P2shP2wpkh,
+    P2wpkh,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Watchlist {
+    pub addresses: BTreeSet<String>,
+    /// Labels of literal addresses; several addresses may share one.
+    pub labels: BTreeMap<String, String>,
+    /// Groups of literal addresses.
+    pub groups: BTreeMap<String, String>,
+    pub xpubs: Vec<XpubWatch>,
+    pub descriptors: Vec<DescriptorWatch>,
+}
+
+#[derive(Debug, Clone)]
+pub struct XpubWatch {
+    pub label: String,
+    pub group: Option<String>,
+    pub script: ScriptKind,
+    network: Network,
+    /// Receive and change chain keys, derived once.
+    chains: [ExtendedPubKey; 2],
+    /// Addresses derived so far, per chain, in index order.
+    pub derived: [Vec<String>; 2],
+}
+
+impl Watchlist {
+    pub fn is_empty(&self) -> bool {
+        self.addresses.is_empty() && self.xpubs.is_empty() && self.descriptors.is_empty()
+    }
+
+    /// Literal and derived addresses, for matching against a snapshot.
+    pub fn all_addresses(&self) -> BTreeSet<String> {
+        let mut all = self.addresses.clone();
+        for xpub in &self.xpubs {
+            for chain in &xpub.derived {
+                all.extend(chain.iter().cloned());
+            }
+        }
+        for descriptor in &self.descriptors {
+            all.extend(descriptor.addresses.iter().cloned());
+        }
+        all
+    }
+
+    pub fn len(&self) -> usize {
+        self.addresses.len()
+            + self
+                .xpubs
+                .iter()
+                .map(|x| x.derived[0].len() + x.derived[1].len())
+                .sum::<usize>()
+            + self
+                .descriptors
+                .iter()
+                .map(|d| d.addresses.len())
+                .sum::<usize>()
+    }
+
+    pub fn is_label(&self, target: &str) -> bool {
+        self.xpubs.iter().any(|x| x.label == target)
+            || self.descriptors.iter().any(|d| d.label == target)
+            || self.labels.values().any(|label| label == target)
+    }
+
+    /// The label shown for `address` in place of the address itself.
+    pub fn label_of(&self, address: &str) -> Option<&str> {
+        if let Some(label) = self.labels.get(address) {
+            return Some(label);
+        }
+        self.xpubs
+            .iter()
+            .find(|x| x.derived.iter().flatten().any(|a| a == address))
+            .map(|x| x.label.as_str())
+            .or_else(|| {
+                self.descriptors
+                    .iter()
+                    .find(|d| d.addresses.iter().any(|a| a == address))
+                    .map(|d| d.label.as_str())
+            })
+    }
+
+    /// Every group named in the watchlist or descriptors.
+    pub fn group_names(&self) -> BTreeSet<&str> {
+        let xpubs = self.xpubs.iter().filter_map(|x| x.group.as_deref());
+        let descriptors = self.descriptors.iter().filter_map(|d| d.group.as_deref());
+        self.groups
+            .values()
+            .map(String::as_str)
+            .chain(xpubs)
+            .chain(descriptors)
+            .collect()
+    }
+
+    /// The entries belonging to `group`, for a group-scoped report.
+    pub fn subset(&self, group: &str) -> Watchlist {
+        let in_group = |g: &Option<String>| g.as_deref() == Some(group);
+        let addresses: BTreeSet<String> = self
+            .groups
+            .iter()
+            .filter(|(_, g)| *g == group)
+            .map(|(address, _)| address.clone())
+            .collect();
+        Watchlist {
+            labels: self
+                .labels
+                .iter()
+                .filter(|(address, _)| addresses.contains(*address))
+                .map(|(address, label)| (address.clone(), label.clone()))
+                .collect(),
+            groups: addresses
+                .iter()
+                .map(|address| (address.clone(), group.to_owned()))
+                .collect(),
+            addresses,
+            xpubs: self
+                .xpubs
+                .iter()
+                .filter(|x| in_group(&x.group))
+                .cloned()
+                .collect(),
+            descriptors: self
+                .descriptors
+                .iter()
+                .filter(|d| in_group(&d.group))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Addresses behind a rule target: an xpub or descriptor label, the
+    /// addresses sharing a label, or a single watched address.
+    pub fn resolve(&self, target: &str) -> Option<Vec<&String>> {
+        if let Some(xpub) = self.xpubs.iter().find(|x| x.label == target) {
+            return Some(xpub.derived.iter().flatten().collect());
+        }
+        if let Some(descriptor) = self.descriptors.iter().find(|d| d.label == target) {
+            return Some(descriptor.addresses.iter().collect());
+        }
+        let labelled: Vec<&String> = self
+            .labels
+            .iter()
+            .filter(|(_, label)| *label == target)
+            .map(|(address, _)| address)
+            .collect();
+        if !labelled.is_empty() {
+            return Some(labelled);
+        }
+        let literal = self.addresses.get(target);
+        let derived = || {
+            self.xpubs
+                .iter()
+                .flat_map(|x| x.derived.iter().flatten())
+                .chain(self.descriptors.iter().flat_map(|d| d.addresses.iter()))
+                .find(|address| *address == target)
+        };
+        literal.or_else(derived).map(|address| vec![address])
+    }
+
+    /// Grow every xpub window so `gap` unused addresses follow the last funded
+    /// one on each chain. Returns `true` if anything new was derived, in which
+    /// case the caller should match again.
+    pub fn extend_for_funded(
+        &mut self,
+        funded: &BTreeSet<String>,
+        gap: u32,
+    ) -> Result<bool, Box<dyn Error>> {
+        let secp = Secp256k1::verification_only();
+        let mut grew = false;
+        for xpub in &mut self.xpubs {
+            for chain in 0..2 {
+                let last_funded = xpub.derived[chain]
+                    .iter()
+                    .rposition(|address| funded.contains(address));
+                let want = last_funded.map_or(0, |i| i + 1) + gap as usize;
+                if xpub.derived[chain].len() < want {
+                    xpub.derive_to(&secp, chain, want)?;
+                    grew = true;
+                }
+            }
+        }
+        Ok(grew)
+    }
+}
+
+impl XpubWatch {
+    pub fn parse(key: &str, label: Option<&str>, gap: u32) -> Result<Self, Box<dyn Error>> {
+        let raw = bitcoin::base58::decode_check(key)
+            .map_err(|error| format!("not an extended public key: {error}"))?;
+        if raw.len() != 78 {
+            return Err("extended public key must be 78 bytes".into());
+        }
+        let version: [u8; 4] = raw[0..4].try_into().unwrap();
+        let (script, canonical) = match version {
+            XPUB => (ScriptKind::P2pkh, XPUB),
+            YPUB => (ScriptKind::P2shP2wpkh, XPUB),
+            ZPUB => (ScriptKind::P2wpkh, XPUB),
+            TPUB => (ScriptKind::P2pkh, TPUB),
+            UPUB => (ScriptKind::P2shP2wpkh, TPUB),
+            VPUB => (ScriptKind::P2wpkh, TPUB),
+            _ => return Err("unsupported extended key version (expected x/y/z/t/u/vpub)".into()),
+        };
+        let mut normalized = raw;
+        normalized[0..4].copy_from_slice(&canonical);
+        let account = ExtendedPubKey::decode(&normalized)?;
+        let secp = Secp256k1::verification_only();
+        let receive = account.ckd_pub(&secp, ChildNumber::Normal { index: 0 })?;
+        let change = account.ckd_pub(&secp, ChildNumber::Normal { index: 1 })?;
+        let mut watch = XpubWatch {
+            label: label
+                .map(str::to_owned)
+                .unwrap_or_else(|| key.chars().take(12).collect()),
+            group: None,
+            script,
+            network: account.network,
+            chains: [receive, change],
+            derived: [Vec::new(), Vec::new()],
+        };
+        watch.derive_to(&secp, 0, gap as usize)?;
+        watch.derive_to(&secp, 1, gap as usize)?;
+        Ok(watch)
+    }
+
+    fn derive_to(
+        &mut self,
+        secp: &Secp256k1<VerifyOnly>,
+        chain: usize,
+        count: usize,
+    ) -> Result<(), Box<dyn Error>> {
+        while self.derived[chain].len() < count {
+            let index = self.derived[chain].len() as u32;
+            let child = self.chains[chain].ckd_pub(secp, ChildNumber::Normal { index })?;
+            let public_key = child.to_pub();
+            let address = match self.script {
+                ScriptKind::P2pkh => Address::p2pkh(&public_key, self.network),
+                ScriptKind::P2shP2wpkh => Address::p2shwpkh(&public_key, self.network)?,
+                ScriptKind::P2wpkh => Address::p2wpkh(&public_key, self.network)?,
+            };
+            self.derived[chain].push(address.to_string());
+        }
+        Ok(())
+    }
+
+    pub fn derived_count(&self) -> usize {
+        self.derived[0].len() + self.derived[1].len()
+    }
+
+    pub fn funded_count(&self, funded: &BTreeSet<String>) -> usize {
+        self.derived
+            .iter()
+            .flatten()
+            .filter(|address| funded.contains(*address))
+            .count()
+    }
+}
+
+pub fn load_watchlist(path: &Path, gap: u32) -> Result<Watchlist, Box<dyn Error>> {
+    let file = File::open(path)?;
+    let mut watchlist = Watchlist::default();
+    let mut labels = BTreeSet::new();
+
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let at = || format!("{}:{}", path.display(), index + 1);
+        let (entry, label, group) =
+            split_columns(line).map_err(|error| format!("{error} at {}", at()))?;
+        if is_extended_key(entry) {
+            let mut xpub = XpubWatch::parse(entry, label, gap)
+                .map_err(|error| format!("invalid extended key at {}: {error}", at()))?;
+            xpub.group = group.map(str::to_owned);
+            if !labels.insert(xpub.label.clone()) {
+                return Err(format!("duplicate xpub label {:?} at {}", xpub.label, at()).into());
+            }
+            watchlist.xpubs.push(xpub);
+            continue;
+        }
+        Address::from_str(entry)
+            .map_err(|error| format!("invalid address at {}: {error}", at()))?;
+        if !watchlist.addresses.insert(entry.to_owned()) {
+            return Err(format!("duplicate address at {}", at()).into());
+        }
+        if let Some(label) = label {
+            watchlist.labels.insert(entry.to_owned(), label.to_owned());
+        }
+        if let Some(group) = group {
+            watchlist.groups.insert(entry.to_owned(), group.to_owned());
+        }
+    }
+
+    Ok(watchlist)
+}
+
+/// `entry<TAB>label<TAB>group` with optional trailing columns, or the older
+/// `entry label` where the label runs to the end of the line.
+pub fn split_columns(line: &str) -> Result<(&str, Option<&str>, Option<&str>), String> {
+    fn present(column: Option<&str>) -> Option<&str> {
+        column.map(str::trim).filter(|c| !c.is_empty())
+    }
+    if line.contains('\t') {
+        let mut columns = line.split('\t');
+        let entry = columns.next().unwrap_or_default().trim();
+        let label = present(columns.next());
+        let group = present(columns.next());
+        if columns.any(|extra| !extra.trim().is_empty()) {
+            return Err("expected at most entry, label and group columns".to_owned());
+        }
+        return Ok((entry, label, group));
+    }
+    Ok(match line.split_once(char::is_whitespace) {
+        Some((entry, label)) => (entry, present(Some(label)), None),
+        None => (line, None, None),
+    })
+}
+
+fn is_extended_key(entry: &str) -> bool {
+    ["xpub", "ypub", "zpub", "tpub", "upub", "vpub"]
+        .iter()
+        .any(|prefix| entry.starts_with(prefix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Account keys for the "abandon ... about" BIP39 test mnemonic.
+    const BIP44_XPUB: &str = "xpub6BosfCnifzxcFwrSzQiqu2DBVTshkCXacvNsWGYJVVhhawA7d4R5WSWGFNbi8Aw6ZRc1brxMyWMzG3DSSSSoekkudhUd9yLb6qx39T9nMdj";
+    const BIP49_YPUB: &str = "ypub6Ww3ibxVfGzLrAH1PNcjyAWenMTbbAosGNB6VvmSEgytSER9azLDWCxoJwW7Ke7icmizBMXrzBx9979FfaHxHcrArf3zbeJJJUZPf663zsP";
+    const BIP84_ZPUB: &str = "zpub6rFR7y4Q2AijBEqTUquhVz398htDFrtymD9xYYfG1m4wAcvPhXNfE3EfH1r1ADqtfSdVCToUG868RvUUkgDKf31mGDtKsAYz2oz2AGutZYs";
+
+    #[test]
+    fn derives_first_receive_address_for_each_version() {
+        let xpub = XpubWatch::parse(BIP44_XPUB, None, 1).unwrap();
+        assert_eq!(xpub.derived[0][0], "1LqBGSKuX5yYUonjxT5qGfpUsXKYYWeabA");
+        let ypub = XpubWatch::parse(BIP49_YPUB, None, 1).unwrap();
+        assert_eq!(ypub.derived[0][0], "37VucYSaXLCAsxYyAPfbSi9eh4iEcbShgf");
+        let zpub = XpubWatch::parse(BIP84_ZPUB, Some("cold"), 1).unwrap();
+        assert_eq!(
+            zpub.derived[0][0],
+            "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu"
+        );
+        assert_eq!(zpub.label, "cold");
+        assert_eq!(zpub.derived_count(), 2);
+    }
+
+    #[test]
+    fn window_extends_past_funded_addresses() {
+        let mut watchlist = Watchlist::default();
+        watchlist
+            .xpubs
+            .push(XpubWatch::parse(BIP84_ZPUB, Some("cold"), 20).unwrap());
+        let far = {
+            let mut probe = XpubWatch::parse(BIP84_ZPUB, None, 31).unwrap();
+            probe.derived[0].pop().unwrap()
+        };
+        let near = watchlist.xpubs[0].derived[0][15].clone();
+        let funded: BTreeSet<String> = [near, far].into_iter().collect();
+
+        assert!(watchlist.extend_for_funded(&funded, 20).unwrap());
+        assert_eq!(watchlist.xpubs[0].derived[0].len(), 36);
+        assert!(watchlist.extend_for_funded(&funded, 20).unwrap());
+        assert_eq!(watchlist.xpubs[0].derived[0].len(), 51);
+        assert!(!watchlist.extend_for_funded(&funded, 20).unwrap());
+        assert_eq!(watchlist.xpubs[0].funded_count(&funded), 2);
+        assert_eq!(watchlist.xpubs[0].derived[1].len(), 20);
+    }
+
+    #[test]
+    fn tab_columns_carry_labels_and_groups() {
+        let dir = crate::test_util::unique_dir("plutus-watchlist");
+        let path = dir.join("watchlist.txt");
+        std::fs::write(
+            &path,
+            format!(
+                "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH\tpayroll\tfinance\n\
+                 3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy\tpayroll\n\
+                 {BIP84_ZPUB}\tcold storage\ttreasury\n\
+                 bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4 legacy label\n"
+            ),
+        )
+        .unwrap();
+        let watchlist = load_watchlist(&path, 2).unwrap();
+        let _ = std::fs::remove_dir_all(dir);
+
+        assert_eq!(
+            watchlist.label_of("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4"),
+            Some("legacy label")
+        );
+        assert_eq!(
+            watchlist.label_of("bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu"),
+            Some("cold storage")
+        );
+        assert_eq!(watchlist.resolve("payroll").unwrap().len(), 2);
+        assert_eq!(
+            watchlist.group_names().into_iter().collect::<Vec<_>>(),
+            ["finance", "treasury"]
+        );
+
+        let finance = watchlist.subset("finance");
+        assert_eq!(
+            finance.all_addresses().into_iter().collect::<Vec<_>>(),
+            ["1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH"]
+        );
+        assert_eq!(watchlist.subset("treasury").len(), 4);
+
+        assert!(split_columns("1Bg\tlabel\tgroup\textra").is_err());
+    }
+
+    #[test]
+    fn rejects_private_and_unknown_keys() {
+        assert!(XpubWatch::parse("xprv9s21ZrQH143K3QTDL4LXw2F7HEK3wJUD2nW2nRk4stbPy6cq3jPPqjiChkVvvNKmPGJxWUtg6LnF5kejMRNNU3TGtRBeJgk33yuGBxrMPHi", None, 1).is_err());
+        assert!(XpubWatch::parse("not-a-key", None, 1).is_err());
+    }
+}
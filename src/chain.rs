@@ -0,0 +1,379 @@
+//! Chains that share Bitcoin's key and hash160 construction.
+//!
+//! Litecoin, Dogecoin and Bitcoin Cash derive P2PKH (and, where they have it,
+//! P2WPKH) addresses from the same `hash160(pubkey)` the hot loop computes;
+//! only the version bytes, bech32 HRP and WIF prefix differ. One snapshot
+//! holds one chain, recorded in its header, and hits are written in that
+//! chain's formats.
+
+use bitcoin::base58;
+use bitcoin::bech32::{self, FromBase32, Variant};
+use bitcoin::secp256k1::SecretKey;
+use bitcoin::Network;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Chain {
+    /// Table key, tag on hits and the `chain=` value in reports.
+    pub name: &'static str,
+    /// `bitcoin`, `litecoin`, `dogecoin` or `bitcoin-cash`.
+    pub family: &'static str,
+    /// Snapshot header byte 45.
+    id: u8,
+    /// Bitcoin network; mainnet for the other chains.
+    pub network: Network,
+    pub pubkey_hash: u8,
+    /// First characters a P2PKH address of this version byte can start with.
+    p2pkh_lead: &'static str,
+    pub wif: u8,
+    /// Native SegWit HRP, without the `1` separator.
+    pub segwit_hrp: Option<&'static str>,
+    /// CashAddr prefix; hits are written in CashAddr when set.
+    pub cashaddr_prefix: Option<&'static str>,
+}
+
+pub const BITCOIN: Chain = Chain {
+    name: "bitcoin",
+    family: "bitcoin",
+    id: 0,
+    network: Network::Bitcoin,
+    pubkey_hash: 0x00,
+    p2pkh_lead: "1",
+    wif: 0x80,
+    segwit_hrp: Some("bc"),
+    cashaddr_prefix: None,
+};
+
+pub const CHAINS: &[Chain] = &[
+    BITCOIN,
+    Chain {
+        name: "testnet",
+        network: Network::Testnet,
+        pubkey_hash: 0x6f,
+        p2pkh_lead: "mn",
+        wif: 0xef,
+        segwit_hrp: Some("tb"),
+        ..BITCOIN
+    },
+    Chain {
+        name: "signet",
+        network: Network::Signet,
+        pubkey_hash: 0x6f,
+        p2pkh_lead: "mn",
+        wif: 0xef,
+        segwit_hrp: Some("tb"),
+        ..BITCOIN
+    },
+    Chain {
+        name: "regtest",
+        network: Network::Regtest,
+        pubkey_hash: 0x6f,
+        p2pkh_lead: "mn",
+        wif: 0xef,
+        segwit_hrp: Some("bcrt"),
+        ..BITCOIN
+    },
+    Chain {
+        name: "litecoin",
+        family: "litecoin",
+        id: 1,
+        pubkey_hash: 0x30,
+        p2pkh_lead: "L",
+        wif: 0xb0,
+        segwit_hrp: Some("ltc"),
+        ..BITCOIN
+    },
+    Chain {
+        name: "dogecoin",
+        family: "dogecoin",
+        id: 2,
+        pubkey_hash: 0x1e,
+        p2pkh_lead: "D",
+        wif: 0x9e,
+        segwit_hrp: None,
+        ..BITCOIN
+    },
+    Chain {
+        name: "bitcoin-cash",
+        family: "bitcoin-cash",
+        id: 3,
+        segwit_hrp: None,
+        cashaddr_prefix: Some("bitcoincash"),
+        ..BITCOIN
+    },
+];
+
+/// Bitcoin on `network`.
+pub fn bitcoin(network: Network) -> Chain {
+    CHAINS
+        .iter()
+        .find(|chain| chain.id == 0 && chain.network == network)
+        .copied()
+        .unwrap_or(BITCOIN)
+}
+
+/// The chain of `family` (`bitcoin`, `ltc`, `doge`, `bch`, ...) on `network`.
+/// Only Bitcoin has test networks.
+pub fn find(family: &str, network: Network) -> Option<Chain> {
+    let family = match family.trim().to_ascii_lowercase().as_str() {
+        "bitcoin" | "btc" => "bitcoin",
+        "litecoin" | "ltc" => "litecoin",
+        "dogecoin" | "doge" => "dogecoin",
+        "bitcoin-cash" | "bitcoincash" | "bch" => "bitcoin-cash",
+        _ => return None,
+    };
+    CHAINS
+        .iter()
+        .find(|chain| chain.family == family && chain.network == network)
+        .copied()
+}
+
+/// The chain stamped in a snapshot header (bytes 45 and 44).
+pub fn from_header(id: u8, network: Network) -> Option<Chain> {
+    CHAINS
+        .iter()
+        .find(|chain| chain.id == id && chain.network == network)
+        .copied()
+}
+
+impl Chain {
+    pub fn header_id(&self) -> u8 {
+        self.id
+    }
+
+    /// Decode a funded address of this chain to the hash160 the hot loop
+    /// matches against: P2PKH, P2WPKH where the chain has SegWit, and
+    /// CashAddr P2PKH (with or without prefix) on Bitcoin Cash. Anything
+    /// else, including other chains' addresses, is `None`.
+    pub fn hash160(&self, addr: &str) -> Option<[u8; 20]> {
+        let lead = addr.chars().next()?;
+        if self.p2pkh_lead.contains(lead) {
+            let raw = base58::decode_check(addr).ok()?;
+            if raw.len() == 21 && raw[0] == self.pubkey_hash {
+                let mut h = [0u8; 20];
+                h.copy_from_slice(&raw[1..21]);
+                return Some(h);
+            }
+            return None;
+        }
+        if let Some(hrp) = self.segwit_hrp {
+            if has_prefix(addr, hrp, '1') {
+                return segwit_v0_hash(addr, hrp);
+            }
+        }
+        if let Some(prefix) = self.cashaddr_prefix {
+            return cashaddr_decode(prefix, addr);
+        }
+        None
+    }
+
+    /// The P2PKH address hits are reported as: Base58Check, or CashAddr on
+    /// Bitcoin Cash.
+    pub fn p2pkh(&self, hash: &[u8; 20]) -> String {
+        if let Some(prefix) = self.cashaddr_prefix {
+            return cashaddr_encode(prefix, hash);
+        }
+        let mut raw = [0u8; 21];
+        raw[0] = self.pubkey_hash;
+        raw[1..].copy_from_slice(hash);
+        base58::encode_check(&raw)
+    }
+
+    pub fn wif(&self, secret: &SecretKey, compressed: bool) -> String {
+        let mut raw = Vec::with_capacity(34);
+        raw.push(self.wif);
+        raw.extend_from_slice(&secret.secret_bytes());
+        if compressed {
+            raw.push(0x01);
+        }
+        base58::encode_check(&raw)
+    }
+}
+
+fn has_prefix(addr: &str, prefix: &str, separator: char) -> bool {
+    addr.len() > prefix.len()
+        && addr.is_char_boundary(prefix.len())
+        && addr[..prefix.len()].eq_ignore_ascii_case(prefix)
+        && addr[prefix.len()..].starts_with(separator)
+}
+
+fn segwit_v0_hash(addr: &str, hrp: &str) -> Option<[u8; 20]> {
+    let (found_hrp, data, variant) = bech32::decode(addr).ok()?;
+    if found_hrp != hrp || variant != Variant::Bech32 || data.first()?.to_u8() != 0 {
+        return None;
+    }
+    let program = Vec::<u8>::from_base32(&data[1..]).ok()?;
+    program.try_into().ok()
+}
+
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+/// CashAddr checksum (BCH spec, 40-bit BCH code over GF(32)).
+fn cashaddr_polymod(values: impl IntoIterator<Item = u8>) -> u64 {
+    const GENERATORS: [u64; 5] = [
+        0x98_f2bc_8e61,
+        0x79_b76d_99e2,
+        0xf3_3e5f_b3c4,
+        0xae_2eab_e2a8,
+        0x1e_4f43_e470,
+    ];
+    let mut c: u64 = 1;
+    for value in values {
+        let top = c >> 35;
+        c = ((c & 0x07_ffff_ffff) << 5) ^ u64::from(value);
+        for (bit, generator) in GENERATORS.iter().enumerate() {
+            if (top >> bit) & 1 == 1 {
+                c ^= generator;
+            }
+        }
+    }
+    c ^ 1
+}
+
+fn prefix_values(prefix: &str) -> impl Iterator<Item = u8> + '_ {
+    prefix.bytes().map(|b| b & 0x1f).chain([0])
+}
+
+fn cashaddr_encode(prefix: &str, hash: &[u8; 20]) -> String {
+    // Version byte 0: P2PKH with a 160-bit hash.
+    let mut payload = vec![0u8];
+    payload.extend_from_slice(hash);
+    let mut data = Vec::with_capacity(42);
+    let (mut acc, mut bits) = (0u32, 0u32);
+    for byte in payload {
+        acc = (acc << 8) | u32::from(byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            data.push(((acc >> bits) & 0x1f) as u8);
+        }
+    }
+    if bits > 0 {
+        data.push(((acc << (5 - bits)) & 0x1f) as u8);
+    }
+    let checksum = cashaddr_polymod(
+        prefix_values(prefix)
+            .chain(data.iter().copied())
+            .chain([0; 8]),
+    );
+    data.extend((0..8).map(|i| ((checksum >> (5 * (7 - i))) & 0x1f) as u8));
+    let body: String = data.iter().map(|&v| CHARSET[v as usize] as char).collect();
+    format!("{prefix}:{body}")
+}
+
+fn cashaddr_decode(prefix: &str, addr: &str) -> Option<[u8; 20]> {
+    let body = if has_prefix(addr, prefix, ':') {
+        &addr[prefix.len() + 1..]
+    } else {
+        addr
+    };
+    if body.len() != 42 {
+        return None;
+    }
+    let mut data = Vec::with_capacity(42);
+    for b in body.bytes() {
+        let b = b.to_ascii_lowercase();
+        data.push(CHARSET.iter().position(|&c| c == b)? as u8);
+    }
+    if cashaddr_polymod(prefix_values(prefix).chain(data.iter().copied())) != 0 {
+        return None;
+    }
+    let mut bytes = Vec::with_capacity(21);
+    let (mut acc, mut bits) = (0u32, 0u32);
+    for &value in &data[..34] {
+        acc = (acc << 5) | u32::from(value);
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push(((acc >> bits) & 0xff) as u8);
+        }
+    }
+    if bytes.len() != 21 || bytes[0] != 0 {
+        return None;
+    }
+    bytes[1..].try_into().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key1_hash() -> [u8; 20] {
+        BITCOIN
+            .hash160("1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH")
+            .unwrap()
+    }
+
+    #[test]
+    fn each_chain_round_trips_its_own_p2pkh_and_rejects_the_others() {
+        let hash = key1_hash();
+        for chain in CHAINS {
+            let address = chain.p2pkh(&hash);
+            assert_eq!(chain.hash160(&address), Some(hash), "{address}");
+            for other in CHAINS {
+                let shared = other.pubkey_hash == chain.pubkey_hash
+                    && chain.cashaddr_prefix.is_none()
+                    || other.cashaddr_prefix.is_some()
+                        && other.cashaddr_prefix == chain.cashaddr_prefix;
+                if !shared {
+                    assert_eq!(
+                        other.hash160(&address),
+                        None,
+                        "{} read {address}",
+                        other.name
+                    );
+                }
+            }
+        }
+        let litecoin = find("ltc", Network::Bitcoin).unwrap();
+        assert!(litecoin.p2pkh(&hash).starts_with('L'));
+        assert!(find("doge", Network::Bitcoin)
+            .unwrap()
+            .p2pkh(&hash)
+            .starts_with('D'));
+        assert_eq!(find("litecoin", Network::Testnet), None);
+        assert_eq!(
+            from_header(litecoin.header_id(), Network::Bitcoin),
+            Some(litecoin)
+        );
+    }
+
+    #[test]
+    fn cashaddr_matches_the_spec_vector() {
+        let bch = find("bch", Network::Bitcoin).unwrap();
+        let legacy = bch.hash160("1BpEi6DfDAUFd7GtittLSdBeYJvcoaVggu").unwrap();
+        let cash = "bitcoincash:qpm2qsznhks23z7629mms6s4cwef74vcwvy22gdx6a";
+        assert_eq!(bch.hash160(cash), Some(legacy));
+        assert_eq!(bch.hash160(&cash["bitcoincash:".len()..]), Some(legacy));
+        assert_eq!(bch.p2pkh(&legacy), cash);
+        assert_eq!(
+            bch.hash160("bitcoincash:qpm2qsznhks23z7629mms6s4cwef74vcwvy22gdx6b"),
+            None
+        );
+    }
+
+    #[test]
+    fn litecoin_segwit_and_wif_prefixes() {
+        let hash = key1_hash();
+        let litecoin = find("litecoin", Network::Bitcoin).unwrap();
+        let data: Vec<_> = [bech32::u5::try_from_u8(0).unwrap()]
+            .into_iter()
+            .chain(bech32::ToBase32::to_base32(&hash))
+            .collect();
+        let address = bech32::encode("ltc", data, Variant::Bech32).unwrap();
+        assert_eq!(litecoin.hash160(&address), Some(hash));
+        assert_eq!(BITCOIN.hash160(&address), None);
+
+        let mut one = [0u8; 32];
+        one[31] = 1;
+        let secret = SecretKey::from_slice(&one).unwrap();
+        assert_eq!(
+            BITCOIN.wif(&secret, true),
+            bitcoin::PrivateKey::new(secret, Network::Bitcoin).to_wif()
+        );
+        assert!(litecoin.wif(&secret, true).starts_with('T'));
+        assert!(find("doge", Network::Bitcoin)
+            .unwrap()
+            .wif(&secret, true)
+            .starts_with('Q'));
+    }
+}
@@ -0,0 +1,143 @@
+//! Streaming decoders for funded-address dumps.
+//!
+//! Mirrors ship the same TSV as gzip, zstd, xz or bzip2. The format is taken
+//! from the first bytes of the file, not the URL or extension, because
+//! `source` downloads everything to the same scratch name.
+
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+
+use bzip2::read::MultiBzDecoder;
+use flate2::read::MultiGzDecoder;
+use xz2::read::XzDecoder;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Plain,
+    Gzip,
+    Zstd,
+    Xz,
+    Bzip2,
+}
+
+impl Compression {
+    pub fn detect(magic: &[u8]) -> Self {
+        if magic.starts_with(&[0x1f, 0x8b]) {
+            Compression::Gzip
+        } else if magic.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Compression::Zstd
+        } else if magic.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+            Compression::Xz
+        } else if magic.starts_with(b"BZh") {
+            Compression::Bzip2
+        } else {
+            Compression::Plain
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Compression::Plain => "plain",
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+            Compression::Xz => "xz",
+            Compression::Bzip2 => "bzip2",
+        }
+    }
+}
+
+/// Open `path` and return a reader over its decompressed bytes.
+pub fn open_dump(path: &Path) -> io::Result<(Compression, Box<dyn Read>)> {
+    let mut magic = [0u8; 6];
+    let n = {
+        let mut probe = File::open(path)?;
+        read_up_to(&mut probe, &mut magic)?
+    };
+    let kind = Compression::detect(&magic[..n]);
+    let file = BufReader::with_capacity(1 << 20, File::open(path)?);
+    let reader: Box<dyn Read> = match kind {
+        Compression::Plain => Box::new(file),
+        Compression::Gzip => Box::new(MultiGzDecoder::new(file)),
+        Compression::Zstd => Box::new(zstd::stream::read::Decoder::with_buffer(file)?),
+        Compression::Xz => Box::new(XzDecoder::new_multi_decoder(file)),
+        Compression::Bzip2 => Box::new(MultiBzDecoder::new(file)),
+    };
+    Ok((kind, reader))
+}
+
+fn read_up_to(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::unique_dir;
+    use std::fs;
+    use std::io::Write;
+    use std::path::PathBuf;
+
+    const FIXTURE: &str = "address\tbalance\n\
+        1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH\t100\n\
+        bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4\t200\n";
+
+    fn fixture_path(name: &str) -> PathBuf {
+        unique_dir("plutus-compress").join(name)
+    }
+
+    fn roundtrip(name: &str, encoded: Vec<u8>, expected: Compression) {
+        let path = fixture_path(name);
+        fs::write(&path, encoded).unwrap();
+        let (kind, mut reader) = open_dump(&path).unwrap();
+        assert_eq!(kind, expected);
+        let mut text = String::new();
+        reader.read_to_string(&mut text).unwrap();
+        assert_eq!(text, FIXTURE);
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn plain_text_passes_through() {
+        roundtrip(
+            "dump.download",
+            FIXTURE.as_bytes().to_vec(),
+            Compression::Plain,
+        );
+    }
+
+    #[test]
+    fn gzip_is_detected_by_magic() {
+        let mut enc = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        enc.write_all(FIXTURE.as_bytes()).unwrap();
+        roundtrip("dump.download", enc.finish().unwrap(), Compression::Gzip);
+    }
+
+    #[test]
+    fn zstd_is_detected_by_magic() {
+        let encoded = zstd::stream::encode_all(FIXTURE.as_bytes(), 3).unwrap();
+        roundtrip("dump.download", encoded, Compression::Zstd);
+    }
+
+    #[test]
+    fn xz_is_detected_by_magic() {
+        let mut enc = xz2::write::XzEncoder::new(Vec::new(), 6);
+        enc.write_all(FIXTURE.as_bytes()).unwrap();
+        roundtrip("dump.download", enc.finish().unwrap(), Compression::Xz);
+    }
+
+    #[test]
+    fn bzip2_is_detected_by_magic() {
+        let mut enc = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::fast());
+        enc.write_all(FIXTURE.as_bytes()).unwrap();
+        // A misleading extension must not matter.
+        roundtrip("dump.txt.gz", enc.finish().unwrap(), Compression::Bzip2);
+    }
+}
@@ -1,9 +1,14 @@
+use std::collections::BTreeMap;
 use std::env;
 use std::fs;
+use std::io;
 use std::path::{Path, PathBuf};
 
+use bitcoin::Network;
 use serde::Deserialize;
 
+use crate::chain::{self, Chain};
+
 const DEFAULT_SOURCE: &str = "http://addresses.loyce.club/Bitcoin_addresses_LATEST.txt.gz";
 const DEFAULT_PICKLE: &str = "database/JUL_12_2026";
 const DEFAULT_BARK_SERVER: &str = "https://api.day.app";
@@ -16,16 +21,27 @@ pub struct Config {
     pub cpu_percent: u8,
     pub lookup: Lookup,
     pub bits_per_key: u32,
+    /// Chain the engine derives hit addresses and WIFs for.
+    pub chain: Chain,
+    /// Chain `data update` accepts addresses for and stamps on the snapshot.
+    pub data_chain: Chain,
+    /// Further snapshots searched alongside `snapshot`, each reporting hits in
+    /// the chain recorded in its header.
+    pub extra_snapshots: Vec<PathBuf>,
     pub data_dir: PathBuf,
     pub snapshot: PathBuf,
     pub pickle_dir: PathBuf,
+    /// Leave unreadable pickle slices out instead of failing the import.
+    pub skip_bad_pickles: bool,
     pub source_url: String,
+    pub s3: S3Config,
     pub auto_update: bool,
     pub max_snapshot_age_hours: u64,
     pub findings: PathBuf,
     pub status: PathBuf,
     pub heartbeat_minutes: u64,
     pub notify: NotifyConfig,
+    pub watch: WatchConfig,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -38,11 +54,23 @@ pub enum Lookup {
     Hash,
 }
 
+/// Credentials and endpoint for `s3://bucket/key` sources. Keys are read from
+/// the named environment variables, never from the file.
+#[derive(Debug, Clone)]
+pub struct S3Config {
+    /// Empty = AWS (`https://s3.<region>.amazonaws.com`). MinIO: `http://host:9000`.
+    pub endpoint: String,
+    pub region: String,
+    pub access_key_env: String,
+    pub secret_key_env: String,
+}
+
 #[derive(Debug, Clone)]
 pub struct NotifyConfig {
     pub provider: NotifyProvider,
     pub token_env: String,
     pub webhook_url_env: String,
+    pub serverchan_key_env: String,
     pub bark_server_env: String,
     /// Seconds between hit re-alerts. `0` = send once.
     pub hit_repeat_secs: u64,
@@ -50,6 +78,34 @@ pub struct NotifyConfig {
     pub hit_repeat_max: u32,
 }
 
+/// `plutus-watch` settings. Command-line flags override these.
+#[derive(Debug, Clone)]
+pub struct WatchConfig {
+    pub watchlist: Option<PathBuf>,
+    pub descriptors: Option<PathBuf>,
+    pub rules: Option<PathBuf>,
+    /// Snapshot paths, or `electrum(s)://`, `bitcoind[://host:port]`,
+    /// `esplora+https://` for live balances. More than one is reconciled by
+    /// `quorum`.
+    pub backends: Vec<String>,
+    /// Sources that must agree on an address; `None` means a majority.
+    pub quorum: Option<usize>,
+    pub interval_secs: u64,
+    pub include_addresses: bool,
+    pub gap_limit: u32,
+    pub descriptor_depth: u32,
+    pub rpc_cookie: Option<PathBuf>,
+    pub rpc_user: Option<String>,
+    pub esplora_concurrency: usize,
+    pub esplora_requests_per_second: f64,
+    /// PEM file `electrums://` trusts instead of the public roots, such as
+    /// a self-hosted server's own certificate.
+    pub electrum_ca_file: Option<PathBuf>,
+    /// `[watch.groups.<name>]`: where notifications about a watchlist group
+    /// go, as overrides of `[notify]`.
+    pub groups: BTreeMap<String, NotifyConfig>,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum NotifyProvider {
     Disabled,
@@ -67,10 +123,20 @@ impl Default for Config {
             cpu_percent: 100,
             lookup: Lookup::Mmap,
             bits_per_key: 16,
+            chain: chain::BITCOIN,
+            data_chain: chain::BITCOIN,
+            extra_snapshots: Vec::new(),
             data_dir: PathBuf::from("data"),
             snapshot: PathBuf::from("data/addresses.h160"),
             pickle_dir: PathBuf::from(DEFAULT_PICKLE),
+            skip_bad_pickles: false,
             source_url: DEFAULT_SOURCE.to_owned(),
+            s3: S3Config {
+                endpoint: String::new(),
+                region: "us-east-1".to_owned(),
+                access_key_env: "PLUTUS_S3_ACCESS_KEY".to_owned(),
+                secret_key_env: "PLUTUS_S3_SECRET_KEY".to_owned(),
+            },
             auto_update: true,
             max_snapshot_age_hours: 30,
             findings: PathBuf::from("findings/hits.txt"),
@@ -80,10 +146,28 @@ impl Default for Config {
                 provider: NotifyProvider::Bark,
                 token_env: "PLUTUS_BARK_KEY".to_owned(),
                 webhook_url_env: "PLUTUS_WEBHOOK_URL".to_owned(),
+                serverchan_key_env: "PLUTUS_SERVERCHAN_KEY".to_owned(),
                 bark_server_env: "PLUTUS_BARK_SERVER".to_owned(),
                 hit_repeat_secs: 120,
                 hit_repeat_max: 0,
             },
+            watch: WatchConfig {
+                watchlist: None,
+                descriptors: None,
+                rules: None,
+                backends: Vec::new(),
+                quorum: None,
+                interval_secs: 6 * 60 * 60,
+                include_addresses: false,
+                gap_limit: 20,
+                descriptor_depth: 1000,
+                rpc_cookie: None,
+                rpc_user: None,
+                esplora_concurrency: 4,
+                esplora_requests_per_second: 10.0,
+                electrum_ca_file: None,
+                groups: BTreeMap::new(),
+            },
         }
     }
 }
@@ -98,6 +182,8 @@ struct FileConfig {
     run: FileRun,
     #[serde(default)]
     notify: FileNotify,
+    #[serde(default)]
+    watch: FileWatch,
 }
 
 #[derive(Debug, Default, Deserialize)]
@@ -110,16 +196,32 @@ struct FileEngine {
     cpu_percent: Option<u8>,
     lookup: Option<String>,
     bits_per_key: Option<u32>,
+    chain: Option<String>,
+    network: Option<String>,
 }
 
 #[derive(Debug, Default, Deserialize)]
 struct FileData {
+    chain: Option<String>,
+    network: Option<String>,
+    extra_snapshots: Option<Vec<String>>,
     directory: Option<String>,
     snapshot: Option<String>,
     pickle_dir: Option<String>,
+    skip_bad_pickles: Option<bool>,
     source_url: Option<String>,
     auto_update: Option<bool>,
     max_snapshot_age_hours: Option<u64>,
+    #[serde(default)]
+    s3: FileS3,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct FileS3 {
+    endpoint: Option<String>,
+    region: Option<String>,
+    access_key_env: Option<String>,
+    secret_key_env: Option<String>,
 }
 
 #[derive(Debug, Default, Deserialize)]
@@ -134,30 +236,80 @@ struct FileNotify {
     provider: Option<String>,
     token_env: Option<String>,
     webhook_url_env: Option<String>,
+    serverchan_key_env: Option<String>,
     bark_server_env: Option<String>,
     hit_repeat_secs: Option<u64>,
     hit_repeat_max: Option<u32>,
 }
 
-pub fn load() -> Config {
-    let path = env::var("PLUTUS_CONFIG")
+#[derive(Debug, Default, Deserialize)]
+struct FileWatch {
+    watchlist: Option<String>,
+    descriptors: Option<String>,
+    rules: Option<String>,
+    backend: Option<String>,
+    backends: Option<Vec<String>>,
+    quorum: Option<usize>,
+    interval_seconds: Option<u64>,
+    include_addresses: Option<bool>,
+    gap_limit: Option<u32>,
+    descriptor_depth: Option<u32>,
+    rpc_cookie: Option<String>,
+    rpc_user: Option<String>,
+    esplora_concurrency: Option<usize>,
+    esplora_requests_per_second: Option<f64>,
+    electrum_ca_file: Option<String>,
+    #[serde(default)]
+    groups: BTreeMap<String, FileNotify>,
+}
+
+pub fn load() -> Result<Config, String> {
+    load_from_path(&config_path())
+}
+
+/// Re-read the config for a running engine. Unlike [`load`], a file that
+/// exists but cannot be read or parsed is an error, so a bad edit keeps the
+/// settings already in use instead of falling back to defaults.
+pub fn reload() -> Result<Config, String> {
+    let path = config_path();
+    let mut cfg = Config::default();
+    match fs::read_to_string(&path) {
+        Ok(raw) => {
+            let file = toml::from_str::<FileConfig>(&raw)
+                .map_err(|error| format!("{}: {error}", path.display()))?;
+            apply_file(&mut cfg, file).map_err(|error| format!("{}: {error}", path.display()))?;
+        }
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+        Err(error) => return Err(format!("{}: {error}", path.display())),
+    }
+    apply_env(&mut cfg);
+    Ok(cfg)
+}
+
+fn config_path() -> PathBuf {
+    env::var("PLUTUS_CONFIG")
         .map(PathBuf::from)
-        .unwrap_or_else(|_| PathBuf::from("config.toml"));
-    load_from_path(&path)
+        .unwrap_or_else(|_| PathBuf::from("config.toml"))
 }
 
-pub fn load_from_path(path: &Path) -> Config {
+/// A file that cannot be parsed is ignored with a warning; one that parses
+/// but names an unknown or conflicting chain is an error, since defaults
+/// would scan the wrong chain.
+pub fn load_from_path(path: &Path) -> Result<Config, String> {
     let mut cfg = Config::default();
     if let Ok(raw) = fs::read_to_string(path) {
         match toml::from_str::<FileConfig>(&raw) {
-            Ok(file) => apply_file(&mut cfg, file),
+            Ok(file) => {
+                apply_file(&mut cfg, file)
+                    .map_err(|error| format!("{}: {error}", path.display()))?;
+            }
             Err(error) => {
                 eprintln!("warning: ignoring {}: {error}", path.display());
             }
         }
     }
     apply_env(&mut cfg);
-    cfg
+    Ok(cfg)
 }
 
 fn apply_profile(cfg: &mut Config, profile: &str) {
@@ -187,7 +339,9 @@ fn apply_profile(cfg: &mut Config, profile: &str) {
     }
 }
 
-fn apply_file(cfg: &mut Config, file: FileConfig) {
+/// Fails when a chain or network is unknown, or when `[engine]` and `[data]`
+/// name different ones.
+fn apply_file(cfg: &mut Config, file: FileConfig) -> Result<(), String> {
     if let Some(profile) = file.engine.profile.as_deref() {
         apply_profile(cfg, profile);
     }
@@ -211,6 +365,43 @@ fn apply_file(cfg: &mut Config, file: FileConfig) {
     if let Some(bits) = file.engine.bits_per_key {
         cfg.bits_per_key = bits.clamp(8, 32);
     }
+    // Either section alone sets both. Setting them apart is refused: the
+    // engine would reject every snapshot built for the data chain.
+    let engine_network = file
+        .engine
+        .network
+        .as_deref()
+        .map(parse_network)
+        .transpose()?;
+    let data_network = file
+        .data
+        .network
+        .as_deref()
+        .map(parse_network)
+        .transpose()?;
+    let engine_family = file.engine.chain.as_deref();
+    let data_family = file.data.chain.as_deref();
+    cfg.chain = resolve_chain(
+        engine_family.or(data_family),
+        engine_network.or(data_network),
+    )?;
+    cfg.data_chain = resolve_chain(
+        data_family.or(engine_family),
+        data_network.or(engine_network),
+    )?;
+    if cfg.data_chain != cfg.chain {
+        return Err(format!(
+            "[engine] selects {} but [data] selects {}; set chain and network in one of them",
+            cfg.chain.name, cfg.data_chain.name
+        ));
+    }
+    if let Some(extra) = file.data.extra_snapshots {
+        cfg.extra_snapshots = extra
+            .into_iter()
+            .filter(|s| !s.is_empty())
+            .map(PathBuf::from)
+            .collect();
+    }
     if let Some(dir) = file.data.directory {
         cfg.data_dir = PathBuf::from(dir);
     }
@@ -220,11 +411,28 @@ fn apply_file(cfg: &mut Config, file: FileConfig) {
     if let Some(pickle) = file.data.pickle_dir {
         cfg.pickle_dir = PathBuf::from(pickle);
     }
+    if let Some(skip) = file.data.skip_bad_pickles {
+        cfg.skip_bad_pickles = skip;
+    }
     if let Some(url) = file.data.source_url {
         if !url.is_empty() {
             cfg.source_url = url;
         }
     }
+    if let Some(endpoint) = file.data.s3.endpoint {
+        cfg.s3.endpoint = endpoint;
+    }
+    if let Some(region) = file.data.s3.region {
+        if !region.is_empty() {
+            cfg.s3.region = region;
+        }
+    }
+    if let Some(access_env) = file.data.s3.access_key_env {
+        cfg.s3.access_key_env = access_env;
+    }
+    if let Some(secret_env) = file.data.s3.secret_key_env {
+        cfg.s3.secret_key_env = secret_env;
+    }
     if let Some(auto_update) = file.data.auto_update {
         cfg.auto_update = auto_update;
     }
@@ -244,23 +452,103 @@ fn apply_file(cfg: &mut Config, file: FileConfig) {
             cfg.heartbeat_minutes = minutes;
         }
     }
-    if let Some(provider) = file.notify.provider {
-        cfg.notify.provider = parse_provider(&provider);
+    apply_notify(&mut cfg.notify, file.notify);
+    apply_watch(&mut cfg.watch, &cfg.notify, file.watch);
+    Ok(())
+}
+
+fn apply_notify(notify: &mut NotifyConfig, file: FileNotify) {
+    if let Some(provider) = file.provider {
+        notify.provider = parse_provider(&provider);
+    }
+    if let Some(token_env) = file.token_env {
+        notify.token_env = token_env;
+    }
+    if let Some(webhook_env) = file.webhook_url_env {
+        notify.webhook_url_env = webhook_env;
+    }
+    if let Some(serverchan_env) = file.serverchan_key_env {
+        notify.serverchan_key_env = serverchan_env;
+    }
+    if let Some(server_env) = file.bark_server_env {
+        notify.bark_server_env = server_env;
+    }
+    if let Some(secs) = file.hit_repeat_secs {
+        notify.hit_repeat_secs = secs;
+    }
+    if let Some(max) = file.hit_repeat_max {
+        notify.hit_repeat_max = max;
+    }
+}
+
+fn apply_watch(watch: &mut WatchConfig, notify: &NotifyConfig, file: FileWatch) {
+    for (group, overrides) in file.groups {
+        let mut routed = notify.clone();
+        apply_notify(&mut routed, overrides);
+        watch.groups.insert(group, routed);
+    }
+    let path = |value: String| Some(value).filter(|s| !s.is_empty()).map(home_relative);
+    if let Some(watchlist) = file.watchlist {
+        watch.watchlist = path(watchlist);
+    }
+    if let Some(descriptors) = file.descriptors {
+        watch.descriptors = path(descriptors);
+    }
+    if let Some(rules) = file.rules {
+        watch.rules = path(rules);
+    }
+    let backends = file.backends.or_else(|| file.backend.map(|b| vec![b]));
+    if let Some(backends) = backends {
+        watch.backends = backends.into_iter().filter(|b| !b.is_empty()).collect();
+    }
+    if let Some(quorum) = file.quorum {
+        watch.quorum = Some(quorum).filter(|&q| q > 0);
+    }
+    if let Some(secs) = file.interval_seconds {
+        if secs > 0 {
+            watch.interval_secs = secs;
+        }
+    }
+    if let Some(include) = file.include_addresses {
+        watch.include_addresses = include;
     }
-    if let Some(token_env) = file.notify.token_env {
-        cfg.notify.token_env = token_env;
+    if let Some(gap) = file.gap_limit {
+        if gap > 0 {
+            watch.gap_limit = gap;
+        }
     }
-    if let Some(webhook_env) = file.notify.webhook_url_env {
-        cfg.notify.webhook_url_env = webhook_env;
+    if let Some(depth) = file.descriptor_depth {
+        if depth > 0 {
+            watch.descriptor_depth = depth;
+        }
+    }
+    if let Some(cookie) = file.rpc_cookie {
+        watch.rpc_cookie = path(cookie);
     }
-    if let Some(server_env) = file.notify.bark_server_env {
-        cfg.notify.bark_server_env = server_env;
+    if let Some(user) = file.rpc_user {
+        watch.rpc_user = Some(user).filter(|s| !s.is_empty());
     }
-    if let Some(secs) = file.notify.hit_repeat_secs {
-        cfg.notify.hit_repeat_secs = secs;
+    if let Some(workers) = file.esplora_concurrency {
+        if workers > 0 {
+            watch.esplora_concurrency = workers;
+        }
     }
-    if let Some(max) = file.notify.hit_repeat_max {
-        cfg.notify.hit_repeat_max = max;
+    if let Some(rate) = file.esplora_requests_per_second {
+        if rate.is_finite() && rate > 0.0 {
+            watch.esplora_requests_per_second = rate;
+        }
+    }
+    if let Some(ca_file) = file.electrum_ca_file {
+        watch.electrum_ca_file = path(ca_file);
+    }
+}
+
+/// Expand a leading `~/`, as a shell would for the same path on the command
+/// line. Left as is when `HOME` is unset.
+fn home_relative(value: String) -> PathBuf {
+    match (value.strip_prefix("~/"), env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(value),
     }
 }
 
@@ -280,6 +568,30 @@ fn apply_env(cfg: &mut Config) {
     }
 }
 
+fn parse_network(value: &str) -> Result<Network, String> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "bitcoin" | "mainnet" | "main" => Ok(Network::Bitcoin),
+        "testnet" | "testnet3" | "test" => Ok(Network::Testnet),
+        "signet" => Ok(Network::Signet),
+        "regtest" => Ok(Network::Regtest),
+        other => Err(format!(
+            "unknown network {other:?} (bitcoin, testnet, signet or regtest)"
+        )),
+    }
+}
+
+fn resolve_chain(family: Option<&str>, network: Option<Network>) -> Result<Chain, String> {
+    let network = network.unwrap_or(Network::Bitcoin);
+    let family = family.unwrap_or("bitcoin");
+    if let Some(chain) = chain::find(family, network) {
+        return Ok(chain);
+    }
+    if chain::find(family, Network::Bitcoin).is_some() {
+        return Err(format!("{family} has no {network} network"));
+    }
+    Err(format!("unknown chain {family:?}"))
+}
+
 fn parse_provider(value: &str) -> NotifyProvider {
     match value.trim().to_ascii_lowercase().as_str() {
         "bark" => NotifyProvider::Bark,
@@ -347,11 +659,11 @@ pub fn ram_hint_mb(cfg: &Config) -> u64 {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::time::{SystemTime, UNIX_EPOCH};
+    use crate::test_util::unique_dir;
 
     #[test]
     fn missing_file_uses_product_defaults() {
-        let cfg = load_from_path(Path::new("definitely-missing-plutus-config.toml"));
+        let cfg = load_from_path(Path::new("definitely-missing-plutus-config.toml")).unwrap();
         assert!(cfg.check_uncompressed);
         assert_eq!(cfg.walk_span, 1 << 30);
         assert_eq!(cfg.notify.provider, NotifyProvider::Bark);
@@ -377,24 +689,134 @@ mod tests {
 
     #[test]
     fn notify_repeat_from_file() {
-        let unique = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_nanos();
-        let dir = std::env::temp_dir().join(format!("plutus-cfg-{unique}"));
-        fs::create_dir_all(&dir).unwrap();
+        let dir = unique_dir("plutus-cfg");
         let path = dir.join("config.toml");
         fs::write(
             &path,
             "[notify]\nhit_repeat_secs = 30\nhit_repeat_max = 0\n",
         )
         .unwrap();
-        let cfg = load_from_path(&path);
+        let cfg = load_from_path(&path).unwrap();
         assert_eq!(cfg.notify.hit_repeat_secs, 30);
         assert_eq!(cfg.notify.hit_repeat_max, 0);
         let _ = fs::remove_dir_all(dir);
     }
 
+    #[test]
+    fn s3_section_nests_under_data() {
+        let dir = unique_dir("plutus-cfg-s3");
+        let path = dir.join("config.toml");
+        fs::write(
+            &path,
+            "[data]\nsource_url = \"s3://dumps/btc.txt.gz\"\n\n[data.s3]\nendpoint = \"http://minio:9000\"\n",
+        )
+        .unwrap();
+        let cfg = load_from_path(&path).unwrap();
+        assert_eq!(cfg.source_url, "s3://dumps/btc.txt.gz");
+        assert_eq!(cfg.s3.endpoint, "http://minio:9000");
+        assert_eq!(cfg.s3.region, "us-east-1");
+        assert_eq!(cfg.s3.access_key_env, "PLUTUS_S3_ACCESS_KEY");
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn one_chain_setting_covers_engine_and_data() {
+        let dir = unique_dir("plutus-cfg-network");
+        let path = dir.join("config.toml");
+        fs::write(&path, "[engine]\nnetwork = \"regtest\"\n").unwrap();
+        let cfg = load_from_path(&path).unwrap();
+        assert_eq!(cfg.chain.network, Network::Regtest);
+        assert_eq!(cfg.data_chain.name, "regtest");
+
+        fs::write(
+            &path,
+            "[engine]\nnetwork = \"signet\"\n[data]\nnetwork = \"signet\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            load_from_path(&path).unwrap().data_chain.network,
+            Network::Signet
+        );
+
+        // Engine and data on different chains is a broken file, not a mix.
+        fs::write(
+            &path,
+            "[engine]\nnetwork = \"signet\"\nthreads = 3\n[data]\nnetwork = \"testnet\"\n",
+        )
+        .unwrap();
+        let error = load_from_path(&path).unwrap_err();
+        assert!(
+            error.contains("signet") && error.contains("testnet"),
+            "{error}"
+        );
+        assert_eq!(Config::default().chain, chain::BITCOIN);
+
+        // Unknown names are refused too, not replaced by mainnet.
+        for bad in [
+            "[engine]\nnetwork = \"testnet4x\"\n",
+            "[data]\nchain = \"dogecash\"\n",
+            "[data]\nchain = \"litecoin\"\nnetwork = \"signet\"\n",
+        ] {
+            fs::write(&path, bad).unwrap();
+            assert!(load_from_path(&path).is_err(), "{bad}");
+        }
+
+        fs::write(
+            &path,
+            "[data]\nchain = \"ltc\"\nextra_snapshots = [\"./data/doge.h160\"]\n",
+        )
+        .unwrap();
+        let cfg = load_from_path(&path).unwrap();
+        assert_eq!(cfg.chain.name, "litecoin");
+        assert_eq!(cfg.data_chain.name, "litecoin");
+        assert_eq!(cfg.extra_snapshots, [PathBuf::from("./data/doge.h160")]);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn watch_section_overrides_defaults() {
+        let dir = unique_dir("plutus-cfg-watch");
+        let path = dir.join("config.toml");
+        fs::write(
+            &path,
+            "[watch]\nwatchlist = \"watch/list.txt\"\nbackend = \"electrum://127.0.0.1:50001\"\ninterval_seconds = 600\ngap_limit = 0\nrpc_cookie = \"~/.bitcoin/.cookie\"\n",
+        )
+        .unwrap();
+        let cfg = load_from_path(&path).unwrap();
+        assert_eq!(cfg.watch.watchlist, Some(PathBuf::from("watch/list.txt")));
+        assert_eq!(cfg.watch.backends, ["electrum://127.0.0.1:50001"]);
+        assert_eq!(cfg.watch.interval_secs, 600);
+        assert_eq!(cfg.watch.gap_limit, 20);
+        assert!(cfg.watch.descriptors.is_none());
+        if let Some(home) = std::env::var_os("HOME") {
+            assert_eq!(
+                cfg.watch.rpc_cookie,
+                Some(PathBuf::from(home).join(".bitcoin/.cookie"))
+            );
+        }
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn watch_groups_override_notify() {
+        let dir = unique_dir("plutus-cfg-groups");
+        let path = dir.join("config.toml");
+        fs::write(
+            &path,
+            "[notify]\nprovider = \"bark\"\nhit_repeat_secs = 30\n\n[watch.groups.finance]\nprovider = \"webhook\"\nwebhook_url_env = \"FINANCE_WEBHOOK\"\n\n[watch.groups.ops]\nprovider = \"serverchan\"\nserverchan_key_env = \"OPS_SERVERCHAN\"\n",
+        )
+        .unwrap();
+        let cfg = load_from_path(&path).unwrap();
+        let finance = &cfg.watch.groups["finance"];
+        assert_eq!(finance.provider, NotifyProvider::Webhook);
+        assert_eq!(finance.webhook_url_env, "FINANCE_WEBHOOK");
+        assert_eq!(finance.hit_repeat_secs, 30);
+        assert_eq!(cfg.watch.groups["ops"].serverchan_key_env, "OPS_SERVERCHAN");
+        assert_eq!(cfg.notify.serverchan_key_env, "PLUTUS_SERVERCHAN_KEY");
+        assert_eq!(cfg.notify.provider, NotifyProvider::Bark);
+        let _ = fs::remove_dir_all(dir);
+    }
+
     #[test]
     fn mmap_ram_hint_is_far_below_sorted_table() {
         let cfg = Config::default();
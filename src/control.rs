@@ -0,0 +1,187 @@
+//! Runtime requests to a running engine.
+//!
+//! On Unix, SIGUSR1 pauses the workers, SIGUSR2 resumes them and SIGHUP
+//! re-reads `config.toml`. Where signals are awkward, `plutus-rustus control
+//! pause|resume|reload` appends the same request to `control` in `data.dir`.
+//! The run loop takes both on every status tick, signals first, and deletes
+//! the file once read.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+const CONTROL_FILE: &str = "control";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Request {
+    Pause,
+    Resume,
+    /// Re-read `config.toml` and apply what can change without a restart.
+    Reload,
+}
+
+impl Request {
+    pub fn parse(value: &str) -> Option<Request> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "pause" => Some(Request::Pause),
+            "resume" => Some(Request::Resume),
+            "reload" => Some(Request::Reload),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Request::Pause => "pause",
+            Request::Resume => "resume",
+            Request::Reload => "reload",
+        }
+    }
+}
+
+pub fn control_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(CONTROL_FILE)
+}
+
+/// Queue `request` for the engine running over `data_dir`.
+pub fn send(data_dir: &Path, request: Request) -> io::Result<()> {
+    fs::create_dir_all(data_dir)?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(control_path(data_dir))?;
+    writeln!(file, "{}", request.as_str())
+}
+
+/// Route SIGUSR1, SIGUSR2 and SIGHUP to [`take`]. A no-op off Unix.
+pub fn install_signal_handlers() -> io::Result<()> {
+    signals::install()
+}
+
+/// Requests that arrived since the last call: signals, then the control
+/// file in the order it was written. Unknown lines are reported and skipped.
+pub fn take(data_dir: &Path) -> Vec<Request> {
+    let mut requests = signals::take();
+    match take_file(data_dir) {
+        Ok(lines) => {
+            for line in lines.iter().filter(|line| !line.trim().is_empty()) {
+                match Request::parse(line) {
+                    Some(request) => requests.push(request),
+                    None => eprintln!("control: ignoring unknown request {line:?}"),
+                }
+            }
+        }
+        Err(error) => eprintln!("control file: {error}"),
+    }
+    requests
+}
+
+/// Move the file aside before reading it, so a request appended meanwhile
+/// lands in a fresh file instead of being deleted unread.
+fn take_file(data_dir: &Path) -> io::Result<Vec<String>> {
+    let path = control_path(data_dir);
+    let taken = path.with_extension("taking");
+    match fs::rename(&path, &taken) {
+        Ok(()) => {}
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(error),
+    }
+    let body = fs::read_to_string(&taken)?;
+    fs::remove_file(&taken)?;
+    Ok(body.lines().map(str::to_owned).collect())
+}
+
+#[cfg(unix)]
+mod signals {
+    use std::io;
+    use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+
+    use super::Request;
+
+    /// Last of SIGUSR1/SIGUSR2 seen: 0 none, 1 pause, 2 resume.
+    static PAUSE: AtomicU8 = AtomicU8::new(0);
+    static RELOAD: AtomicBool = AtomicBool::new(false);
+
+    // Only atomic stores: anything else is not async-signal-safe.
+    extern "C" fn handle(signal: libc::c_int) {
+        match signal {
+            libc::SIGUSR1 => PAUSE.store(1, Ordering::Relaxed),
+            libc::SIGUSR2 => PAUSE.store(2, Ordering::Relaxed),
+            libc::SIGHUP => RELOAD.store(true, Ordering::Relaxed),
+            _ => {}
+        }
+    }
+
+    pub fn install() -> io::Result<()> {
+        let handler = handle as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        for signal in [libc::SIGUSR1, libc::SIGUSR2, libc::SIGHUP] {
+            if unsafe { libc::signal(signal, handler) } == libc::SIG_ERR {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn take() -> Vec<Request> {
+        let mut requests = Vec::new();
+        match PAUSE.swap(0, Ordering::Relaxed) {
+            1 => requests.push(Request::Pause),
+            2 => requests.push(Request::Resume),
+            _ => {}
+        }
+        if RELOAD.swap(false, Ordering::Relaxed) {
+            requests.push(Request::Reload);
+        }
+        requests
+    }
+}
+
+#[cfg(not(unix))]
+mod signals {
+    use std::io;
+
+    use super::Request;
+
+    pub fn install() -> io::Result<()> {
+        Ok(())
+    }
+
+    pub fn take() -> Vec<Request> {
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::unique_dir;
+
+    #[test]
+    fn signals_and_control_file_arrive_in_order() {
+        let dir = unique_dir("plutus-control");
+        send(&dir, Request::Pause).unwrap();
+        send(&dir, Request::Reload).unwrap();
+        fs::write(
+            control_path(&dir),
+            fs::read_to_string(control_path(&dir)).unwrap() + "bogus\nresume\n",
+        )
+        .unwrap();
+
+        install_signal_handlers().unwrap();
+        #[cfg(unix)]
+        unsafe {
+            libc::raise(libc::SIGUSR1);
+            libc::raise(libc::SIGUSR2);
+            libc::raise(libc::SIGHUP);
+        }
+        let mut expected = Vec::new();
+        if cfg!(unix) {
+            expected.extend([Request::Resume, Request::Reload]);
+        }
+        expected.extend([Request::Pause, Request::Reload, Request::Resume]);
+        assert_eq!(take(&dir), expected);
+        assert!(!control_path(&dir).exists());
+        assert_eq!(take(&dir), []);
+        let _ = fs::remove_dir_all(dir);
+    }
+}
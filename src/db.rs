@@ -1,6 +1,7 @@
 use std::cell::RefCell;
 use std::cmp::Reverse;
 use std::collections::{BinaryHeap, HashSet};
+use std::fmt;
 use std::fs::{self, File};
 use std::hash::{BuildHasherDefault, Hasher};
 use std::io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
@@ -9,11 +10,15 @@ use std::str::FromStr;
 use std::thread;
 use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
-use bitcoin::Address;
-use flate2::read::GzDecoder;
+use bitcoin::{Address, AddressType, Network};
+use serde::de::{Deserializer as _, SeqAccess, Visitor};
+use serde::Serialize;
 
 use crate::bloom::Bloom;
+use crate::chain::{self, Chain};
+use crate::compress;
 use crate::config::{Config, Lookup};
+use crate::source::{self, SnapshotSource};
 
 const MAGIC_V1: &[u8; 4] = b"PLH1";
 const MAGIC_V2: &[u8; 4] = b"PLH2";
@@ -98,6 +103,28 @@ impl Db {
         }
     }
 
+    /// `contains` with the intermediate steps exposed, for `data query`.
+    pub fn probe(&self, hash: &[u8; 20]) -> Probe {
+        let bucket = bucket_of(hash) as u16;
+        match self {
+            Db::Mmap(disk) => {
+                let bloom = disk.bloom.maybe_contains(hash);
+                Probe {
+                    bloom: Some(bloom),
+                    bucket,
+                    bucket_records: Some(disk.buckets[bucket as usize].1),
+                    found: bloom && disk.contains(hash),
+                }
+            }
+            _ => Probe {
+                bloom: None,
+                bucket,
+                bucket_records: None,
+                found: self.contains(hash),
+            },
+        }
+    }
+
     fn from_hashes(mut hashes: Vec<[u8; 20]>, lookup: Lookup) -> Self {
         hashes.sort_unstable();
         hashes.dedup();
@@ -150,51 +177,239 @@ impl DiskDb {
     }
 }
 
+/// What each lookup stage said about one hash160. Bloom and bucket size are
+/// only known for the mmap lookup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Probe {
+    pub bloom: Option<bool>,
+    pub bucket: u16,
+    pub bucket_records: Option<u32>,
+    pub found: bool,
+}
+
+/// One `data query` answer.
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryResult {
+    pub input: String,
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub hash160: Option<String>,
+    pub bloom: Option<bool>,
+    pub bucket: Option<String>,
+    pub bucket_records: Option<u32>,
+    pub found: bool,
+}
+
 pub struct LoadReport {
     pub db: Db,
     pub skipped: u64,
     pub source: String,
     pub elapsed: std::time::Duration,
+    /// Per-slice counts for pickle imports; empty for snapshot loads.
+    pub files: Vec<FileCount>,
+    /// Slices left out because `data.skip_bad_pickles` is on.
+    pub bad_files: u64,
+}
+
+/// Records read from one pickle slice.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileCount {
+    pub path: PathBuf,
+    pub records: u64,
+    /// Addresses that are not P2PKH/P2WPKH.
+    pub skipped: u64,
+    /// Why the slice was skipped, if it was.
+    pub error: Option<String>,
 }
 
-/// Decode a funded address to the 20-byte hash160 the hot loop matches against,
-/// or `None` if it is a type this generator can never produce.
+/// Decode a funded Bitcoin mainnet address to the 20-byte hash160 the hot
+/// loop matches against, or `None` if it is a type this generator can never
+/// produce. Other chains and networks go through [`Chain::hash160`].
 pub fn address_hash160(addr: &str) -> Option<[u8; 20]> {
-    match addr.as_bytes().first() {
-        Some(b'1') => {
-            let raw = bitcoin::base58::decode_check(addr).ok()?;
-            if raw.len() == 21 && raw[0] == 0x00 {
-                let mut h = [0u8; 20];
-                h.copy_from_slice(&raw[1..21]);
-                return Some(h);
-            }
-            None
+    chain::BITCOIN.hash160(addr)
+}
+
+/// Header byte 44. Zero is mainnet, which every snapshot written before the
+/// field existed holds.
+fn network_byte(network: Network) -> u8 {
+    match network {
+        Network::Testnet => 1,
+        Network::Signet => 2,
+        Network::Regtest => 3,
+        _ => 0,
+    }
+}
+
+/// The chain in header bytes 44 (network) and 45 (chain); both are zero on
+/// snapshots written before either existed, which hold Bitcoin mainnet.
+fn header_chain(header: &[u8; HEADER_LEN], path: &Path) -> io::Result<Chain> {
+    let network = match header[44] {
+        0 => Network::Bitcoin,
+        1 => Network::Testnet,
+        2 => Network::Signet,
+        3 => Network::Regtest,
+        other => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: unknown network byte {other}", path.display()),
+            ))
         }
-        Some(b'b') if addr.starts_with("bc1") => {
-            let addr = Address::from_str(addr).ok()?.assume_checked();
-            let spk = addr.script_pubkey();
-            let b = spk.as_bytes();
-            if b.len() == 22 && b[0] == 0x00 && b[1] == 0x14 {
-                let mut h = [0u8; 20];
-                h.copy_from_slice(&b[2..22]);
-                return Some(h);
+    };
+    chain::from_header(header[45], network).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "{}: unknown chain byte {} on {network}",
+                path.display(),
+                header[45]
+            ),
+        )
+    })
+}
+
+/// Fail unless the snapshot at `path` was built for `chain`.
+pub fn require_chain(path: &Path, chain: Chain) -> io::Result<()> {
+    let found = read_header(path)?.chain;
+    if found == chain {
+        return Ok(());
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!(
+            "{} holds {} addresses but the engine is set to {}; \
+             set data.chain/data.network to match and run `plutus-rustus data update`",
+            path.display(),
+            found.name,
+            chain.name
+        ),
+    ))
+}
+
+/// Load every `data.extra_snapshots` entry with the chain its header records.
+/// Two sets of one chain, including the main snapshot's, are refused: hits
+/// are tagged by chain name.
+pub fn load_extra(cfg: &Config) -> io::Result<Vec<(Chain, LoadReport)>> {
+    let mut loaded: Vec<(Chain, LoadReport)> = Vec::new();
+    for path in &cfg.extra_snapshots {
+        let chain = read_header(path)
+            .map_err(|e| io::Error::new(e.kind(), format!("{}: {e}", path.display())))?
+            .chain;
+        if chain == cfg.chain || loaded.iter().any(|(seen, _)| *seen == chain) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "{}: a {} snapshot is already loaded; keep one per chain",
+                    path.display(),
+                    chain.name
+                ),
+            ));
+        }
+        loaded.push((
+            chain,
+            load_snapshot_with(path, cfg.lookup, cfg.bits_per_key)?,
+        ));
+    }
+    Ok(loaded)
+}
+
+/// Address type as the importer sees it. Only `p2pkh`, `p2wpkh` and raw
+/// `hash160` inputs can ever be in the snapshot.
+pub fn address_kind(input: &str) -> &'static str {
+    if parse_hash160_hex(input).is_some() {
+        return "hash160";
+    }
+    let Ok(addr) = Address::from_str(input) else {
+        return "invalid";
+    };
+    match addr.assume_checked().address_type() {
+        Some(AddressType::P2pkh) => "p2pkh",
+        Some(AddressType::P2sh) => "p2sh",
+        Some(AddressType::P2wpkh) => "p2wpkh",
+        Some(AddressType::P2wsh) => "p2wsh",
+        Some(AddressType::P2tr) => "p2tr",
+        _ => "unknown",
+    }
+}
+
+fn parse_hash160_hex(input: &str) -> Option<[u8; 20]> {
+    if input.len() != 40 {
+        return None;
+    }
+    let mut out = [0u8; 20];
+    for (i, byte) in out.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(input.get(i * 2..i * 2 + 2)?, 16).ok()?;
+    }
+    Some(out)
+}
+
+/// Run one address or 40-hex hash160 through the same decode and lookup the
+/// engine uses, for a snapshot of `chain`.
+pub fn query(db: &Db, input: &str, chain: Chain) -> QueryResult {
+    let input = input.trim();
+    let hash = parse_hash160_hex(input).or_else(|| chain.hash160(input));
+    // `address_kind` only parses Bitcoin; another chain's address that
+    // decoded is one of the two hash160 types.
+    let kind = match (address_kind(input), hash) {
+        ("invalid", Some(_)) => {
+            let segwit = chain.segwit_hrp.is_some_and(|hrp| {
+                input.len() > hrp.len()
+                    && input[..=hrp.len()].eq_ignore_ascii_case(&format!("{hrp}1"))
+            });
+            if segwit {
+                "p2wpkh"
+            } else {
+                "p2pkh"
             }
-            None
         }
-        _ => None,
+        (kind, _) => kind,
+    };
+    let Some(hash) = hash else {
+        return QueryResult {
+            input: input.to_owned(),
+            kind,
+            hash160: None,
+            bloom: None,
+            bucket: None,
+            bucket_records: None,
+            found: false,
+        };
+    };
+    let probe = db.probe(&hash);
+    QueryResult {
+        input: input.to_owned(),
+        kind,
+        hash160: Some(source::hex(&hash)),
+        bloom: probe.bloom,
+        bucket: Some(format!("{:04x}", probe.bucket)),
+        bucket_records: probe.bucket_records,
+        found: probe.found,
     }
 }
 
+/// Load the engine's set, refusing a snapshot built for another chain than
+/// `engine.chain` on `engine.network`. Bundled pickles are Bitcoin mainnet.
 pub fn load(cfg: &Config) -> io::Result<LoadReport> {
     if cfg.snapshot.is_file() {
+        require_chain(&cfg.snapshot, cfg.chain)?;
         return load_snapshot_with(&cfg.snapshot, cfg.lookup, cfg.bits_per_key);
     }
     if cfg.pickle_dir.is_dir() {
+        if cfg.chain != chain::BITCOIN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "no snapshot at {} and the bundled pickles are bitcoin, not {}; \
+                     run `plutus-rustus data update`",
+                    cfg.snapshot.display(),
+                    cfg.chain.name
+                ),
+            ));
+        }
         let mut report = load_pickles(cfg)?;
         if matches!(report.db, Db::Mmap(_)) {
             return Ok(report);
         }
-        match write_snapshot(&cfg.snapshot, &report.db, cfg.bits_per_key) {
+        match write_snapshot(&cfg.snapshot, &report.db, cfg.bits_per_key, chain::BITCOIN) {
             Ok(()) => {
                 println!(
                     "Wrote binary snapshot {} ({} hash160s, ram~{}MB) for fast restarts",
@@ -266,6 +481,8 @@ pub fn load_snapshot_with(
                     skipped: 0,
                     source: path.display().to_string(),
                     elapsed: timer.elapsed(),
+                    files: Vec::new(),
+                    bad_files: 0,
                 });
             }
             Ok(LoadReport {
@@ -273,6 +490,8 @@ pub fn load_snapshot_with(
                 skipped: 0,
                 source: path.display().to_string(),
                 elapsed: timer.elapsed(),
+                files: Vec::new(),
+                bad_files: 0,
             })
         }
         Lookup::Sorted | Lookup::Hash => {
@@ -283,11 +502,25 @@ pub fn load_snapshot_with(
                 skipped: 0,
                 source: path.display().to_string(),
                 elapsed: timer.elapsed(),
+                files: Vec::new(),
+                bad_files: 0,
             })
         }
     }
 }
 
+/// Open a PLH1/PLH2 snapshot for lookups only. Unlike [`load_snapshot_with`]
+/// this never converts or rebuilds the file, so readers that do not own the
+/// snapshot can share it with a running engine. PLH1 is read into RAM.
+pub fn open_snapshot_read_only(path: &Path) -> io::Result<Db> {
+    let mut header = [0u8; HEADER_LEN];
+    File::open(path)?.read_exact(&mut header)?;
+    if header[0..4] == MAGIC_V2[..] {
+        return Ok(Db::Mmap(load_plh2(path)?));
+    }
+    Ok(Db::from_hashes(read_all_records(path)?, Lookup::Sorted))
+}
+
 fn load_plh2(path: &Path) -> io::Result<DiskDb> {
     let mut file = File::open(path)?;
     let mut header = [0u8; HEADER_LEN];
@@ -360,6 +593,7 @@ pub fn inspect_snapshot(path: &Path) -> io::Result<String> {
     let version = u16::from_le_bytes(header[4..6].try_into().unwrap());
     let count = u64::from_le_bytes(header[8..16].try_into().unwrap());
     let created = u64::from_le_bytes(header[16..24].try_into().unwrap());
+    let chain = header_chain(&header, path)?;
     let age = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .map(|d| d.as_secs().saturating_sub(created))
@@ -375,18 +609,40 @@ pub fn inspect_snapshot(path: &Path) -> io::Result<String> {
         );
     }
     Ok(format!(
-        "snapshot={}\nsize_bytes={}\ncount={count}\ncreated_unix={created}\nage_hours={:.1}\nmagic={magic}\nversion={version}{extra}",
+        "snapshot={}\nsize_bytes={}\ncount={count}\ncreated_unix={created}\nage_hours={:.1}\nmagic={magic}\nversion={version}\nchain={}\nnetwork={}{extra}",
         path.display(),
         meta.len(),
         age as f64 / 3600.0,
+        chain.family,
+        chain.network,
     ))
 }
 
-pub fn snapshot_age_secs(path: &Path) -> io::Result<u64> {
+/// The fields every PLH1/PLH2 header shares.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotHeader {
+    pub magic: String,
+    pub version: u16,
+    pub count: u64,
+    pub created_unix: u64,
+    pub chain: Chain,
+}
+
+pub fn read_header(path: &Path) -> io::Result<SnapshotHeader> {
     let mut file = File::open(path)?;
     let mut header = [0u8; HEADER_LEN];
     file.read_exact(&mut header)?;
-    let created = u64::from_le_bytes(header[16..24].try_into().unwrap());
+    Ok(SnapshotHeader {
+        magic: String::from_utf8_lossy(&header[0..4]).into_owned(),
+        version: u16::from_le_bytes(header[4..6].try_into().unwrap()),
+        count: u64::from_le_bytes(header[8..16].try_into().unwrap()),
+        created_unix: u64::from_le_bytes(header[16..24].try_into().unwrap()),
+        chain: header_chain(&header, path)?,
+    })
+}
+
+pub fn snapshot_age_secs(path: &Path) -> io::Result<u64> {
+    let created = read_header(path)?.created_unix;
     let now = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .map(|d| d.as_secs())
@@ -394,26 +650,32 @@ pub fn snapshot_age_secs(path: &Path) -> io::Result<u64> {
     Ok(now.saturating_sub(created))
 }
 
-pub fn write_snapshot(path: &Path, db: &Db, bits_per_key: u32) -> io::Result<()> {
+/// Write `db` as a PLH2 snapshot of `chain` addresses.
+pub fn write_snapshot(path: &Path, db: &Db, bits_per_key: u32, chain: Chain) -> io::Result<()> {
     match db {
         Db::Mmap(_) => Ok(()),
-        Db::Sorted(rows) => write_plh2_from_slice(path, rows, bits_per_key),
+        Db::Sorted(rows) => write_plh2_from_slice(path, rows, bits_per_key, chain),
         Db::Hash(set) => {
             let mut hashes: Vec<[u8; 20]> = set.iter().copied().collect();
             hashes.sort_unstable();
-            write_plh2_from_slice(path, &hashes, bits_per_key)
+            write_plh2_from_slice(path, &hashes, bits_per_key, chain)
         }
     }
 }
 
-fn write_plh2_from_slice(path: &Path, hashes: &[[u8; 20]], bits_per_key: u32) -> io::Result<()> {
+fn write_plh2_from_slice(
+    path: &Path,
+    hashes: &[[u8; 20]],
+    bits_per_key: u32,
+    chain: Chain,
+) -> io::Result<()> {
     let tmp = path.with_extension("h160.tmp");
-    write_plh2(&tmp, hashes, bits_per_key)?;
+    write_plh2(&tmp, hashes, bits_per_key, chain)?;
     fs::rename(tmp, path)?;
     Ok(())
 }
 
-fn write_plh2(path: &Path, hashes: &[[u8; 20]], bits_per_key: u32) -> io::Result<()> {
+fn write_plh2(path: &Path, hashes: &[[u8; 20]], bits_per_key: u32, chain: Chain) -> io::Result<()> {
     if let Some(parent) = path.parent() {
         if !parent.as_os_str().is_empty() {
             fs::create_dir_all(parent)?;
@@ -427,7 +689,7 @@ fn write_plh2(path: &Path, hashes: &[[u8; 20]], bits_per_key: u32) -> io::Result
     }
     let index = index_from_counts(&counts);
     let mut file = File::create(path)?;
-    write_plh2_header(&mut file, hashes.len() as u64, &bloom)?;
+    write_plh2_header(&mut file, hashes.len() as u64, &bloom, chain)?;
     write_bucket_index(&mut file, &index)?;
     for h in hashes {
         file.write_all(h)?;
@@ -436,7 +698,7 @@ fn write_plh2(path: &Path, hashes: &[[u8; 20]], bits_per_key: u32) -> io::Result
     Ok(())
 }
 
-fn write_plh2_header(file: &mut File, count: u64, bloom: &Bloom) -> io::Result<()> {
+fn write_plh2_header(file: &mut File, count: u64, bloom: &Bloom, chain: Chain) -> io::Result<()> {
     let mut bloom_bytes = Vec::new();
     bloom.write_to(&mut bloom_bytes);
     let mut header = [0u8; HEADER_LEN];
@@ -452,6 +714,8 @@ fn write_plh2_header(file: &mut File, count: u64, bloom: &Bloom) -> io::Result<(
     header[32..36].copy_from_slice(&bloom.k().to_le_bytes());
     header[36..40].copy_from_slice(&bloom.bits_per_key().to_le_bytes());
     header[40..44].copy_from_slice(&(N_BUCKETS as u32).to_le_bytes());
+    header[44] = network_byte(chain.network);
+    header[45] = chain.header_id();
     file.write_all(&header)?;
     file.write_all(&bloom_bytes)?;
     Ok(())
@@ -486,6 +750,7 @@ fn rewrite_snapshot_as_plh2(path: &Path, bits_per_key: u32) -> io::Result<()> {
     let mut header = [0u8; HEADER_LEN];
     src.read_exact(&mut header)?;
     let (count, rec_off) = record_region(&header, path, file_len)?;
+    let chain = header_chain(&header, path)?;
     src.seek(SeekFrom::Start(rec_off))?;
     let mut bloom = Bloom::new(count as usize, bits_per_key);
     let mut counts = vec![0u32; N_BUCKETS];
@@ -498,7 +763,7 @@ fn rewrite_snapshot_as_plh2(path: &Path, bits_per_key: u32) -> io::Result<()> {
     let index = index_from_counts(&counts);
     let tmp = path.with_extension("h160.tmp");
     let mut out = File::create(&tmp)?;
-    write_plh2_header(&mut out, count, &bloom)?;
+    write_plh2_header(&mut out, count, &bloom, chain)?;
     write_bucket_index(&mut out, &index)?;
     src.seek(SeekFrom::Start(rec_off))?;
     io::copy(&mut src, &mut out)?;
@@ -614,10 +879,16 @@ pub fn load_pickles(cfg: &Config) -> io::Result<LoadReport> {
     if cfg.lookup == Lookup::Mmap {
         let partial = cfg.data_dir.join("partial");
         fs::create_dir_all(&partial)?;
-        let (chunks, invalid) = pickle_to_chunks(&paths, &partial)?;
+        let (chunks, invalid, files) = pickle_to_chunks(&paths, &partial, cfg.skip_bad_pickles)?;
         let (sorted, dups) = merge_chunks(&chunks, &partial.join("sorted.raw"))?;
         let count = fs::metadata(&sorted)?.len() / 20;
-        write_plh2_from_sorted_file(&cfg.snapshot, &sorted, count, cfg.bits_per_key)?;
+        write_plh2_from_sorted_file(
+            &cfg.snapshot,
+            &sorted,
+            count,
+            cfg.bits_per_key,
+            chain::BITCOIN,
+        )?;
         let _ = fs::remove_file(&sorted);
         for chunk in chunks {
             let _ = fs::remove_file(chunk);
@@ -628,6 +899,8 @@ pub fn load_pickles(cfg: &Config) -> io::Result<LoadReport> {
             skipped: invalid + dups,
             source: cfg.snapshot.display().to_string(),
             elapsed: timer.elapsed(),
+            bad_files: bad_file_count(&files),
+            files,
         });
     }
 
@@ -636,104 +909,236 @@ pub fn load_pickles(cfg: &Config) -> io::Result<LoadReport> {
     for (i, p) in paths.into_iter().enumerate() {
         shards[i % num_threads].push(p);
     }
-    let shard_results: Vec<(Vec<[u8; 20]>, u64)> = thread::scope(|s| {
+    let skip_bad = cfg.skip_bad_pickles;
+    let shard_results: Vec<ShardResult> = thread::scope(|s| {
         let handles: Vec<_> = shards
             .into_iter()
-            .map(|shard| s.spawn(move || load_pickle_shard(shard)))
+            .map(|shard| s.spawn(move || load_pickle_shard(shard, skip_bad)))
             .collect();
         handles
             .into_iter()
             .map(|h| h.join().expect("pickle shard panicked"))
             .collect()
     });
-    let mut skipped = 0u64;
     let mut hashes = Vec::new();
-    for (shard_hashes, shard_skipped) in shard_results {
-        skipped += shard_skipped;
+    let mut files = Vec::new();
+    for result in shard_results {
+        let (shard_hashes, shard_files) = result?;
         hashes.extend(shard_hashes);
+        files.extend(shard_files);
     }
+    files.sort_by(|a, b| a.path.cmp(&b.path));
     Ok(LoadReport {
         db: Db::from_hashes(hashes, cfg.lookup),
-        skipped,
+        skipped: files.iter().map(|f| f.skipped).sum(),
         source: dir.display().to_string(),
         elapsed: timer.elapsed(),
+        bad_files: bad_file_count(&files),
+        files,
     })
 }
 
-fn pickle_to_chunks(paths: &[PathBuf], partial: &Path) -> io::Result<(Vec<PathBuf>, u64)> {
+/// A pickle slice that could not be read, named so the operator can find it.
+#[derive(Debug)]
+pub struct PickleError {
+    pub path: PathBuf,
+    pub kind: PickleErrorKind,
+}
+
+#[derive(Debug)]
+pub enum PickleErrorKind {
+    Io(io::Error),
+    Parse(serde_pickle::Error),
+}
+
+impl fmt::Display for PickleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            PickleErrorKind::Io(error) => write!(f, "{}: {error}", self.path.display()),
+            PickleErrorKind::Parse(error) => write!(
+                f,
+                "{}: not a pickled list of addresses: {error}",
+                self.path.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PickleError {}
+
+impl From<PickleError> for io::Error {
+    fn from(error: PickleError) -> Self {
+        let kind = match &error.kind {
+            PickleErrorKind::Io(inner) => inner.kind(),
+            PickleErrorKind::Parse(_) => io::ErrorKind::InvalidData,
+        };
+        io::Error::new(kind, error)
+    }
+}
+
+/// Turns a pickled `list[str]` into hash160s. serde-pickle decodes the whole
+/// slice into its own values before visiting, so this is not streaming: it
+/// only avoids collecting a second copy of the list as a `Vec<String>`.
+struct AddressList;
+
+impl<'de> Visitor<'de> for AddressList {
+    type Value = (Vec<[u8; 20]>, u64, u64);
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a list of address strings")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut hashes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        let mut records = 0u64;
+        let mut skipped = 0u64;
+        while let Some(addr) = seq.next_element::<String>()? {
+            records += 1;
+            match address_hash160(&addr) {
+                Some(h) => hashes.push(h),
+                None => skipped += 1,
+            }
+        }
+        Ok((hashes, records, skipped))
+    }
+}
+
+/// Decode one slice. Nothing is returned for a file that fails part-way, so a
+/// skipped slice never contributes a partial set.
+fn read_pickle(path: &Path) -> Result<(Vec<[u8; 20]>, FileCount), PickleError> {
+    let err = |kind| PickleError {
+        path: path.to_path_buf(),
+        kind,
+    };
+    let file = File::open(path).map_err(|e| err(PickleErrorKind::Io(e)))?;
+    let mut de = serde_pickle::Deserializer::new(
+        BufReader::with_capacity(1 << 20, file),
+        serde_pickle::DeOptions::new(),
+    );
+    let (hashes, records, skipped) = de
+        .deserialize_seq(AddressList)
+        .and_then(|parsed| de.end().map(|()| parsed))
+        .map_err(|e| match e {
+            serde_pickle::Error::Io(io) => err(PickleErrorKind::Io(io)),
+            other => err(PickleErrorKind::Parse(other)),
+        })?;
+    Ok((
+        hashes,
+        FileCount {
+            path: path.to_path_buf(),
+            records,
+            skipped,
+            error: None,
+        },
+    ))
+}
+
+/// `read_pickle`, but with `skip_bad` a broken slice is logged, recorded in
+/// `files` and treated as empty.
+fn read_pickle_or_skip(
+    path: &Path,
+    skip_bad: bool,
+    files: &mut Vec<FileCount>,
+) -> Result<Vec<[u8; 20]>, PickleError> {
+    match read_pickle(path) {
+        Ok((hashes, count)) => {
+            println!(
+                "Loaded {:?} ({} records, {} skipped)",
+                path.file_name().unwrap_or_default(),
+                count.records,
+                count.skipped
+            );
+            files.push(count);
+            Ok(hashes)
+        }
+        Err(error) if skip_bad => {
+            eprintln!("warning: skipping bad slice {error}");
+            files.push(FileCount {
+                path: path.to_path_buf(),
+                records: 0,
+                skipped: 0,
+                error: Some(error.to_string()),
+            });
+            Ok(Vec::new())
+        }
+        Err(error) => Err(error),
+    }
+}
+
+fn pickle_to_chunks(
+    paths: &[PathBuf],
+    partial: &Path,
+    skip_bad: bool,
+) -> io::Result<(Vec<PathBuf>, u64, Vec<FileCount>)> {
     let mut writer = ChunkWriter::new(partial, CHUNK_RECORDS);
-    let mut skipped = 0u64;
+    let mut files = Vec::with_capacity(paths.len());
     for path in paths {
-        let mut bytes = Vec::new();
-        File::open(path)?.read_to_end(&mut bytes)?;
-        let addresses: Vec<String> =
-            serde_pickle::from_slice(&bytes, Default::default()).expect("couldn't load pickle");
-        for addr in &addresses {
-            if let Some(h) = address_hash160(addr) {
-                writer.push(h)?;
-            } else {
-                skipped += 1;
-            }
+        for h in read_pickle_or_skip(path, skip_bad, &mut files)? {
+            writer.push(h)?;
         }
-        println!("Loaded {:?}", path.file_name().unwrap_or_default());
     }
-    Ok((writer.finish()?, skipped))
+    let skipped = files.iter().map(|f| f.skipped).sum();
+    Ok((writer.finish()?, skipped, files))
 }
 
-fn load_pickle_shard(paths: Vec<PathBuf>) -> (Vec<[u8; 20]>, u64) {
+type ShardResult = Result<(Vec<[u8; 20]>, Vec<FileCount>), PickleError>;
+
+fn load_pickle_shard(paths: Vec<PathBuf>, skip_bad: bool) -> ShardResult {
     let mut out = Vec::new();
-    let mut skipped = 0u64;
+    let mut files = Vec::with_capacity(paths.len());
     for path in paths {
-        let mut bytes = Vec::new();
-        if let Err(error) = File::open(&path).and_then(|mut f| f.read_to_end(&mut bytes)) {
-            panic!("read {path:?}: {error}");
-        }
-        let addresses: Vec<String> =
-            serde_pickle::from_slice(&bytes, Default::default()).expect("couldn't load pickle");
-        for addr in &addresses {
-            match address_hash160(addr) {
-                Some(h) => out.push(h),
-                None => skipped += 1,
-            }
-        }
-        println!("Loaded {:?}", path.file_name().unwrap_or_default());
+        out.extend(read_pickle_or_skip(&path, skip_bad, &mut files)?);
     }
-    (out, skipped)
+    Ok((out, files))
+}
+
+fn bad_file_count(files: &[FileCount]) -> u64 {
+    files.iter().filter(|f| f.error.is_some()).count() as u64
 }
 
-pub fn update_from_url(cfg: &Config, source_url: &str) -> io::Result<LoadReport> {
-    refresh_snapshot(cfg, source_url)?;
+pub fn update_from_source(cfg: &Config, source: &dyn SnapshotSource) -> io::Result<LoadReport> {
+    refresh_snapshot(cfg, source)?;
     load_snapshot_with(&cfg.snapshot, cfg.lookup, cfg.bits_per_key)
 }
 
 pub fn refresh_snapshot(
     cfg: &Config,
-    source_url: &str,
+    source: &dyn SnapshotSource,
 ) -> io::Result<(usize, u64, std::time::Duration)> {
     fs::create_dir_all(&cfg.data_dir)?;
     let partial = cfg.data_dir.join("partial");
     fs::create_dir_all(&partial)?;
-    let gz_path = partial.join("addresses.txt.gz");
 
-    println!("Downloading {source_url}");
-    download_file(source_url, &gz_path)?;
-    println!("Importing funded P2PKH + P2WPKH hash160s (chunked, low RAM)");
+    println!("Fetching {}", source.describe());
+    let fetched = source.fetch(&partial)?;
+    println!(
+        "Importing funded {} P2PKH + P2WPKH hash160s (chunked, low RAM)",
+        cfg.data_chain.name
+    );
     let timer = Instant::now();
-    let (chunks, invalid) = gzip_to_chunks(&gz_path, &partial)?;
+    let (chunks, invalid) = dump_to_chunks(&fetched.path, &partial, cfg.data_chain)?;
     let (sorted, dups) = merge_chunks(&chunks, &partial.join("sorted.raw"))?;
     let count = (fs::metadata(&sorted)?.len() / 20) as usize;
-    write_plh2_from_sorted_file(&cfg.snapshot, &sorted, count as u64, cfg.bits_per_key)?;
+    write_plh2_from_sorted_file(
+        &cfg.snapshot,
+        &sorted,
+        count as u64,
+        cfg.bits_per_key,
+        cfg.data_chain,
+    )?;
     let _ = fs::remove_file(&sorted);
-    let _ = fs::remove_file(&gz_path);
+    if fetched.temporary {
+        let _ = fs::remove_file(&fetched.path);
+    }
     for chunk in chunks {
         let _ = fs::remove_file(chunk);
     }
     Ok((count, invalid + dups, timer.elapsed()))
 }
 
-fn gzip_to_chunks(path: &Path, partial: &Path) -> io::Result<(Vec<PathBuf>, u64)> {
-    let file = File::open(path)?;
-    let decoder = GzDecoder::new(file);
+fn dump_to_chunks(path: &Path, partial: &Path, chain: Chain) -> io::Result<(Vec<PathBuf>, u64)> {
+    let (kind, decoder) = compress::open_dump(path)?;
+    println!("  decoding {} dump", kind.as_str());
     let reader = BufReader::with_capacity(1 << 20, decoder);
     let mut writer = ChunkWriter::new(partial, CHUNK_RECORDS);
     let mut seen = 0u64;
@@ -745,7 +1150,7 @@ fn gzip_to_chunks(path: &Path, partial: &Path) -> io::Result<(Vec<PathBuf>, u64)
             continue;
         }
         seen += 1;
-        if let Some(hash) = address_hash160(addr) {
+        if let Some(hash) = chain.hash160(addr) {
             writer.push(hash)?;
         } else {
             skipped += 1;
@@ -762,6 +1167,7 @@ fn write_plh2_from_sorted_file(
     sorted: &Path,
     count: u64,
     bits_per_key: u32,
+    chain: Chain,
 ) -> io::Result<()> {
     let mut bloom = Bloom::new(count as usize, bits_per_key);
     let mut counts = vec![0u32; N_BUCKETS];
@@ -782,7 +1188,7 @@ fn write_plh2_from_sorted_file(
         }
     }
     let mut out = File::create(&tmp)?;
-    write_plh2_header(&mut out, count, &bloom)?;
+    write_plh2_header(&mut out, count, &bloom, chain)?;
     write_bucket_index(&mut out, &index)?;
     let mut input = File::open(sorted)?;
     io::copy(&mut input, &mut out)?;
@@ -886,25 +1292,6 @@ fn read_rec(reader: &mut BufReader<File>) -> io::Result<Option<[u8; 20]>> {
     }
 }
 
-fn download_file(url: &str, dest: &Path) -> io::Result<()> {
-    let client = reqwest::blocking::Client::builder()
-        .timeout(None)
-        .build()
-        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
-    let mut response = client
-        .get(url)
-        .send()
-        .and_then(|r| r.error_for_status())
-        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
-    let tmp = dest.with_extension("gz.tmp");
-    let mut file = File::create(&tmp)?;
-    io::copy(&mut response, &mut file)?;
-    file.sync_all()?;
-    drop(file);
-    fs::rename(tmp, dest)?;
-    Ok(())
-}
-
 pub fn prepare_from_pickles(cfg: &Config) -> io::Result<LoadReport> {
     load_pickles(cfg)
 }
@@ -912,8 +1299,7 @@ pub fn prepare_from_pickles(cfg: &Config) -> io::Result<LoadReport> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::env;
-    use std::time::{SystemTime, UNIX_EPOCH};
+    use crate::test_util::unique_dir;
 
     #[test]
     fn address_hash160_decodes_p2pkh_and_p2wpkh() {
@@ -943,19 +1329,118 @@ mod tests {
         );
     }
 
-    fn unique_dir() -> PathBuf {
-        let unique = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
+    #[test]
+    fn bitcoin_chains_follow_the_network_prefixes() {
+        let secp = bitcoin::secp256k1::Secp256k1::new();
+        let mut one = [0u8; 32];
+        one[31] = 1;
+        let secret = bitcoin::secp256k1::SecretKey::from_slice(&one).unwrap();
+        let key = bitcoin::PublicKey::from_private_key(
+            &secp,
+            &bitcoin::PrivateKey::new(secret, Network::Bitcoin),
+        );
+        let key1 = address_hash160("1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH").unwrap();
+        for network in [Network::Testnet, Network::Signet, Network::Regtest] {
+            let p2pkh = Address::p2pkh(&key, network).to_string();
+            let p2wpkh = Address::p2wpkh(&key, network).unwrap().to_string();
+            assert!(p2pkh.starts_with(['m', 'n']), "{p2pkh}");
+            assert_eq!(
+                chain::bitcoin(network).hash160(&p2pkh),
+                Some(key1),
+                "{p2pkh}"
+            );
+            assert_eq!(
+                chain::bitcoin(network).hash160(&p2wpkh),
+                Some(key1),
+                "{p2wpkh}"
+            );
+            assert_eq!(chain::BITCOIN.hash160(&p2pkh), None);
+            assert_eq!(chain::BITCOIN.hash160(&p2wpkh), None);
+        }
+        assert!(Address::p2wpkh(&key, Network::Regtest)
             .unwrap()
-            .as_nanos();
-        let dir = env::temp_dir().join(format!("plutus-snap-{unique}"));
-        fs::create_dir_all(&dir).unwrap();
-        dir
+            .to_string()
+            .starts_with("bcrt1"));
+        assert_eq!(
+            chain::bitcoin(Network::Regtest).hash160("tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx"),
+            None,
+            "tb1 is not regtest"
+        );
+        assert_eq!(
+            chain::bitcoin(Network::Testnet).hash160("2MzQwSSnBHWHqSAqtTVQ6v47XtaisrJa1Vc"),
+            None,
+            "P2SH"
+        );
+        assert_eq!(
+            chain::bitcoin(Network::Testnet).hash160("1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH"),
+            None
+        );
+    }
+
+    #[test]
+    fn snapshot_records_its_chain_and_load_refuses_another() {
+        let dir = unique_dir("plutus-snap");
+        let snapshot = dir.join("addresses.h160");
+        let testnet = chain::bitcoin(Network::Testnet);
+        write_snapshot(&snapshot, &Db::Sorted(vec![[7; 20]]), 16, testnet).unwrap();
+        assert_eq!(read_header(&snapshot).unwrap().chain, testnet);
+        let info = inspect_snapshot(&snapshot).unwrap();
+        assert!(info.contains("chain=bitcoin\nnetwork=testnet"), "{info}");
+
+        let cfg = Config {
+            snapshot: snapshot.clone(),
+            ..Config::default()
+        };
+        let error = load(&cfg)
+            .err()
+            .expect("mainnet engine must refuse a testnet snapshot");
+        assert!(error.to_string().contains("data.chain"), "{error}");
+        let cfg = Config {
+            chain: testnet,
+            ..cfg
+        };
+        assert_eq!(load(&cfg).unwrap().db.len(), 1);
+
+        rewrite_snapshot_as_plh2(&snapshot, 14).unwrap();
+        assert_eq!(read_header(&snapshot).unwrap().chain, testnet);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn extra_snapshots_load_with_their_own_chain_once_each() {
+        let dir = unique_dir("plutus-snap");
+        let main = dir.join("addresses.h160");
+        let doge = dir.join("doge.h160");
+        let dogecoin = chain::find("doge", Network::Bitcoin).unwrap();
+        write_snapshot(&main, &Db::Sorted(vec![[1; 20]]), 16, chain::BITCOIN).unwrap();
+        write_snapshot(&doge, &Db::Sorted(vec![[2; 20], [3; 20]]), 16, dogecoin).unwrap();
+
+        let cfg = Config {
+            snapshot: main.clone(),
+            extra_snapshots: vec![doge.clone()],
+            ..Config::default()
+        };
+        let extra = load_extra(&cfg).unwrap();
+        assert_eq!(extra.len(), 1);
+        assert_eq!(extra[0].0, dogecoin);
+        assert_eq!(extra[0].1.db.len(), 2);
+
+        let cfg = Config {
+            extra_snapshots: vec![doge.clone(), doge.clone()],
+            ..cfg
+        };
+        assert!(load_extra(&cfg).is_err());
+        let cfg = Config {
+            extra_snapshots: vec![main.clone()],
+            ..cfg
+        };
+        assert!(load_extra(&cfg).is_err());
+        let _ = fs::remove_dir_all(&dir);
     }
 
     #[test]
     fn plh2_mmap_matches_hashset_and_stays_compact() {
-        let dir = unique_dir();
+        let dir = unique_dir("plutus-snap");
         let path = dir.join("addresses.h160");
         let mut hashes = Vec::new();
         for i in 0..8_000u32 {
@@ -966,7 +1451,7 @@ mod tests {
         }
         hashes.sort_unstable();
         hashes.dedup();
-        write_plh2_from_slice(&path, &hashes, 16).unwrap();
+        write_plh2_from_slice(&path, &hashes, 16, chain::BITCOIN).unwrap();
 
         let loaded = load_snapshot_with(&path, Lookup::Mmap, 16).unwrap();
         assert_eq!(loaded.db.lookup_name(), "mmap");
@@ -1008,7 +1493,7 @@ mod tests {
 
     #[test]
     fn plh1_converts_to_plh2_without_false_negatives() {
-        let dir = unique_dir();
+        let dir = unique_dir("plutus-snap");
         let path = dir.join("addresses.h160");
         let mut hashes = Vec::new();
         for i in 0..1_200u32 {
@@ -1029,9 +1514,34 @@ mod tests {
         let _ = fs::remove_dir_all(dir);
     }
 
+    #[test]
+    fn read_only_open_leaves_plh1_in_place() {
+        let dir = unique_dir("plutus-snap");
+        let path = dir.join("addresses.h160");
+        let hashes: Vec<[u8; 20]> = (0..300u32)
+            .map(|i| {
+                let mut h = [0u8; 20];
+                h[0..4].copy_from_slice(&i.to_be_bytes());
+                h
+            })
+            .collect();
+        write_plh1(&path, &hashes);
+        let db = open_snapshot_read_only(&path).unwrap();
+        assert_eq!(db.lookup_name(), "sorted");
+        assert!(hashes.iter().all(|h| db.contains(h)));
+        assert!(!db.contains(&[0xff; 20]));
+        assert_eq!(read_header(&path).unwrap().magic, "PLH1");
+
+        load_snapshot_with(&path, Lookup::Mmap, 16).unwrap();
+        let db = open_snapshot_read_only(&path).unwrap();
+        assert_eq!(db.lookup_name(), "mmap");
+        assert!(hashes.iter().all(|h| db.contains(h)));
+        let _ = fs::remove_dir_all(dir);
+    }
+
     #[test]
     fn mmap_binary_searches_a_dense_bucket() {
-        let dir = unique_dir();
+        let dir = unique_dir("plutus-snap");
         let path = dir.join("addresses.h160");
         let mut hashes = Vec::new();
         for i in 0..400u32 {
@@ -1040,7 +1550,7 @@ mod tests {
             hashes.push(h);
         }
         hashes.sort_unstable();
-        write_plh2_from_slice(&path, &hashes, 16).unwrap();
+        write_plh2_from_slice(&path, &hashes, 16, chain::BITCOIN).unwrap();
         let loaded = load_snapshot_with(&path, Lookup::Mmap, 16).unwrap();
         for h in &hashes {
             assert!(loaded.db.contains(h), "missed key in dense bucket");
@@ -1053,10 +1563,10 @@ mod tests {
 
     #[test]
     fn sorted_and_hash_still_roundtrip() {
-        let dir = unique_dir();
+        let dir = unique_dir("plutus-snap");
         let path = dir.join("addresses.h160");
         let hashes = vec![[1u8; 20], [2u8; 20], [9u8; 20]];
-        write_plh2_from_slice(&path, &hashes, 16).unwrap();
+        write_plh2_from_slice(&path, &hashes, 16, chain::BITCOIN).unwrap();
         let sorted = load_snapshot_with(&path, Lookup::Sorted, 16).unwrap();
         assert!(sorted.db.contains(&[1u8; 20]));
         assert!(!sorted.db.contains(&[7u8; 20]));
@@ -1067,7 +1577,7 @@ mod tests {
 
     #[test]
     fn external_sort_dedups_chunks() {
-        let dir = unique_dir();
+        let dir = unique_dir("plutus-snap");
         let mut writer = ChunkWriter::new(&dir, 4);
         for v in [3u8, 1, 2, 1, 3, 2, 9] {
             writer.push([v; 20]).unwrap();
@@ -1084,9 +1594,134 @@ mod tests {
         let _ = fs::remove_dir_all(dir);
     }
 
+    fn pickle_cfg(dir: &Path, lookup: Lookup, skip_bad: bool) -> Config {
+        Config {
+            data_dir: dir.join("data"),
+            snapshot: dir.join("data/addresses.h160"),
+            pickle_dir: dir.join("pickles"),
+            lookup,
+            skip_bad_pickles: skip_bad,
+            ..Config::default()
+        }
+    }
+
+    fn write_pickle_fixtures(dir: &Path) {
+        let pickles = dir.join("pickles");
+        fs::create_dir_all(&pickles).unwrap();
+        let good = vec![
+            "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH".to_owned(),
+            "3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy".to_owned(),
+        ];
+        let bytes = serde_pickle::to_vec(&good, Default::default()).unwrap();
+        fs::write(pickles.join("00.pickle"), &bytes).unwrap();
+        fs::write(pickles.join("01.pickle"), &bytes[..bytes.len() / 2]).unwrap();
+    }
+
+    #[test]
+    fn bad_pickle_is_an_error_naming_the_file() {
+        let dir = unique_dir("plutus-snap");
+        write_pickle_fixtures(&dir);
+        for lookup in [Lookup::Mmap, Lookup::Sorted] {
+            let error = match load_pickles(&pickle_cfg(&dir, lookup, false)) {
+                Ok(_) => panic!("truncated slice must not load"),
+                Err(error) => error,
+            };
+            assert!(error.to_string().contains("01.pickle"), "{error}");
+        }
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn bad_pickle_can_be_skipped_with_counts() {
+        let dir = unique_dir("plutus-snap");
+        write_pickle_fixtures(&dir);
+        for lookup in [Lookup::Mmap, Lookup::Hash] {
+            let report = load_pickles(&pickle_cfg(&dir, lookup, true)).unwrap();
+            assert_eq!(report.db.len(), 1);
+            assert_eq!(report.skipped, 1);
+            assert_eq!(report.bad_files, 1);
+            assert_eq!(report.files.len(), 2);
+            assert_eq!(report.files[0].records, 2);
+            assert_eq!(report.files[0].skipped, 1);
+            assert!(report.files[0].error.is_none());
+            assert!(report.files[1].error.is_some());
+        }
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn query_reports_each_stage() {
+        let dir = unique_dir("plutus-snap");
+        let path = dir.join("addresses.h160");
+        let key1 = address_hash160("1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH").unwrap();
+        write_plh2_from_slice(&path, &[[3u8; 20], key1], 16, chain::BITCOIN).unwrap();
+        let loaded = load_snapshot_with(&path, Lookup::Mmap, 16).unwrap();
+
+        let hit = query(
+            &loaded.db,
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4",
+            chain::BITCOIN,
+        );
+        assert_eq!(hit.kind, "p2wpkh");
+        assert_eq!(
+            hit.hash160.as_deref(),
+            Some("751e76e8199196d454941c45d1b3a323f1433bd6")
+        );
+        assert_eq!(hit.bloom, Some(true));
+        assert_eq!(hit.bucket.as_deref(), Some("751e"));
+        assert_eq!(hit.bucket_records, Some(1));
+        assert!(hit.found);
+
+        let raw = query(&loaded.db, &"03".repeat(20), chain::BITCOIN);
+        assert_eq!(raw.kind, "hash160");
+        assert!(raw.found);
+
+        let p2sh = query(
+            &loaded.db,
+            "3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy",
+            chain::BITCOIN,
+        );
+        assert_eq!(p2sh.kind, "p2sh");
+        assert!(p2sh.hash160.is_none());
+        assert!(!p2sh.found);
+
+        assert_eq!(
+            query(&loaded.db, "not-an-address", chain::BITCOIN).kind,
+            "invalid"
+        );
+        let sorted = load_snapshot_with(&path, Lookup::Sorted, 16).unwrap();
+        let via_sorted = query(
+            &sorted.db,
+            "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH",
+            chain::BITCOIN,
+        );
+        assert_eq!(via_sorted.bloom, None);
+        assert!(via_sorted.found);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn zstd_dump_imports_like_gzip() {
+        let dir = unique_dir("plutus-snap");
+        let dump = dir.join("dump.download");
+        let text = "address\tbalance\n\
+            1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH\t5\n\
+            3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy\t7\n";
+        fs::write(&dump, zstd::stream::encode_all(text.as_bytes(), 3).unwrap()).unwrap();
+        let (chunks, skipped) = dump_to_chunks(&dump, &dir, chain::BITCOIN).unwrap();
+        assert_eq!(skipped, 1, "P2SH row is skipped");
+        assert_eq!(chunks.len(), 1);
+        let data = fs::read(&chunks[0]).unwrap();
+        assert_eq!(
+            &data[..],
+            &address_hash160("1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH").unwrap()[..]
+        );
+        let _ = fs::remove_dir_all(dir);
+    }
+
     #[test]
     fn mmap_ram_under_full_table_at_64k_keys() {
-        let dir = unique_dir();
+        let dir = unique_dir("plutus-snap");
         let path = dir.join("addresses.h160");
         let mut hashes = Vec::with_capacity(64_000);
         for i in 0..64_000u32 {
@@ -1096,7 +1731,7 @@ mod tests {
             hashes.push(h);
         }
         hashes.sort_unstable();
-        write_plh2_from_slice(&path, &hashes, 16).unwrap();
+        write_plh2_from_slice(&path, &hashes, 16, chain::BITCOIN).unwrap();
         let loaded = load_snapshot_with(&path, Lookup::Mmap, 16).unwrap();
         assert!(
             loaded.db.ram_bytes() < hashes.len() * 20,
@@ -1,19 +1,19 @@
 use std::path::PathBuf;
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
-use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
 use std::sync::Arc;
 use std::thread;
 use std::time::{Duration, Instant};
 
 use bitcoin::secp256k1::{All, PublicKey, Scalar, Secp256k1, SecretKey};
-use bitcoin::{Address, Network, PrivateKey};
+use bitcoin::PrivateKey;
 use ripemd::Ripemd160;
 use sha2::{Digest, Sha256};
 
+use crate::chain::Chain;
 use crate::config::{self, Config};
-use crate::db::{self, Db};
 use crate::hit;
-use crate::notify::Notifier;
+use crate::matcher::Matcher;
 use crate::pending;
 use crate::status::{self, Status};
 
@@ -22,6 +22,7 @@ pub const RELOAD_EXIT: u8 = 75;
 
 const BATCH: usize = 512;
 const REPORT_BLOCK: u64 = 1 << 17;
+const PAUSE_POLL: Duration = Duration::from_millis(50);
 
 mod ec {
     use std::ffi::c_void;
@@ -164,150 +165,578 @@ fn try_simd(data: &[u8], out: &mut [u8], n: usize, uncompressed: bool) -> bool {
     }
 }
 
-struct Shared {
-    db: Arc<Db>,
-    secp: Arc<Secp256k1<All>>,
-    keys: AtomicU64,
-    hits: AtomicU64,
-    running: AtomicBool,
+/// Cooperative shutdown shared between an [`Engine`] and its owner. Clones
+/// share the same flags, so a signal handler or another thread can hold one.
+#[derive(Debug, Clone, Default)]
+pub struct StopToken(Arc<StopFlags>);
+
+#[derive(Debug, Default)]
+struct StopFlags {
+    stop: AtomicBool,
     reload: AtomicBool,
-    check_uncompressed: bool,
-    walk_span: u64,
-    cpu_percent: u8,
-    findings: PathBuf,
-    data_dir: PathBuf,
 }
 
-struct HitNotice {
-    address: String,
-    compressed: bool,
+impl StopToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stop for good: [`Engine::run`] returns [`RunOutcome::Stopped`].
+    pub fn stop(&self) {
+        self.0.stop.store(true, Ordering::Relaxed);
+    }
+
+    /// Stop so the owner can refresh the snapshot and start a new engine. The
+    /// request is consumed when the engine returns [`RunOutcome::Reload`], so
+    /// the same token can drive the next one.
+    pub fn request_reload(&self) {
+        self.0.reload.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.0.stop.load(Ordering::Relaxed)
+    }
+
+    fn should_exit(&self) -> bool {
+        self.is_stopped() || self.0.reload.load(Ordering::Relaxed)
+    }
+}
+
+/// Settings an owner can change while an [`Engine`] runs. Clones share the
+/// same flags, and they outlive the engine, so a pause holds across reloads.
+#[derive(Debug, Clone)]
+pub struct Controls(Arc<ControlFlags>);
+
+#[derive(Debug)]
+struct ControlFlags {
+    paused: AtomicBool,
+    cpu_percent: AtomicU8,
+}
+
+impl Default for Controls {
+    fn default() -> Self {
+        Controls(Arc::new(ControlFlags {
+            paused: AtomicBool::new(false),
+            cpu_percent: AtomicU8::new(Config::default().cpu_percent),
+        }))
+    }
+}
+
+impl Controls {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Park the workers after the batch in hand. The set stays loaded.
+    pub fn pause(&self) {
+        self.0.paused.store(true, Ordering::Relaxed);
+    }
+
+    pub fn resume(&self) {
+        self.0.paused.store(false, Ordering::Relaxed);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.0.paused.load(Ordering::Relaxed)
+    }
+
+    /// Share of each worker's time spent walking, 1-100; applies from the
+    /// next batch.
+    pub fn set_cpu_percent(&self, cpu_percent: u8) {
+        self.0
+            .cpu_percent
+            .store(cpu_percent.clamp(1, 100), Ordering::Relaxed);
+    }
+
+    pub fn cpu_percent(&self) -> u8 {
+        self.0.cpu_percent.load(Ordering::Relaxed)
+    }
+}
+
+/// A walked key whose hash160 is in the set. By the time the hit callback
+/// sees it, the secret is in the findings file and the address is queued for
+/// alerts; the secret never leaves the worker.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hit {
+    pub address: String,
+    pub compressed: bool,
+    /// Dataset that matched, when the set is a [`Tagged`](crate::matcher::Tagged).
+    pub tag: Option<String>,
+    /// Set when the findings record or the pending alert could not be
+    /// written. The hit is counted and reported all the same.
+    pub persist_error: Option<String>,
+}
+
+/// Counters handed to the progress callback on every tick.
+#[derive(Debug, Clone, Copy)]
+pub struct Progress {
+    pub keys_checked: u64,
+    pub hits: u64,
+    pub elapsed: Duration,
+    pub keys_per_sec_avg: f64,
+    pub keys_per_sec_inst: f64,
+}
+
+/// Totals once every worker has joined.
+#[derive(Debug, Clone)]
+pub struct Summary {
+    pub outcome: RunOutcome,
+    pub started_unix: u64,
+    pub keys_checked: u64,
+    pub hits: u64,
+    pub elapsed: Duration,
+    /// Keys checked by each worker, in spawn order.
+    pub per_thread_keys: Vec<u64>,
+}
+
+impl Summary {
+    pub fn keys_per_sec(&self) -> f64 {
+        self.keys_checked as f64 / self.elapsed.as_secs_f64().max(0.001)
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum RunOutcome {
     Stopped,
     Reload,
+    /// The run reached its `duration` or `max_keys` bound.
+    Bounded,
 }
 
-pub fn run(cfg: &Config) -> Result<RunOutcome, String> {
-    let halt = Arc::new(AtomicBool::new(false));
-    {
-        let halt = Arc::clone(&halt);
-        ctrlc::set_handler(move || {
-            halt.store(true, Ordering::Relaxed);
-        })
-        .map_err(|e| e.to_string())?;
+impl RunOutcome {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RunOutcome::Stopped => "stopped",
+            RunOutcome::Reload => "reload",
+            RunOutcome::Bounded => "bounded",
+        }
     }
+}
 
-    loop {
-        if halt.load(Ordering::Relaxed) {
-            return Ok(RunOutcome::Stopped);
-        }
+type Callback<T> = Box<dyn FnMut(&T) + Send>;
 
-        let loaded = db::load(cfg).map_err(|e| e.to_string())?;
-        println!(
-            "Loaded {} unique funded hash160s (P2PKH + P2WPKH) in {:.2?} from {} via {} (~{}MB RAM, {} skipped)",
-            loaded.db.len(),
-            loaded.elapsed,
-            loaded.source,
-            loaded.db.lookup_name(),
-            loaded.db.ram_bytes() / (1024 * 1024),
-            loaded.skipped
-        );
+/// Settings and callbacks for an [`Engine`]. Defaults match an empty
+/// `config.toml`, with no pending-alert queue and no callbacks.
+pub struct EngineBuilder {
+    set: Arc<dyn Matcher>,
+    threads: usize,
+    cpu_percent: u8,
+    check_uncompressed: bool,
+    walk_span: u64,
+    chain: Chain,
+    tag_chains: Vec<(String, Chain)>,
+    findings: PathBuf,
+    pending_dir: Option<PathBuf>,
+    start: Option<SecretKey>,
+    snapshot: String,
+    node: String,
+    stop: StopToken,
+    controls: Controls,
+    tick: Duration,
+    duration: Option<Duration>,
+    max_keys: Option<u64>,
+    on_progress: Option<Callback<Progress>>,
+    on_hit: Option<Callback<Hit>>,
+    on_status: Option<Callback<Status>>,
+}
 
-        let threads = config::worker_count(cfg);
-        println!(
-            "Running on {threads} worker thread(s) | uncompressed={} | simd={} | cpu={} | lookup={} | walk_span={}",
-            cfg.check_uncompressed,
-            simd_name(),
-            cfg.cpu_percent,
-            loaded.db.lookup_name(),
-            cfg.walk_span
-        );
+impl EngineBuilder {
+    /// Take threads, throttle, uncompressed checks, walk span, chain, the
+    /// findings file and the pending queue (`data.dir`) from `cfg`.
+    pub fn config(self, cfg: &Config) -> Self {
+        self.threads(config::worker_count(cfg))
+            .cpu_percent(cfg.cpu_percent)
+            .check_uncompressed(cfg.check_uncompressed)
+            .walk_span(cfg.walk_span)
+            .chain(cfg.chain)
+            .findings(&cfg.findings)
+            .pending_dir(&cfg.data_dir)
+    }
+
+    /// Worker threads; `0` uses every core.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = if threads == 0 {
+            num_cpus::get().max(1)
+        } else {
+            threads
+        };
+        self
+    }
+
+    /// Share of each worker's time spent walking, 1-100.
+    pub fn cpu_percent(mut self, cpu_percent: u8) -> Self {
+        self.cpu_percent = cpu_percent.clamp(1, 100);
+        self
+    }
 
+    pub fn check_uncompressed(mut self, check_uncompressed: bool) -> Self {
+        self.check_uncompressed = check_uncompressed;
+        self
+    }
+
+    /// Keys walked from each random start before drawing a new one.
+    pub fn walk_span(mut self, walk_span: u64) -> Self {
+        self.walk_span = walk_span.max(1);
+        self
+    }
+
+    /// Chain whose WIF and address formats hit records use.
+    pub fn chain(mut self, chain: Chain) -> Self {
+        self.chain = chain;
+        self
+    }
+
+    /// Record hits reported with `tag` (see [`crate::matcher::Tagged`]) in
+    /// `chain`'s formats instead of the engine's.
+    pub fn tag_chain(mut self, tag: impl Into<String>, chain: Chain) -> Self {
+        self.tag_chains.push((tag.into(), chain));
+        self
+    }
+
+    pub fn findings(mut self, path: impl Into<PathBuf>) -> Self {
+        self.findings = path.into();
+        self
+    }
+
+    /// Directory of the pending hit-alert queue; see [`pending::enqueue`].
+    pub fn pending_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.pending_dir = Some(dir.into());
+        self
+    }
+
+    /// Walk from `start` before drawing random keys; worker `n` begins
+    /// `n * walk_span` keys further on. For self-tests and demos.
+    pub fn start_key(mut self, start: SecretKey) -> Self {
+        self.start = Some(start);
+        self
+    }
+
+    /// Where the set came from, for status reports.
+    pub fn snapshot(mut self, source: impl Into<String>) -> Self {
+        self.snapshot = source.into();
+        self
+    }
+
+    pub fn node(mut self, node: impl Into<String>) -> Self {
+        self.node = node.into();
+        self
+    }
+
+    pub fn stop_token(mut self, stop: StopToken) -> Self {
+        self.stop = stop;
+        self
+    }
+
+    /// Pause and throttle handles. The run starts from the builder's
+    /// `cpu_percent`, written into `controls`, and keeps their pause state.
+    pub fn controls(mut self, controls: Controls) -> Self {
+        self.controls = controls;
+        self
+    }
+
+    /// Interval between progress and status callbacks (default: 3s).
+    pub fn tick(mut self, tick: Duration) -> Self {
+        self.tick = tick;
+        self
+    }
+
+    /// Stop with [`RunOutcome::Bounded`] once the run has lasted `duration`.
+    pub fn duration(mut self, duration: Duration) -> Self {
+        self.duration = Some(duration);
+        self
+    }
+
+    /// Stop with [`RunOutcome::Bounded`] once `max_keys` keys are checked.
+    /// Each thread walks its share rounded up to whole batches, so the total
+    /// may pass it by up to one batch per thread.
+    pub fn max_keys(mut self, max_keys: u64) -> Self {
+        self.max_keys = Some(max_keys);
+        self
+    }
+
+    pub fn on_progress(mut self, callback: impl FnMut(&Progress) + Send + 'static) -> Self {
+        self.on_progress = Some(Box::new(callback));
+        self
+    }
+
+    pub fn on_hit(mut self, callback: impl FnMut(&Hit) + Send + 'static) -> Self {
+        self.on_hit = Some(Box::new(callback));
+        self
+    }
+
+    /// Called with a `status.json` record every tick and once more, with
+    /// `running: false`, after the workers have stopped.
+    pub fn on_status(mut self, callback: impl FnMut(&Status) + Send + 'static) -> Self {
+        self.on_status = Some(Box::new(callback));
+        self
+    }
+
+    pub fn build(self) -> Engine {
+        Engine { settings: self }
+    }
+}
+
+/// The key-space walker over one loaded set. It installs no signal handlers
+/// and prints nothing: callers drive it through a [`StopToken`] and observe
+/// it through the builder's callbacks, which all run on the thread that
+/// calls [`Engine::run`].
+pub struct Engine {
+    settings: EngineBuilder,
+}
+
+struct Shared {
+    set: Arc<dyn Matcher>,
+    secp: Arc<Secp256k1<All>>,
+    keys: AtomicU64,
+    worker_keys: Vec<AtomicU64>,
+    max_keys: Option<u64>,
+    hits: AtomicU64,
+    running: AtomicBool,
+    check_uncompressed: bool,
+    walk_span: u64,
+    controls: Controls,
+    chain: Chain,
+    tag_chains: Vec<(String, Chain)>,
+    findings: PathBuf,
+    pending_dir: Option<PathBuf>,
+    start: Option<SecretKey>,
+}
+
+impl Engine {
+    /// An engine over `set`: a loaded `Db`, or any other [`Matcher`].
+    pub fn builder(set: impl Matcher + 'static) -> EngineBuilder {
+        let defaults = Config::default();
+        EngineBuilder {
+            set: Arc::new(set),
+            threads: config::worker_count(&defaults),
+            cpu_percent: defaults.cpu_percent,
+            check_uncompressed: defaults.check_uncompressed,
+            walk_span: defaults.walk_span,
+            chain: defaults.chain,
+            tag_chains: Vec::new(),
+            findings: defaults.findings,
+            pending_dir: None,
+            start: None,
+            snapshot: String::new(),
+            node: config::node_name(),
+            stop: StopToken::new(),
+            controls: Controls::new(),
+            tick: Duration::from_secs(3),
+            duration: None,
+            max_keys: None,
+            on_progress: None,
+            on_hit: None,
+            on_status: None,
+        }
+    }
+
+    /// Walk until the stop token fires or a bound is reached, then join the
+    /// workers and report the totals. Hits are passed on as they arrive.
+    pub fn run(mut self) -> Summary {
+        let settings = &mut self.settings;
+        settings.controls.set_cpu_percent(settings.cpu_percent);
         let shared = Arc::new(Shared {
-            db: Arc::new(loaded.db),
+            set: Arc::clone(&settings.set),
             secp: Arc::new(Secp256k1::new()),
             keys: AtomicU64::new(0),
+            worker_keys: (0..settings.threads).map(|_| AtomicU64::new(0)).collect(),
+            max_keys: settings.max_keys,
             hits: AtomicU64::new(0),
             running: AtomicBool::new(true),
-            reload: AtomicBool::new(false),
-            check_uncompressed: cfg.check_uncompressed,
-            walk_span: cfg.walk_span,
-            cpu_percent: cfg.cpu_percent,
-            findings: cfg.findings.clone(),
-            data_dir: cfg.data_dir.clone(),
+            check_uncompressed: settings.check_uncompressed,
+            walk_span: settings.walk_span,
+            controls: settings.controls.clone(),
+            chain: settings.chain,
+            tag_chains: settings.tag_chains.clone(),
+            findings: settings.findings.clone(),
+            pending_dir: settings.pending_dir.clone(),
+            start: settings.start,
         });
 
-        let (tx, rx) = mpsc::channel::<HitNotice>();
-        let mut workers = Vec::with_capacity(threads);
-        for _ in 0..threads {
+        let (tx, rx) = mpsc::channel::<Hit>();
+        let mut workers = Vec::with_capacity(settings.threads);
+        for worker in 0..settings.threads {
             let shared = Arc::clone(&shared);
             let tx = tx.clone();
-            workers.push(thread::spawn(move || process(&shared, tx)));
+            workers.push(thread::spawn(move || process(&shared, worker as u64, tx)));
         }
         drop(tx);
 
-        let outcome = reporter(cfg, &shared, &halt, rx, threads, &loaded.source);
+        let start = Instant::now();
+        let started_unix = status::unix_now();
+        let mut last_total = 0u64;
+        let mut last_at = start;
+        let mut next_tick = start + settings.tick;
+        // A duration past what `Instant` can represent never ends the run.
+        let deadline = settings
+            .duration
+            .and_then(|duration| start.checked_add(duration));
+        let mut bounded = false;
+        while !settings.stop.should_exit() {
+            let wake = deadline.map_or(next_tick, |deadline| deadline.min(next_tick));
+            match rx.recv_timeout(wake.saturating_duration_since(Instant::now())) {
+                Ok(hit) => {
+                    settings.emit_hit(&hit);
+                    continue;
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                // Every worker is gone: they reached `max_keys`, or panicked.
+                Err(RecvTimeoutError::Disconnected) => {
+                    bounded = shared.key_bound_reached();
+                    break;
+                }
+            }
+            let now = Instant::now();
+            if deadline.is_some_and(|deadline| now >= deadline) {
+                bounded = true;
+                break;
+            }
+            if now < next_tick {
+                continue;
+            }
+            next_tick = now + settings.tick;
+            let total = shared.keys.load(Ordering::Relaxed);
+            let dt = (now - last_at).as_secs_f64().max(0.001);
+            let progress = Progress {
+                keys_checked: total,
+                hits: shared.hits.load(Ordering::Relaxed),
+                elapsed: now - start,
+                keys_per_sec_avg: total as f64 / (now - start).as_secs_f64().max(0.001),
+                keys_per_sec_inst: total.saturating_sub(last_total) as f64 / dt,
+            };
+            if let Some(callback) = &mut settings.on_progress {
+                callback(&progress);
+            }
+            let status = settings.status(started_unix, &progress, true, false);
+            if let Some(callback) = &mut settings.on_status {
+                callback(&status);
+            }
+            last_total = total;
+            last_at = now;
+        }
+
         shared.running.store(false, Ordering::Relaxed);
         for worker in workers {
             let _ = worker.join();
         }
+        for hit in rx.iter() {
+            settings.emit_hit(&hit);
+        }
 
-        match outcome {
-            RunOutcome::Stopped => return Ok(RunOutcome::Stopped),
-            RunOutcome::Reload => {
-                if !cfg.auto_update {
-                    return Ok(RunOutcome::Reload);
-                }
-                println!("releasing snapshot and downloading a fresh funded set");
-                drop(shared);
-                match db::refresh_snapshot(cfg, &cfg.source_url) {
-                    Ok((count, skipped, elapsed)) => {
-                        println!(
-                            "Updated snapshot to {count} hash160s in {elapsed:.2?} ({skipped} skipped)"
-                        );
-                    }
-                    Err(error) => {
-                        eprintln!("snapshot update failed: {error}; reloading previous file");
-                        let notifier = Notifier::from_config(&cfg.notify);
-                        notifier.send(
-                            "Plutus 更新失败",
-                            &format!("node={} error={error}", config::node_name()),
-                        );
-                    }
-                }
-            }
+        let reload = settings.stop.0.reload.swap(false, Ordering::Relaxed);
+        let reload = reload && !settings.stop.is_stopped() && !bounded;
+        let outcome = if reload {
+            RunOutcome::Reload
+        } else if bounded && !settings.stop.is_stopped() {
+            RunOutcome::Bounded
+        } else {
+            RunOutcome::Stopped
+        };
+        let summary = Summary {
+            outcome,
+            started_unix,
+            keys_checked: shared.keys.load(Ordering::Relaxed),
+            hits: shared.hits.load(Ordering::Relaxed),
+            elapsed: start.elapsed(),
+            per_thread_keys: shared
+                .worker_keys
+                .iter()
+                .map(|keys| keys.load(Ordering::Relaxed))
+                .collect(),
+        };
+        let last = Progress {
+            keys_checked: summary.keys_checked,
+            hits: summary.hits,
+            elapsed: summary.elapsed,
+            keys_per_sec_avg: 0.0,
+            keys_per_sec_inst: 0.0,
+        };
+        let status = settings.status(started_unix, &last, false, reload);
+        if let Some(callback) = &mut settings.on_status {
+            callback(&status);
         }
+        summary
     }
 }
 
-fn process(shared: &Shared, hits: Sender<HitNotice>) {
+impl EngineBuilder {
+    fn emit_hit(&mut self, hit: &Hit) {
+        if let Some(callback) = &mut self.on_hit {
+            callback(hit);
+        }
+    }
+
+    fn status(
+        &self,
+        started_unix: u64,
+        progress: &Progress,
+        running: bool,
+        reload: bool,
+    ) -> Status {
+        Status {
+            started_unix,
+            now_unix: status::unix_now(),
+            uptime_secs: progress.elapsed.as_secs(),
+            keys_checked: progress.keys_checked,
+            keys_per_sec_avg: progress.keys_per_sec_avg,
+            keys_per_sec_inst: progress.keys_per_sec_inst,
+            hits: progress.hits,
+            db_size: self.set.len(),
+            threads: self.threads,
+            check_uncompressed: self.check_uncompressed,
+            simd: simd_name(),
+            snapshot: self.snapshot.clone(),
+            lookup: self.set.lookup_name(),
+            ram_bytes: self.set.ram_bytes(),
+            cpu_percent: self.controls.cpu_percent(),
+            node: self.node.clone(),
+            running,
+            paused: running && self.controls.is_paused(),
+            reload_requested: reload,
+        }
+    }
+}
+
+fn process(shared: &Shared, worker: u64, hits: Sender<Hit>) {
     let mut rng = rand::thread_rng();
     let mut walk = ec::Walk::new(BATCH);
     let mut comp = vec![0u8; BATCH * 33];
-    let mut h160 = vec![0u8; BATCH * 20];
+    let mut h160 = vec![[0u8; 20]; BATCH];
     let mut uncomp = if shared.check_uncompressed {
         vec![0u8; BATCH * 65]
     } else {
         Vec::new()
     };
     let mut h160_u = if shared.check_uncompressed {
-        vec![0u8; BATCH * 20]
+        vec![[0u8; 20]; BATCH]
     } else {
         Vec::new()
     };
     let mut since_report: u64 = 0;
+    // Counting against a private share keeps the shared counters off the
+    // per-batch path.
+    let budget = shared.key_budget(worker);
+    let mut walked: u64 = 0;
+    let walking = |walked: u64| {
+        shared.running.load(Ordering::Relaxed) && budget.is_none_or(|budget| walked < budget)
+    };
+    let mut first = shared
+        .start
+        .and_then(|start| offset_secret(&start, worker.saturating_mul(shared.walk_span)));
 
-    while shared.running.load(Ordering::Relaxed) {
-        let start_secret = random_secret(&mut rng);
+    while walking(walked) {
+        let start_secret = first.take().unwrap_or_else(|| random_secret(&mut rng));
         let start_pub = PublicKey::from_secret_key(&shared.secp, &start_secret);
         if !walk.set_start(&start_pub.serialize()) {
             continue;
         }
 
         let mut base: u64 = 0;
-        while base < shared.walk_span && shared.running.load(Ordering::Relaxed) {
+        while base < shared.walk_span && walking(walked) {
+            if shared.controls.is_paused() {
+                thread::sleep(PAUSE_POLL);
+                continue;
+            }
             let batch_started = Instant::now();
             if shared.check_uncompressed {
                 walk.batch(BATCH, &mut comp, Some(&mut uncomp));
@@ -315,35 +744,54 @@ fn process(shared: &Shared, hits: Sender<HitNotice>) {
                 walk.batch(BATCH, &mut comp, None);
             }
 
-            hash_batch(&comp, &mut h160, BATCH);
+            hash_batch(&comp, h160.as_flattened_mut(), BATCH);
+            shared.set.match_batch(&h160, &mut |i, tag| {
+                on_hit(shared, &start_secret, base + i as u64, true, tag, &hits);
+            });
             if shared.check_uncompressed {
-                hash_batch_uncomp(&uncomp, &mut h160_u, BATCH);
-            }
-
-            for i in 0..BATCH {
-                let hash: &[u8; 20] = h160[i * 20..i * 20 + 20].try_into().unwrap();
-                if shared.db.contains(hash) {
-                    on_hit(shared, &start_secret, base + i as u64, true, &hits);
-                }
-                if shared.check_uncompressed {
-                    let hash_u: &[u8; 20] = h160_u[i * 20..i * 20 + 20].try_into().unwrap();
-                    if shared.db.contains(hash_u) {
-                        on_hit(shared, &start_secret, base + i as u64, false, &hits);
-                    }
-                }
+                hash_batch_uncomp(&uncomp, h160_u.as_flattened_mut(), BATCH);
+                shared.set.match_batch(&h160_u, &mut |i, tag| {
+                    on_hit(shared, &start_secret, base + i as u64, false, tag, &hits);
+                });
             }
 
             base += BATCH as u64;
+            walked += BATCH as u64;
             since_report += BATCH as u64;
             if since_report >= REPORT_BLOCK {
-                shared.keys.fetch_add(since_report, Ordering::Relaxed);
+                shared.count_keys(worker, since_report);
                 since_report = 0;
             }
-            throttle(shared.cpu_percent, batch_started);
+            throttle(shared.controls.cpu_percent(), batch_started);
         }
     }
     if since_report > 0 {
-        shared.keys.fetch_add(since_report, Ordering::Relaxed);
+        shared.count_keys(worker, since_report);
+    }
+}
+
+impl Shared {
+    fn count_keys(&self, worker: u64, keys: u64) {
+        self.keys.fetch_add(keys, Ordering::Relaxed);
+        self.worker_keys[worker as usize].fetch_add(keys, Ordering::Relaxed);
+    }
+
+    fn key_bound_reached(&self) -> bool {
+        self.max_keys
+            .is_some_and(|max| self.keys.load(Ordering::Relaxed) >= max)
+    }
+
+    /// This worker's share of `max_keys`; the remainder goes one key each to
+    /// the first workers.
+    fn key_budget(&self, worker: u64) -> Option<u64> {
+        let workers = self.worker_keys.len() as u64;
+        self.max_keys
+            .map(|max| max / workers + u64::from(worker < max % workers))
+    }
+
+    fn chain_for(&self, tag: Option<&str>) -> Chain {
+        tag.and_then(|tag| self.tag_chains.iter().find(|(t, _)| t == tag))
+            .map_or(self.chain, |(_, chain)| *chain)
     }
 }
 
@@ -352,62 +800,72 @@ fn on_hit(
     start_secret: &SecretKey,
     offset: u64,
     compressed: bool,
-    hits: &Sender<HitNotice>,
+    tag: Option<&str>,
+    hits: &Sender<Hit>,
 ) {
-    let address = match persist_hit(
+    let (address, persisted) = persist_hit(
         &shared.secp,
         start_secret,
         offset,
         compressed,
+        tag,
+        shared.chain_for(tag),
         &shared.findings,
-    ) {
-        Ok(address) => address,
-        Err(error) => {
-            eprintln!("failed to persist hit: {error}");
-            return;
-        }
-    };
+    );
+    let mut persist_error = persisted
+        .err()
+        .map(|error| format!("failed to persist hit: {error}"));
     shared.hits.fetch_add(1, Ordering::Relaxed);
-    if let Err(error) =
-        pending::enqueue(&shared.data_dir, &address, compressed, pending::unix_now())
-    {
-        eprintln!("failed to persist pending hit alert: {error}");
+    if let Some(dir) = &shared.pending_dir {
+        if let Err(error) = pending::enqueue(dir, &address, compressed, pending::unix_now()) {
+            persist_error
+                .get_or_insert_with(|| format!("failed to persist pending hit alert: {error}"));
+        }
     }
-    let _ = hits.send(HitNotice {
+    let _ = hits.send(Hit {
         address,
         compressed,
+        tag: tag.map(str::to_owned),
+        persist_error,
     });
 }
 
+/// The hit's address, and whether its record reached the findings file.
 fn persist_hit(
     secp: &Secp256k1<All>,
     start_secret: &SecretKey,
     offset: u64,
     compressed: bool,
+    tag: Option<&str>,
+    chain: Chain,
     findings: &std::path::Path,
-) -> Result<String, String> {
-    let mut tweak = [0u8; 32];
-    tweak[24..].copy_from_slice(&offset.to_be_bytes());
-    let secret_key = start_secret
-        .add_tweak(&Scalar::from_be_bytes(tweak).expect("offset < order"))
-        .expect("valid secret");
+) -> (String, Result<(), String>) {
+    let secret_key = offset_secret(start_secret, offset).expect("valid secret");
 
-    let mut private_key = PrivateKey::new(secret_key, Network::Bitcoin);
+    let mut private_key = PrivateKey::new(secret_key, chain.network);
     private_key.compressed = compressed;
     let public_key = bitcoin::PublicKey::from_private_key(secp, &private_key);
-    let address = Address::p2pkh(&public_key, Network::Bitcoin);
-    let address_s = address.to_string();
+    let address_s = chain.p2pkh(&hash160(&public_key.to_bytes()));
 
+    let set = tag.map(|tag| format!("# set={tag}\n")).unwrap_or_default();
     let record = format!(
-        "{}\n{}\n{}\n{}\n",
+        "{set}{}\n{}\n{}\n{}\n",
         secret_key.display_secret(),
-        private_key.to_wif(),
+        chain.wif(&secret_key, compressed),
         public_key,
         address_s
     );
-    println!("!!! MATCH FOUND -> {address_s}");
-    hit::persist(findings, &record).map_err(|e| e.to_string())?;
-    Ok(address_s)
+    let persisted = hit::persist(findings, &record).map_err(|e| e.to_string());
+    (address_s, persisted)
+}
+
+/// `start + offset`, or `None` past the end of the curve order.
+fn offset_secret(start: &SecretKey, offset: u64) -> Option<SecretKey> {
+    let mut tweak = [0u8; 32];
+    tweak[24..].copy_from_slice(&offset.to_be_bytes());
+    start
+        .add_tweak(&Scalar::from_be_bytes(tweak).expect("offset < order"))
+        .ok()
 }
 
 fn throttle(cpu_percent: u8, started: Instant) {
@@ -431,214 +889,6 @@ fn random_secret(rng: &mut impl rand::RngCore) -> SecretKey {
     }
 }
 
-fn reporter(
-    cfg: &Config,
-    shared: &Shared,
-    halt: &AtomicBool,
-    rx: Receiver<HitNotice>,
-    threads: usize,
-    source: &str,
-) -> RunOutcome {
-    let notifier = Notifier::from_config(&cfg.notify);
-    let node = config::node_name();
-    notifier.send(
-        "Plutus 已启动",
-        &format!(
-            "node={node} threads={threads} db={} ram_mb={} uncompressed={} simd={} cpu={} lookup={} snapshot={source}",
-            shared.db.len(),
-            shared.db.ram_bytes() / (1024 * 1024),
-            cfg.check_uncompressed,
-            simd_name(),
-            cfg.cpu_percent,
-            shared.db.lookup_name(),
-        ),
-    );
-
-    let start = Instant::now();
-    let started_unix = status::unix_now();
-    let mut last_total = 0u64;
-    let mut last_at = start;
-    let mut last_heartbeat = Instant::now();
-    let mut last_age_check = Instant::now();
-    let mut last_progress_log = Instant::now();
-    let heartbeat = Duration::from_secs(cfg.heartbeat_minutes.saturating_mul(60).max(60));
-    let progress_log = Duration::from_secs(3600);
-    flush_pending(cfg, &notifier);
-
-    while shared.running.load(Ordering::Relaxed) {
-        if halt.load(Ordering::Relaxed) {
-            shared.running.store(false, Ordering::Relaxed);
-            break;
-        }
-        thread::sleep(Duration::from_secs(3));
-        drain_hits(&rx, cfg, &notifier);
-
-        let now = Instant::now();
-        let total = shared.keys.load(Ordering::Relaxed);
-        let hits = shared.hits.load(Ordering::Relaxed);
-        let dt = (now - last_at).as_secs_f64().max(0.001);
-        let inst = (total.saturating_sub(last_total)) as f64 / dt;
-        let avg = total as f64 / now.duration_since(start).as_secs_f64().max(0.001);
-        if last_progress_log.elapsed() >= progress_log {
-            println!("still running | checked {total} keys | {avg:.0} keys/s avg | hits {hits}");
-            last_progress_log = now;
-        }
-
-        let snapshot_age_hours = db::snapshot_age_secs(&cfg.snapshot)
-            .map(|s| s as f64 / 3600.0)
-            .unwrap_or(0.0);
-
-        let snapshot = Status {
-            started_unix,
-            now_unix: status::unix_now(),
-            uptime_secs: now.duration_since(start).as_secs(),
-            keys_checked: total,
-            keys_per_sec_avg: avg,
-            keys_per_sec_inst: inst,
-            hits,
-            db_size: shared.db.len(),
-            threads,
-            check_uncompressed: cfg.check_uncompressed,
-            simd: simd_name(),
-            snapshot: source.to_owned(),
-            lookup: shared.db.lookup_name().to_owned(),
-            ram_bytes: shared.db.ram_bytes(),
-            cpu_percent: cfg.cpu_percent,
-            node: node.clone(),
-            running: true,
-            reload_requested: false,
-        };
-        if let Err(error) = status::write_atomic(&cfg.status, &snapshot) {
-            eprintln!("status write failed: {error}");
-        }
-
-        if last_heartbeat.elapsed() >= heartbeat {
-            notifier.send(
-                "Plutus 还活着",
-                &format!(
-                    "node={node} keys={total} avg_keys_s={avg:.0} hits={hits} db={} uptime_h={:.1} snapshot_age_h={snapshot_age_hours:.1} cpu={}",
-                    shared.db.len(),
-                    snapshot.uptime_secs as f64 / 3600.0,
-                    cfg.cpu_percent
-                ),
-            );
-            last_heartbeat = Instant::now();
-        }
-
-        if cfg.auto_update && last_age_check.elapsed() >= Duration::from_secs(600) {
-            last_age_check = Instant::now();
-            let max_age = cfg.max_snapshot_age_hours.saturating_mul(3600);
-            if db::snapshot_age_secs(&cfg.snapshot).unwrap_or(0) >= max_age {
-                println!(
-                    "snapshot older than {}h, recycling workers to refresh",
-                    cfg.max_snapshot_age_hours
-                );
-                notifier.send(
-                    "Plutus 正在更新地址库",
-                    &format!("node={node} snapshot_age_h={snapshot_age_hours:.1}"),
-                );
-                shared.reload.store(true, Ordering::Relaxed);
-                shared.running.store(false, Ordering::Relaxed);
-                break;
-            }
-        }
-
-        last_total = total;
-        last_at = now;
-    }
-
-    drain_hits(&rx, cfg, &notifier);
-    let total = shared.keys.load(Ordering::Relaxed);
-    let hits = shared.hits.load(Ordering::Relaxed);
-    let reload = shared.reload.load(Ordering::Relaxed) && !halt.load(Ordering::Relaxed);
-    println!("shutting down | checked {total} keys | hits {hits} | reload={reload}");
-    if !reload {
-        notifier.send(
-            "Plutus 已停止",
-            &format!(
-                "node={node} keys={total} hits={hits} db={}",
-                shared.db.len()
-            ),
-        );
-    }
-    let snapshot = Status {
-        started_unix,
-        now_unix: status::unix_now(),
-        uptime_secs: start.elapsed().as_secs(),
-        keys_checked: total,
-        keys_per_sec_avg: 0.0,
-        keys_per_sec_inst: 0.0,
-        hits,
-        db_size: shared.db.len(),
-        threads,
-        check_uncompressed: cfg.check_uncompressed,
-        simd: simd_name(),
-        snapshot: source.to_owned(),
-        lookup: shared.db.lookup_name().to_owned(),
-        ram_bytes: shared.db.ram_bytes(),
-        cpu_percent: cfg.cpu_percent,
-        node,
-        running: false,
-        reload_requested: reload,
-    };
-    let _ = status::write_atomic(&cfg.status, &snapshot);
-    if reload {
-        RunOutcome::Reload
-    } else {
-        RunOutcome::Stopped
-    }
-}
-
-fn drain_hits(rx: &Receiver<HitNotice>, cfg: &Config, notifier: &Notifier) {
-    let now = pending::unix_now();
-    while let Ok(hit) = rx.try_recv() {
-        if let Err(error) = pending::enqueue(&cfg.data_dir, &hit.address, hit.compressed, now) {
-            eprintln!("failed to persist pending hit alert: {error}");
-        }
-    }
-    flush_pending(cfg, notifier);
-}
-
-fn flush_pending(cfg: &Config, notifier: &Notifier) {
-    if !notifier.enabled() {
-        return;
-    }
-    let now = pending::unix_now();
-    let interval = cfg.notify.hit_repeat_secs;
-    let max = cfg.notify.hit_repeat_max;
-    let due = match pending::due(&cfg.data_dir, now, interval, max) {
-        Ok(items) => items,
-        Err(error) => {
-            eprintln!("pending hit queue: {error}");
-            return;
-        }
-    };
-    for item in due {
-        let enc = if item.compressed {
-            "compressed"
-        } else {
-            "uncompressed"
-        };
-        let attempt = item.sent.saturating_add(1);
-        let title = if max == 0 {
-            format!("Plutus 命中 #{attempt}")
-        } else {
-            format!("Plutus 命中 {attempt}/{max}")
-        };
-        let body = format!("address={} encoding={enc} attempt={attempt}", item.address);
-        match notifier.send_hit(&title, &body, attempt) {
-            Ok(()) => {
-                if let Err(error) =
-                    pending::mark_sent(&cfg.data_dir, &item.address, item.compressed, now)
-                {
-                    eprintln!("failed to record hit alert: {error}");
-                }
-            }
-            Err(error) => eprintln!("notify failed: {error}"),
-        }
-    }
-}
-
 #[cfg(test)]
 fn generator(secp: &Secp256k1<All>) -> PublicKey {
     let mut one = [0u8; 32];
@@ -649,6 +899,11 @@ fn generator(secp: &Secp256k1<All>) -> PublicKey {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::db::Db;
+    use crate::matcher::{MemorySet, Tagged};
+    use crate::test_util::unique_dir;
+    use bitcoin::{Address, Network};
+    use std::fs;
 
     fn secret_from_u8(last: u8) -> SecretKey {
         let mut b = [0u8; 32];
@@ -828,4 +1083,314 @@ mod tests {
             pk = pk.combine(&g).unwrap();
         }
     }
+
+    #[test]
+    fn bounded_runs_stop_at_their_key_or_time_limit() {
+        let max_keys = BATCH as u64 * 8;
+        let summary = Engine::builder(Db::Sorted(Vec::new()))
+            .threads(2)
+            .max_keys(max_keys)
+            .tick(Duration::from_millis(20))
+            .build()
+            .run();
+        assert_eq!(summary.outcome, RunOutcome::Bounded);
+        assert!(summary.keys_checked >= max_keys);
+        assert!(summary.keys_checked <= max_keys + 2 * BATCH as u64);
+        assert_eq!(summary.per_thread_keys.len(), 2);
+        assert_eq!(
+            summary.per_thread_keys.iter().sum::<u64>(),
+            summary.keys_checked
+        );
+
+        // Shares that do not divide evenly still cover the bound, each
+        // rounded up to a whole batch.
+        let max_keys = BATCH as u64 * 8 + 1;
+        let summary = Engine::builder(Db::Sorted(Vec::new()))
+            .threads(3)
+            .max_keys(max_keys)
+            .tick(Duration::from_millis(20))
+            .build()
+            .run();
+        assert_eq!(summary.outcome, RunOutcome::Bounded);
+        assert_eq!(summary.per_thread_keys, [3 * BATCH as u64; 3]);
+
+        let summary = Engine::builder(Db::Sorted(Vec::new()))
+            .threads(1)
+            .duration(Duration::from_millis(150))
+            .build()
+            .run();
+        assert_eq!(summary.outcome, RunOutcome::Bounded);
+        assert!(summary.elapsed >= Duration::from_millis(150));
+        assert!(summary.elapsed < Duration::from_secs(5));
+        assert!(summary.keys_checked > 0);
+
+        // A duration `Instant` cannot add is no deadline, not a panic.
+        let summary = Engine::builder(Db::Sorted(Vec::new()))
+            .threads(1)
+            .duration(Duration::MAX)
+            .max_keys(BATCH as u64)
+            .build()
+            .run();
+        assert_eq!(summary.outcome, RunOutcome::Bounded);
+    }
+
+    #[test]
+    fn tagged_chains_report_hits_in_their_own_formats() {
+        use crate::chain::{self, CHAINS};
+        use std::sync::Mutex;
+
+        let secp = Secp256k1::new();
+        let hash_of = |n: u8| {
+            let key = bitcoin::PublicKey::from_private_key(
+                &secp,
+                &PrivateKey::new(secret_from_u8(n), Network::Bitcoin),
+            );
+            hash160(&key.to_bytes())
+        };
+        let litecoin = CHAINS
+            .iter()
+            .find(|c| c.name == "litecoin")
+            .copied()
+            .unwrap();
+        let set = Tagged::new()
+            .with("bitcoin", MemorySet::new([hash_of(2)]))
+            .with("litecoin", MemorySet::new([hash_of(1)]));
+        let dir = unique_dir("plutus-engine-chains");
+        let findings = dir.join("findings.txt");
+        let stop = StopToken::new();
+        let hits = Arc::new(Mutex::new(Vec::new()));
+        Engine::builder(set)
+            .threads(1)
+            .check_uncompressed(false)
+            .start_key(secret_from_u8(1))
+            .tag_chain("litecoin", litecoin)
+            .findings(&findings)
+            .stop_token(stop.clone())
+            .tick(Duration::from_millis(20))
+            .on_hit({
+                let hits = Arc::clone(&hits);
+                let stop = stop.clone();
+                move |hit| {
+                    let mut hits = hits.lock().unwrap();
+                    hits.push((hit.tag.clone().unwrap(), hit.address.clone()));
+                    if hits.len() == 2 {
+                        stop.stop();
+                    }
+                }
+            })
+            .build()
+            .run();
+
+        let mut hits = hits.lock().unwrap().clone();
+        hits.sort();
+        assert_eq!(
+            hits,
+            [
+                ("bitcoin".to_owned(), chain::BITCOIN.p2pkh(&hash_of(2))),
+                ("litecoin".to_owned(), litecoin.p2pkh(&hash_of(1))),
+            ]
+        );
+        assert!(hits[1].1.starts_with('L'));
+        let record = fs::read_to_string(&findings).unwrap();
+        assert!(record.contains(&litecoin.wif(&secret_from_u8(1), true)));
+        assert!(record.contains(&chain::BITCOIN.wif(&secret_from_u8(2), true)));
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn a_key_funded_on_two_chains_is_recorded_on_both() {
+        use crate::chain::{self, CHAINS};
+        use std::sync::Mutex;
+
+        let secp = Secp256k1::new();
+        let key = bitcoin::PublicKey::from_private_key(
+            &secp,
+            &PrivateKey::new(secret_from_u8(1), Network::Bitcoin),
+        );
+        let hash = hash160(&key.to_bytes());
+        let cash = CHAINS
+            .iter()
+            .find(|c| c.name == "bitcoin-cash")
+            .copied()
+            .unwrap();
+        // BTC and BCH share every pre-fork P2PKH hash160.
+        let set = Tagged::new()
+            .with("bitcoin", MemorySet::new([hash]))
+            .with("bitcoin-cash", MemorySet::new([hash]));
+        let dir = unique_dir("plutus-engine-overlap");
+        let findings = dir.join("findings.txt");
+        let stop = StopToken::new();
+        let hits = Arc::new(Mutex::new(Vec::new()));
+        Engine::builder(set)
+            .threads(1)
+            .check_uncompressed(false)
+            .start_key(secret_from_u8(1))
+            .tag_chain("bitcoin-cash", cash)
+            .findings(&findings)
+            .stop_token(stop.clone())
+            .tick(Duration::from_millis(20))
+            .on_hit({
+                let hits = Arc::clone(&hits);
+                let stop = stop.clone();
+                move |hit| {
+                    let mut hits = hits.lock().unwrap();
+                    hits.push(hit.address.clone());
+                    if hits.len() == 2 {
+                        stop.stop();
+                    }
+                }
+            })
+            .build()
+            .run();
+
+        let mut hits = hits.lock().unwrap().clone();
+        hits.sort();
+        let mut expected = [chain::BITCOIN.p2pkh(&hash), cash.p2pkh(&hash)];
+        expected.sort();
+        assert_eq!(hits, expected);
+        let record = fs::read_to_string(&findings).unwrap();
+        for address in &expected {
+            assert!(record.contains(address.as_str()), "{address} not recorded");
+        }
+        assert!(record.contains("# set=bitcoin\n"));
+        assert!(record.contains("# set=bitcoin-cash\n"));
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn unwritable_findings_are_reported_with_the_hit() {
+        use std::sync::Mutex;
+
+        let secp = Secp256k1::new();
+        let key = bitcoin::PublicKey::from_private_key(
+            &secp,
+            &PrivateKey::new(secret_from_u8(1), Network::Bitcoin),
+        );
+        // A directory where the findings file should be.
+        let findings = unique_dir("plutus-engine-unwritable");
+        let stop = StopToken::new();
+        let errors = Arc::new(Mutex::new(Vec::new()));
+        let summary = Engine::builder(MemorySet::new([hash160(&key.to_bytes())]))
+            .threads(1)
+            .check_uncompressed(false)
+            .start_key(secret_from_u8(1))
+            .findings(&findings)
+            .stop_token(stop.clone())
+            .tick(Duration::from_millis(20))
+            .on_hit({
+                let errors = Arc::clone(&errors);
+                let stop = stop.clone();
+                move |hit| {
+                    errors.lock().unwrap().push(hit.persist_error.clone());
+                    stop.stop();
+                }
+            })
+            .build()
+            .run();
+
+        assert_eq!(summary.hits, 1);
+        let errors = errors.lock().unwrap();
+        assert!(
+            errors[0]
+                .as_deref()
+                .is_some_and(|error| error.starts_with("failed to persist hit")),
+            "{errors:?}"
+        );
+        let _ = fs::remove_dir_all(findings);
+    }
+
+    #[test]
+    fn engine_reports_through_callbacks_and_stops_on_its_token() {
+        use std::sync::Mutex;
+
+        let stop = StopToken::new();
+        let ticks = Arc::new(AtomicU64::new(0));
+        let statuses = Arc::new(Mutex::new(Vec::new()));
+        let engine = Engine::builder(Db::Sorted(Vec::new()))
+            .threads(2)
+            .walk_span(BATCH as u64 * 4)
+            .tick(Duration::from_millis(20))
+            .stop_token(stop.clone())
+            .on_progress({
+                let ticks = Arc::clone(&ticks);
+                let stop = stop.clone();
+                move |_| {
+                    if ticks.fetch_add(1, Ordering::Relaxed) == 2 {
+                        stop.request_reload();
+                    }
+                }
+            })
+            .on_hit(|hit| panic!("empty set matched {hit:?}"))
+            .on_status({
+                let statuses = Arc::clone(&statuses);
+                move |status| {
+                    let mut statuses = statuses.lock().unwrap();
+                    statuses.push((status.running, status.reload_requested, status.threads));
+                }
+            })
+            .build();
+
+        let summary = engine.run();
+        assert_eq!(summary.outcome, RunOutcome::Reload);
+        assert!(summary.keys_checked > 0);
+        assert_eq!(ticks.load(Ordering::Relaxed), 3);
+        let statuses = statuses.lock().unwrap();
+        assert_eq!(statuses.len(), 4);
+        assert_eq!(statuses[0], (true, false, 2));
+        assert_eq!(statuses[3], (false, true, 2));
+
+        // The reload request was consumed; a stop before the next run ends it
+        // at once.
+        assert!(!stop.should_exit());
+        stop.stop();
+        let summary = Engine::builder(Db::Sorted(Vec::new()))
+            .threads(1)
+            .stop_token(stop)
+            .build()
+            .run();
+        assert_eq!(summary.outcome, RunOutcome::Stopped);
+    }
+
+    #[test]
+    fn paused_workers_hold_until_resumed_and_take_live_throttle() {
+        use std::sync::Mutex;
+
+        let stop = StopToken::new();
+        let controls = Controls::new();
+        controls.pause();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let summary = Engine::builder(Db::Sorted(Vec::new()))
+            .threads(2)
+            .cpu_percent(100)
+            // Any key bound makes workers publish their counts every batch.
+            .max_keys(u64::MAX)
+            .tick(Duration::from_millis(20))
+            .stop_token(stop.clone())
+            .controls(controls.clone())
+            .on_status({
+                let seen = Arc::clone(&seen);
+                let controls = controls.clone();
+                let stop = stop.clone();
+                move |status| {
+                    let mut seen = seen.lock().unwrap();
+                    seen.push((status.paused, status.keys_checked, status.cpu_percent));
+                    if seen.len() == 3 {
+                        controls.set_cpu_percent(50);
+                        controls.resume();
+                    } else if status.keys_checked > 0 {
+                        stop.stop();
+                    }
+                }
+            })
+            .build()
+            .run();
+
+        assert_eq!(summary.outcome, RunOutcome::Stopped);
+        assert!(summary.keys_checked > 0);
+        let seen = seen.lock().unwrap();
+        assert!(seen[..3].iter().all(|s| *s == (true, 0, 100)));
+        let resumed = &seen[seen.len() - 2];
+        assert!(!resumed.0 && resumed.1 > 0 && resumed.2 == 50);
+        assert!(!seen.last().unwrap().0, "a stopped engine is not paused");
+    }
 }
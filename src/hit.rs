@@ -34,17 +34,12 @@ pub fn persist(path: &Path, record: &str) -> io::Result<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::env;
-    use std::time::{SystemTime, UNIX_EPOCH};
+    use crate::test_util::unique_dir;
 
     #[test]
     fn persist_creates_missing_file_and_parent() {
-        let unique = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_nanos();
-        let dir = env::temp_dir().join(format!("plutus-hit-{unique}"));
-        let path = dir.join("hits.txt");
+        let dir = unique_dir("plutus-hit");
+        let path = dir.join("missing").join("hits.txt");
         persist(&path, "example-record\n").unwrap();
         let body = fs::read_to_string(&path).unwrap();
         assert!(body.contains("example-record"));
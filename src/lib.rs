@@ -5,13 +5,23 @@
 //! secret material.
 
 pub mod bloom;
+pub mod chain;
+pub mod compress;
 pub mod config;
+pub mod control;
 pub mod db;
 pub mod engine;
 pub mod hit;
+pub mod matcher;
 pub mod notify;
+pub mod peer;
 pub mod pending;
+pub mod selftest;
+pub mod source;
 pub mod status;
+#[cfg(test)]
+mod test_util;
 
 pub use config::Config;
 pub use db::Db;
+pub use engine::Engine;
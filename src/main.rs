@@ -1,15 +1,24 @@
 use std::fs;
 use std::io::{self, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::ExitCode;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 
+use plutus_rustus::chain::Chain;
 use plutus_rustus::config::{self, Config};
+use plutus_rustus::control::{self, Request};
 use plutus_rustus::db;
-use plutus_rustus::engine::{self, RunOutcome};
+use plutus_rustus::engine::{self, Controls, Engine, Hit, Progress, RunOutcome, StopToken};
+use plutus_rustus::matcher::{Matcher, Tagged};
 use plutus_rustus::notify::Notifier;
+use plutus_rustus::peer;
 use plutus_rustus::pending;
+use plutus_rustus::selftest;
+use plutus_rustus::source;
+use plutus_rustus::status;
 
 #[derive(Parser)]
 #[command(
@@ -25,13 +34,25 @@ struct Cli {
 #[derive(Subcommand)]
 enum Command {
     /// Run the collider (default if no subcommand is given).
-    Run,
+    Run(RunArgs),
     /// Check config, snapshot, write paths, RAM hints, and notifier wiring.
-    Doctor,
+    Doctor {
+        /// Also run `selftest`.
+        #[arg(long)]
+        deep: bool,
+    },
+    /// Plant known keys in a temporary snapshot and prove the engine finds them.
+    Selftest,
     /// Send one Bark/webhook test that contains no secrets.
     NotifyTest,
     /// Stop repeating hit alerts. Findings on disk are kept.
     Ack,
+    /// Pause, resume or reload `config.toml` in a running engine, like
+    /// SIGUSR1, SIGUSR2 and SIGHUP.
+    Control {
+        #[arg(value_name = "pause|resume|reload", value_parser = parse_request)]
+        request: Request,
+    },
     /// Snapshot import, refresh, and inspection.
     Data {
         #[command(subcommand)]
@@ -39,40 +60,103 @@ enum Command {
     },
 }
 
+#[derive(Args, Default)]
+struct RunArgs {
+    /// Stop cleanly after this long: `90`, `90s`, `15m`, `2h` or `1d`.
+    #[arg(long, value_name = "TIME", value_parser = parse_duration)]
+    duration: Option<Duration>,
+    /// Stop cleanly once this many keys have been checked.
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    max_keys: Option<u64>,
+    /// Write totals, rates, SIMD kernel, lookup and snapshot identity here
+    /// when the run ends.
+    #[arg(long, value_name = "PATH")]
+    summary_json: Option<PathBuf>,
+}
+
+impl RunArgs {
+    fn bounded(&self) -> bool {
+        self.duration.is_some() || self.max_keys.is_some()
+    }
+}
+
 #[derive(Subcommand)]
 enum DataCommand {
     /// Convert bundled pickle slices into the binary snapshot.
-    Prepare,
-    /// Download the latest funded-address dump and atomically replace the snapshot.
-    Update {
+    Prepare {
+        /// Leave unreadable slices out instead of failing (`data.skip_bad_pickles`).
         #[arg(long)]
+        skip_bad: bool,
+    },
+    /// Fetch the latest funded-address dump and atomically replace the snapshot.
+    Update {
+        /// Override `data.source_url`: http(s)://, s3://bucket/key, file:// or a path.
+        #[arg(long, conflicts_with = "from_peer")]
         source_url: Option<String>,
+        /// Copy a ready-made snapshot from another node's `data serve`.
+        #[arg(long, value_name = "URL")]
+        from_peer: Option<String>,
+    },
+    /// Serve the current snapshot read-only to other nodes on the LAN.
+    Serve {
+        #[arg(long, default_value = peer::DEFAULT_BIND)]
+        bind: String,
     },
     /// Print snapshot header fields.
     Inspect,
+    /// Look up addresses or hash160 hex in the snapshot (reads stdin if none are given).
+    Query {
+        inputs: Vec<String>,
+        /// One JSON object per line instead of key=value text.
+        #[arg(long)]
+        json: bool,
+    },
 }
 
 fn main() -> ExitCode {
     let cli = Cli::parse();
-    let cfg = config::load();
-    let result = match cli.command.unwrap_or(Command::Run) {
-        Command::Run => match engine::run(&cfg) {
-            Ok(RunOutcome::Stopped) => Ok(()),
+    let cfg = match config::load() {
+        Ok(cfg) => cfg,
+        Err(error) => {
+            eprintln!("plutus-rustus: {error}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let result = match cli
+        .command
+        .unwrap_or_else(|| Command::Run(RunArgs::default()))
+    {
+        Command::Run(args) => match run(&cfg, &args) {
+            Ok(RunOutcome::Stopped | RunOutcome::Bounded) => Ok(()),
             Ok(RunOutcome::Reload) => return ExitCode::from(engine::RELOAD_EXIT),
             Err(error) => Err(error),
         },
-        Command::Doctor => doctor(&cfg),
+        Command::Doctor { deep } => doctor(&cfg, deep),
+        Command::Selftest => selftest::run(&cfg).map(|report| {
+            println!("{}", selftest::describe(&report));
+        }),
         Command::NotifyTest => notify_test(&cfg),
         Command::Ack => ack_hits(&cfg),
+        Command::Control { request } => send_control(&cfg, request),
         Command::Data {
-            command: DataCommand::Prepare,
-        } => data_prepare(&cfg),
+            command: DataCommand::Prepare { skip_bad },
+        } => data_prepare(&cfg, skip_bad),
         Command::Data {
-            command: DataCommand::Update { source_url },
-        } => data_update(&cfg, source_url),
+            command:
+                DataCommand::Update {
+                    source_url,
+                    from_peer,
+                },
+        } => data_update(&cfg, source_url, from_peer),
+        Command::Data {
+            command: DataCommand::Serve { bind },
+        } => peer::serve(&cfg.snapshot, &bind).map_err(|e| e.to_string()),
         Command::Data {
             command: DataCommand::Inspect,
         } => data_inspect(&cfg),
+        Command::Data {
+            command: DataCommand::Query { inputs, json },
+        } => data_query(&cfg, inputs, json),
     };
     match result {
         Ok(()) => ExitCode::SUCCESS,
@@ -83,7 +167,428 @@ fn main() -> ExitCode {
     }
 }
 
-fn doctor(cfg: &Config) -> Result<(), String> {
+/// Load the snapshot and run an [`Engine`] over it until ctrl-c, refreshing
+/// the snapshot in between runs when it ages past `data.max_snapshot_age_hours`.
+fn run(cfg: &Config, args: &RunArgs) -> Result<RunOutcome, String> {
+    let stop = StopToken::new();
+    {
+        let stop = stop.clone();
+        ctrlc::set_handler(move || stop.stop()).map_err(|e| e.to_string())?;
+    }
+    control::install_signal_handlers().map_err(|e| e.to_string())?;
+    let controls = Controls::new();
+    let live = Arc::new(Mutex::new(Live {
+        cfg: cfg.clone(),
+        notifier: Arc::new(Notifier::from_config(&cfg.notify)),
+    }));
+    let node = config::node_name();
+
+    loop {
+        if stop.is_stopped() {
+            return Ok(RunOutcome::Stopped);
+        }
+        // Pick up settings reloaded during the previous run.
+        let (cfg, notifier) = {
+            let live = live.lock().unwrap();
+            (live.cfg.clone(), Arc::clone(&live.notifier))
+        };
+        let cfg = &cfg;
+
+        let loaded = db::load(cfg).map_err(|e| e.to_string())?;
+        println!(
+            "Loaded {} unique funded hash160s (P2PKH + P2WPKH) in {:.2?} from {} via {} (~{}MB RAM, {} skipped)",
+            loaded.db.len(),
+            loaded.elapsed,
+            loaded.source,
+            loaded.db.lookup_name(),
+            loaded.db.ram_bytes() / (1024 * 1024),
+            loaded.skipped
+        );
+        let extra = db::load_extra(cfg).map_err(|e| e.to_string())?;
+        let extra_chains: Vec<Chain> = extra.iter().map(|(chain, _)| *chain).collect();
+        let set: Arc<dyn Matcher> = if extra.is_empty() {
+            Arc::new(loaded.db)
+        } else {
+            let mut tagged = Tagged::new().with(cfg.chain.name, loaded.db);
+            for (chain, report) in extra {
+                println!(
+                    "Loaded {} {} hash160s in {:.2?} from {} via {} (~{}MB RAM)",
+                    report.db.len(),
+                    chain.name,
+                    report.elapsed,
+                    report.source,
+                    report.db.lookup_name(),
+                    report.db.ram_bytes() / (1024 * 1024)
+                );
+                tagged.push(chain.name, report.db);
+            }
+            Arc::new(tagged)
+        };
+
+        let threads = config::worker_count(cfg);
+        println!(
+            "Running on {threads} worker thread(s) | uncompressed={} | simd={} | cpu={} | lookup={} | walk_span={}",
+            cfg.check_uncompressed,
+            engine::simd_name(),
+            cfg.cpu_percent,
+            set.lookup_name(),
+            cfg.walk_span
+        );
+        let db_size = set.len();
+        notifier.send(
+            "Plutus 已启动",
+            &format!(
+                "node={node} threads={threads} db={db_size} ram_mb={} uncompressed={} simd={} cpu={} lookup={} snapshot={}",
+                set.ram_bytes() / (1024 * 1024),
+                cfg.check_uncompressed,
+                engine::simd_name(),
+                cfg.cpu_percent,
+                set.lookup_name(),
+                loaded.source,
+            ),
+        );
+        flush_pending(cfg, &notifier);
+
+        let status_path = cfg.status.clone();
+        // A bounded job measures one snapshot; it never stops to refresh it.
+        let reload_when_old = cfg.auto_update && !args.bounded();
+        let mut builder = extra_chains
+            .into_iter()
+            .fold(
+                Engine::builder(Arc::clone(&set)).config(cfg),
+                |builder, chain| builder.tag_chain(chain.name, chain),
+            )
+            .snapshot(loaded.source)
+            .node(node.clone())
+            .stop_token(stop.clone())
+            .controls(controls.clone())
+            .on_hit(on_hit(&live))
+            .on_progress(on_progress(
+                cfg,
+                &live,
+                &controls,
+                &stop,
+                db_size,
+                reload_when_old,
+            ))
+            .on_status(move |snapshot| {
+                if let Err(error) = status::write_atomic(&status_path, snapshot) {
+                    eprintln!("status write failed: {error}");
+                }
+            });
+        if let Some(duration) = args.duration {
+            builder = builder.duration(duration);
+        }
+        if let Some(max_keys) = args.max_keys {
+            builder = builder.max_keys(max_keys);
+        }
+        let summary = builder.build().run();
+        if let Some(path) = &args.summary_json {
+            match status::write_atomic(path, &run_summary(cfg, args, &summary, set.as_ref())) {
+                Ok(()) => println!("run summary written to {}", path.display()),
+                Err(error) => eprintln!("run summary write failed: {error}"),
+            }
+        }
+
+        let notifier = Arc::clone(&live.lock().unwrap().notifier);
+        let reload = summary.outcome == RunOutcome::Reload;
+        println!(
+            "shutting down | checked {} keys | hits {} | reload={reload}",
+            summary.keys_checked, summary.hits
+        );
+        match summary.outcome {
+            RunOutcome::Stopped | RunOutcome::Bounded => {
+                notifier.send(
+                    "Plutus 已停止",
+                    &format!(
+                        "node={node} keys={} hits={} db={db_size}",
+                        summary.keys_checked, summary.hits
+                    ),
+                );
+                return Ok(summary.outcome);
+            }
+            RunOutcome::Reload => {
+                if !cfg.auto_update {
+                    return Ok(RunOutcome::Reload);
+                }
+                println!("releasing snapshot and downloading a fresh funded set");
+                let refreshed = source::from_config(cfg)
+                    .and_then(|source| db::refresh_snapshot(cfg, source.as_ref()));
+                match refreshed {
+                    Ok((count, skipped, elapsed)) => {
+                        println!(
+                            "Updated snapshot to {count} hash160s in {elapsed:.2?} ({skipped} skipped)"
+                        );
+                    }
+                    Err(error) => {
+                        eprintln!("snapshot update failed: {error}; reloading previous file");
+                        notifier.send("Plutus 更新失败", &format!("node={node} error={error}"));
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn run_summary(
+    cfg: &Config,
+    args: &RunArgs,
+    summary: &engine::Summary,
+    set: &dyn Matcher,
+) -> status::RunSummary {
+    let secs = summary.elapsed.as_secs_f64().max(0.001);
+    let snapshots = std::iter::once(&cfg.snapshot)
+        .chain(&cfg.extra_snapshots)
+        .filter_map(|path| snapshot_identity(path).ok())
+        .collect();
+    status::RunSummary {
+        version: env!("CARGO_PKG_VERSION"),
+        node: config::node_name(),
+        outcome: summary.outcome.as_str(),
+        started_unix: summary.started_unix,
+        finished_unix: status::unix_now(),
+        elapsed_secs: summary.elapsed.as_secs_f64(),
+        duration_limit_secs: args.duration.map(|d| d.as_secs_f64()),
+        max_keys: args.max_keys,
+        keys_checked: summary.keys_checked,
+        hits: summary.hits,
+        keys_per_sec_avg: summary.keys_per_sec(),
+        threads: summary.per_thread_keys.len(),
+        per_thread_keys: summary.per_thread_keys.clone(),
+        per_thread_keys_per_sec: summary
+            .per_thread_keys
+            .iter()
+            .map(|&keys| keys as f64 / secs)
+            .collect(),
+        check_uncompressed: cfg.check_uncompressed,
+        cpu_percent: cfg.cpu_percent,
+        simd: engine::simd_name(),
+        lookup: set.lookup_name(),
+        ram_bytes: set.ram_bytes(),
+        db_size: set.len(),
+        snapshots,
+    }
+}
+
+fn snapshot_identity(path: &Path) -> io::Result<status::SnapshotIdentity> {
+    let header = db::read_header(path)?;
+    Ok(status::SnapshotIdentity {
+        path: path.display().to_string(),
+        magic: header.magic,
+        version: header.version,
+        count: header.count,
+        created_unix: header.created_unix,
+        size_bytes: fs::metadata(path)?.len(),
+        chain: header.chain.family,
+        network: header.chain.network.to_string(),
+    })
+}
+
+fn parse_request(value: &str) -> Result<Request, String> {
+    Request::parse(value).ok_or_else(|| format!("expected pause, resume or reload, not {value:?}"))
+}
+
+/// Longest `run --duration`: ten years, well inside what `Instant` can add.
+const MAX_DURATION: Duration = Duration::from_secs(3650 * 86400);
+
+/// `90`, `90s`, `15m`, `2h` or `1d`.
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration {value:?}"))?;
+    let scale = match unit {
+        "" | "s" => 1.0,
+        "m" => 60.0,
+        "h" => 3600.0,
+        "d" => 86400.0,
+        _ => {
+            return Err(format!(
+                "unknown unit {unit:?} in {value:?}; use s, m, h or d"
+            ))
+        }
+    };
+    let secs = number * scale;
+    if !secs.is_finite() || secs <= 0.0 {
+        return Err(format!("duration {value:?} must be positive"));
+    }
+    match Duration::try_from_secs_f64(secs) {
+        Ok(duration) if duration <= MAX_DURATION => Ok(duration),
+        _ => Err(format!("duration {value:?} is longer than 3650d")),
+    }
+}
+
+/// The engine has already written the finding and queued the alert; announce
+/// it and send the first alert right away.
+fn on_hit(live: &Arc<Mutex<Live>>) -> impl FnMut(&Hit) + Send + 'static {
+    let live = Arc::clone(live);
+    move |hit| {
+        match &hit.tag {
+            Some(tag) => println!("!!! MATCH FOUND -> {} ({tag})", hit.address),
+            None => println!("!!! MATCH FOUND -> {}", hit.address),
+        }
+        if let Some(error) = &hit.persist_error {
+            eprintln!("{error}");
+        }
+        let live = live.lock().unwrap();
+        flush_pending(&live.cfg, &live.notifier);
+    }
+}
+
+/// Pause, resume and reload requests, the hourly log line, heartbeat
+/// notifications, repeat hit alerts, and the snapshot age check that asks the
+/// engine to stop for a refresh.
+fn on_progress(
+    cfg: &Config,
+    live: &Arc<Mutex<Live>>,
+    controls: &Controls,
+    stop: &StopToken,
+    db_size: usize,
+    reload_when_old: bool,
+) -> impl FnMut(&Progress) + Send + 'static {
+    let data_dir = cfg.data_dir.clone();
+    let live = Arc::clone(live);
+    let controls = controls.clone();
+    let stop = stop.clone();
+    let node = config::node_name();
+    let progress_log = Duration::from_secs(3600);
+    let mut last_heartbeat = Instant::now();
+    let mut last_age_check = Instant::now();
+    let mut last_progress_log = Instant::now();
+    move |progress| {
+        for request in control::take(&data_dir) {
+            apply_request(request, &live, &controls);
+        }
+        let live = live.lock().unwrap();
+        let (cfg, notifier) = (&live.cfg, &live.notifier);
+        flush_pending(cfg, notifier);
+        let total = progress.keys_checked;
+        let hits = progress.hits;
+        let avg = progress.keys_per_sec_avg;
+        if last_progress_log.elapsed() >= progress_log {
+            println!("still running | checked {total} keys | {avg:.0} keys/s avg | hits {hits}");
+            last_progress_log = Instant::now();
+        }
+
+        let snapshot_age_hours = db::snapshot_age_secs(&cfg.snapshot)
+            .map(|s| s as f64 / 3600.0)
+            .unwrap_or(0.0);
+        let heartbeat = Duration::from_secs(cfg.heartbeat_minutes.saturating_mul(60).max(60));
+        if last_heartbeat.elapsed() >= heartbeat {
+            notifier.send(
+                "Plutus 还活着",
+                &format!(
+                    "node={node} keys={total} avg_keys_s={avg:.0} hits={hits} db={db_size} uptime_h={:.1} snapshot_age_h={snapshot_age_hours:.1} cpu={} paused={}",
+                    progress.elapsed.as_secs_f64() / 3600.0,
+                    controls.cpu_percent(),
+                    controls.is_paused()
+                ),
+            );
+            last_heartbeat = Instant::now();
+        }
+
+        if reload_when_old && last_age_check.elapsed() >= Duration::from_secs(600) {
+            last_age_check = Instant::now();
+            let max_age = cfg.max_snapshot_age_hours.saturating_mul(3600);
+            if db::snapshot_age_secs(&cfg.snapshot).unwrap_or(0) >= max_age {
+                println!(
+                    "snapshot older than {}h, recycling workers to refresh",
+                    cfg.max_snapshot_age_hours
+                );
+                notifier.send(
+                    "Plutus 正在更新地址库",
+                    &format!("node={node} snapshot_age_h={snapshot_age_hours:.1}"),
+                );
+                stop.request_reload();
+            }
+        }
+    }
+}
+
+/// Settings a SIGHUP or `control reload` changes while the engine runs:
+/// `cpu_percent`, `heartbeat_minutes` and `[notify]`. The rest of `cfg` stays
+/// as loaded at start.
+struct Live {
+    cfg: Config,
+    notifier: Arc<Notifier>,
+}
+
+fn apply_request(request: Request, live: &Mutex<Live>, controls: &Controls) {
+    match request {
+        Request::Pause => {
+            controls.pause();
+            println!("paused | workers parked, snapshot kept loaded");
+        }
+        Request::Resume => {
+            controls.resume();
+            println!("resumed");
+        }
+        Request::Reload => match config::reload() {
+            Ok(fresh) => {
+                let mut live = live.lock().unwrap();
+                live.cfg.cpu_percent = fresh.cpu_percent;
+                live.cfg.heartbeat_minutes = fresh.heartbeat_minutes;
+                live.notifier = Arc::new(Notifier::from_config(&fresh.notify));
+                live.cfg.notify = fresh.notify;
+                controls.set_cpu_percent(fresh.cpu_percent);
+                println!(
+                    "config reloaded | cpu={} heartbeat_minutes={} notify={} | other settings apply on restart",
+                    fresh.cpu_percent,
+                    fresh.heartbeat_minutes,
+                    live.notifier.configured_without_secret()
+                );
+            }
+            Err(error) => eprintln!("config reload failed, keeping current settings: {error}"),
+        },
+    }
+}
+
+/// Send every pending hit alert whose repeat interval has elapsed.
+fn flush_pending(cfg: &Config, notifier: &Notifier) {
+    if !notifier.enabled() {
+        return;
+    }
+    let now = pending::unix_now();
+    let interval = cfg.notify.hit_repeat_secs;
+    let max = cfg.notify.hit_repeat_max;
+    let due = match pending::due(&cfg.data_dir, now, interval, max) {
+        Ok(items) => items,
+        Err(error) => {
+            eprintln!("pending hit queue: {error}");
+            return;
+        }
+    };
+    for item in due {
+        let enc = if item.compressed {
+            "compressed"
+        } else {
+            "uncompressed"
+        };
+        let attempt = item.sent.saturating_add(1);
+        let title = if max == 0 {
+            format!("Plutus 命中 #{attempt}")
+        } else {
+            format!("Plutus 命中 {attempt}/{max}")
+        };
+        let body = format!("address={} encoding={enc} attempt={attempt}", item.address);
+        match notifier.send_hit(&title, &body, attempt) {
+            Ok(()) => {
+                if let Err(error) =
+                    pending::mark_sent(&cfg.data_dir, &item.address, item.compressed, now)
+                {
+                    eprintln!("failed to record hit alert: {error}");
+                }
+            }
+            Err(error) => eprintln!("notify failed: {error}"),
+        }
+    }
+}
+
+fn doctor(cfg: &Config, deep: bool) -> Result<(), String> {
     let mut ok = true;
     let threads = config::worker_count(cfg);
     println!("node={}", config::node_name());
@@ -91,6 +596,11 @@ fn doctor(cfg: &Config) -> Result<(), String> {
     println!("check_uncompressed={}", cfg.check_uncompressed);
     println!("cpu_percent={}", cfg.cpu_percent);
     println!("lookup={}", cfg.lookup.as_str());
+    println!("engine_chain={} {}", cfg.chain.family, cfg.chain.network);
+    println!(
+        "data_chain={} {}",
+        cfg.data_chain.family, cfg.data_chain.network
+    );
     println!("bits_per_key={}", cfg.bits_per_key);
     println!("simd={}", engine::simd_name());
     println!("auto_update={}", cfg.auto_update);
@@ -138,6 +648,10 @@ fn doctor(cfg: &Config) -> Result<(), String> {
                 ok = false;
             }
         }
+        if let Err(error) = db::require_chain(&cfg.snapshot, cfg.chain) {
+            println!("chain=ERROR {error}");
+            ok = false;
+        }
     } else if cfg.pickle_dir.is_dir() {
         println!("snapshot=missing (pickle fallback present; first run will migrate)");
     } else {
@@ -147,6 +661,15 @@ fn doctor(cfg: &Config) -> Result<(), String> {
 
     let notifier = Notifier::from_config(&cfg.notify);
     println!("notify={}", notifier.configured_without_secret());
+    if deep {
+        match selftest::run(cfg) {
+            Ok(report) => println!("{}", selftest::describe(&report)),
+            Err(error) => {
+                println!("selftest=ERROR {error}");
+                ok = false;
+            }
+        }
+    }
     if ok {
         println!("doctor=ok");
         Ok(())
@@ -161,6 +684,16 @@ fn ack_hits(cfg: &Config) -> Result<(), String> {
     Ok(())
 }
 
+fn send_control(cfg: &Config, request: Request) -> Result<(), String> {
+    control::send(&cfg.data_dir, request).map_err(|e| e.to_string())?;
+    println!(
+        "queued {} in {}; a running engine applies it within one status tick",
+        request.as_str(),
+        control::control_path(&cfg.data_dir).display()
+    );
+    Ok(())
+}
+
 fn notify_test(cfg: &Config) -> Result<(), String> {
     let notifier = Notifier::from_config(&cfg.notify);
     notifier.send_result(
@@ -192,21 +725,50 @@ fn writable_parent(path: &Path) -> io::Result<()> {
     Ok(())
 }
 
-fn data_prepare(cfg: &Config) -> Result<(), String> {
-    let report = db::prepare_from_pickles(cfg).map_err(|e| e.to_string())?;
+fn data_prepare(cfg: &Config, skip_bad: bool) -> Result<(), String> {
+    let mut cfg = cfg.clone();
+    cfg.skip_bad_pickles |= skip_bad;
+    let report = db::prepare_from_pickles(&cfg).map_err(|e| e.to_string())?;
+    for file in report.files.iter().filter(|f| f.error.is_some()) {
+        println!(
+            "bad slice {}: {}",
+            file.path.display(),
+            file.error.as_deref().unwrap_or_default()
+        );
+    }
     println!(
-        "Prepared {} unique hash160s in {:.2?} -> {} ({} skipped)",
+        "Prepared {} unique hash160s from {} slice(s) in {:.2?} -> {} ({} skipped, {} bad slice(s))",
         report.db.len(),
+        report.files.len(),
         report.elapsed,
         cfg.snapshot.display(),
-        report.skipped
+        report.skipped,
+        report.bad_files
     );
     Ok(())
 }
 
-fn data_update(cfg: &Config, source_url: Option<String>) -> Result<(), String> {
-    let url = source_url.unwrap_or_else(|| cfg.source_url.clone());
-    let report = db::update_from_url(cfg, &url).map_err(|e| e.to_string())?;
+fn data_update(
+    cfg: &Config,
+    source_url: Option<String>,
+    from_peer: Option<String>,
+) -> Result<(), String> {
+    if let Some(peer_url) = from_peer {
+        let report = peer::fetch_from_peer(cfg, &peer_url).map_err(|e| e.to_string())?;
+        println!(
+            "Copied {} with {} hash160s in {:.2?}. Restart the engine to load it.",
+            report.source,
+            report.db.len(),
+            report.elapsed
+        );
+        return Ok(());
+    }
+    let source = match source_url {
+        Some(location) => source::from_location(&location, &cfg.s3),
+        None => source::from_config(cfg),
+    }
+    .map_err(|e| e.to_string())?;
+    let report = db::update_from_source(cfg, source.as_ref()).map_err(|e| e.to_string())?;
     println!(
         "Updated snapshot {} with {} unique hash160s in {:.2?} ({} skipped). Restart the engine to load it.",
         cfg.snapshot.display(),
@@ -222,3 +784,71 @@ fn data_inspect(cfg: &Config) -> Result<(), String> {
     println!("{info}");
     Ok(())
 }
+
+fn data_query(cfg: &Config, inputs: Vec<String>, json: bool) -> Result<(), String> {
+    let chain = db::read_header(&cfg.snapshot)
+        .map_err(|e| format!("{}: {e}", cfg.snapshot.display()))?
+        .chain;
+    let loaded = db::load_snapshot_with(&cfg.snapshot, cfg.lookup, cfg.bits_per_key)
+        .map_err(|e| format!("{}: {e}", cfg.snapshot.display()))?;
+    let inputs = if inputs.is_empty() {
+        io::stdin()
+            .lines()
+            .collect::<io::Result<Vec<_>>>()
+            .map_err(|e| e.to_string())?
+    } else {
+        inputs
+    };
+    let mut out = io::stdout().lock();
+    for input in inputs {
+        let input = input.trim();
+        if input.is_empty() || input.starts_with('#') {
+            continue;
+        }
+        let result = db::query(&loaded.db, input, chain);
+        let line = if json {
+            serde_json::to_string(&result).map_err(|e| e.to_string())?
+        } else {
+            format!(
+                "input={} type={} hash160={} bloom={} bucket={} bucket_records={} found={}",
+                result.input,
+                result.kind,
+                result.hash160.as_deref().unwrap_or("-"),
+                result
+                    .bloom
+                    .map_or("n/a", |b| if b { "maybe" } else { "no" }),
+                result.bucket.as_deref().unwrap_or("-"),
+                result
+                    .bucket_records
+                    .map_or_else(|| "n/a".to_owned(), |n| n.to_string()),
+                result.found
+            )
+        };
+        writeln!(out, "{line}").map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations_take_units_and_refuse_nonsense() {
+        assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("1.5m"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("2h"), Ok(Duration::from_secs(7200)));
+        assert_eq!(parse_duration("3650d"), Ok(MAX_DURATION));
+        for bad in [
+            "",
+            "0",
+            "-5s",
+            "10w",
+            "3651d",
+            "300000000000d",
+            "99999999999999999999999d",
+        ] {
+            assert!(parse_duration(bad).is_err(), "{bad:?} was accepted");
+        }
+    }
+}
@@ -0,0 +1,198 @@
+//! What the hot loop looks hash160s up in.
+//!
+//! Workers hand each batch of hashes to a [`Matcher`] and learn which of them
+//! are in the set. A loaded [`Db`] is the usual one; [`MemorySet`] holds a
+//! few hashes for tests and demos, and [`Tagged`] searches several sets at
+//! once so a hit can say which dataset it came from.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::db::Db;
+
+pub trait Matcher: Send + Sync {
+    /// Call `found(index, tag)` for every hash in `hashes` that is in the
+    /// set. `tag` names the dataset that matched, for sets that have one; a
+    /// hash held by several tagged datasets is reported once for each.
+    fn match_batch(&self, hashes: &[[u8; 20]], found: &mut dyn FnMut(usize, Option<&str>));
+
+    /// Distinct hashes in the set.
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Lookup strategy, for status reports.
+    fn lookup_name(&self) -> String;
+
+    /// Approximate resident memory.
+    fn ram_bytes(&self) -> usize;
+}
+
+impl Matcher for Db {
+    fn match_batch(&self, hashes: &[[u8; 20]], found: &mut dyn FnMut(usize, Option<&str>)) {
+        for (i, hash) in hashes.iter().enumerate() {
+            if self.contains(hash) {
+                found(i, None);
+            }
+        }
+    }
+
+    fn len(&self) -> usize {
+        Db::len(self)
+    }
+
+    fn lookup_name(&self) -> String {
+        Db::lookup_name(self).to_owned()
+    }
+
+    fn ram_bytes(&self) -> usize {
+        Db::ram_bytes(self)
+    }
+}
+
+impl<M: Matcher + ?Sized> Matcher for Arc<M> {
+    fn match_batch(&self, hashes: &[[u8; 20]], found: &mut dyn FnMut(usize, Option<&str>)) {
+        (**self).match_batch(hashes, found);
+    }
+
+    fn len(&self) -> usize {
+        (**self).len()
+    }
+
+    fn lookup_name(&self) -> String {
+        (**self).lookup_name()
+    }
+
+    fn ram_bytes(&self) -> usize {
+        (**self).ram_bytes()
+    }
+}
+
+/// A plain in-memory set, for synthetic end-to-end runs.
+#[derive(Debug, Default, Clone)]
+pub struct MemorySet(HashSet<[u8; 20]>);
+
+impl MemorySet {
+    pub fn new(hashes: impl IntoIterator<Item = [u8; 20]>) -> Self {
+        MemorySet(hashes.into_iter().collect())
+    }
+
+    pub fn insert(&mut self, hash: [u8; 20]) -> bool {
+        self.0.insert(hash)
+    }
+}
+
+impl Matcher for MemorySet {
+    fn match_batch(&self, hashes: &[[u8; 20]], found: &mut dyn FnMut(usize, Option<&str>)) {
+        for (i, hash) in hashes.iter().enumerate() {
+            if self.0.contains(hash) {
+                found(i, None);
+            }
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn lookup_name(&self) -> String {
+        "memory".to_owned()
+    }
+
+    fn ram_bytes(&self) -> usize {
+        self.0.len() * 40
+    }
+}
+
+/// Several sets searched together. A hash present in more than one is
+/// reported once per set that holds it, so a key funded on two chains is
+/// recorded on both.
+#[derive(Default)]
+pub struct Tagged {
+    sets: Vec<(String, Box<dyn Matcher>)>,
+}
+
+impl Tagged {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, tag: impl Into<String>, set: impl Matcher + 'static) -> Self {
+        self.push(tag, set);
+        self
+    }
+
+    pub fn push(&mut self, tag: impl Into<String>, set: impl Matcher + 'static) {
+        self.sets.push((tag.into(), Box::new(set)));
+    }
+
+    pub fn tags(&self) -> impl Iterator<Item = &str> {
+        self.sets.iter().map(|(tag, _)| tag.as_str())
+    }
+}
+
+impl Matcher for Tagged {
+    fn match_batch(&self, hashes: &[[u8; 20]], found: &mut dyn FnMut(usize, Option<&str>)) {
+        for (tag, set) in &self.sets {
+            set.match_batch(hashes, &mut |i, inner| found(i, Some(inner.unwrap_or(tag))));
+        }
+    }
+
+    /// The sum over the sets; a hash held by two sets counts twice.
+    fn len(&self) -> usize {
+        self.sets.iter().map(|(_, set)| set.len()).sum()
+    }
+
+    fn lookup_name(&self) -> String {
+        let names: Vec<_> = self
+            .sets
+            .iter()
+            .map(|(tag, set)| format!("{tag}:{}", set.lookup_name()))
+            .collect();
+        names.join(",")
+    }
+
+    fn ram_bytes(&self) -> usize {
+        self.sets.iter().map(|(_, set)| set.ram_bytes()).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(set: &dyn Matcher, hashes: &[[u8; 20]]) -> Vec<(usize, Option<String>)> {
+        let mut out = Vec::new();
+        set.match_batch(hashes, &mut |i, tag| out.push((i, tag.map(str::to_owned))));
+        out
+    }
+
+    #[test]
+    fn tagged_sets_report_every_dataset_that_matches() {
+        let batch = [[1; 20], [2; 20], [3; 20], [4; 20]];
+        let tagged = Tagged::new()
+            .with("main", MemorySet::new([[2; 20], [3; 20]]))
+            .with("extra", MemorySet::new([[3; 20], [4; 20]]))
+            .with("empty", Db::Sorted(Vec::new()));
+
+        let mut found = matches(&tagged, &batch);
+        found.sort();
+        assert_eq!(
+            found,
+            [
+                (1, Some("main".to_owned())),
+                (2, Some("extra".to_owned())),
+                (2, Some("main".to_owned())),
+                (3, Some("extra".to_owned()))
+            ]
+        );
+        assert_eq!(tagged.len(), 4);
+        assert_eq!(
+            tagged.lookup_name(),
+            "main:memory,extra:memory,empty:sorted"
+        );
+        assert_eq!(matches(&Db::Sorted(vec![[4; 20]]), &batch), [(3, None)]);
+    }
+}
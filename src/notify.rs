@@ -33,20 +33,38 @@ pub struct Notifier {
 
 impl Notifier {
     pub fn from_config(cfg: &NotifyConfig) -> Self {
+        Self::build(cfg, true)
+    }
+
+    /// A notifier for a routed copy such as `[watch.groups.<name>]`. It uses
+    /// only the env vars that config names, never the `PLUTUS_BARK_KEY`
+    /// fallback, and fails when the provider's own secret is unset, so a
+    /// misconfigured route cannot land on the default device.
+    pub fn for_route(cfg: &NotifyConfig) -> Result<Self, String> {
+        let notifier = Self::build(cfg, false);
+        let env = match cfg.provider {
+            NotifyProvider::Disabled => return Ok(notifier),
+            NotifyProvider::Bark => &cfg.token_env,
+            NotifyProvider::Webhook => &cfg.webhook_url_env,
+            NotifyProvider::ServerChan => &cfg.serverchan_key_env,
+        };
+        if notifier.enabled() {
+            Ok(notifier)
+        } else {
+            Err(format!("{env} is not set"))
+        }
+    }
+
+    fn build(cfg: &NotifyConfig, bark_fallback: bool) -> Self {
         let client = Client::builder()
             .timeout(Duration::from_secs(10))
             .build()
             .unwrap_or_else(|_| Client::new());
-        let webhook_url = env::var(&cfg.webhook_url_env)
-            .ok()
-            .filter(|s| !s.is_empty());
-        let serverchan_key = env::var("PLUTUS_SERVERCHAN_KEY")
-            .ok()
-            .filter(|s| !s.is_empty());
-        let bark_key = env::var(&cfg.token_env)
-            .ok()
-            .or_else(|| env::var("PLUTUS_BARK_KEY").ok())
-            .filter(|s| !s.is_empty());
+        let secret = |name: &str| env::var(name).ok().filter(|s| !s.is_empty());
+        let webhook_url = secret(&cfg.webhook_url_env);
+        let serverchan_key = secret(&cfg.serverchan_key_env);
+        let bark_key = secret(&cfg.token_env)
+            .or_else(|| bark_fallback.then(|| secret("PLUTUS_BARK_KEY")).flatten());
         Self {
             client,
             provider: cfg.provider,
@@ -123,6 +141,15 @@ impl Notifier {
         self.send_inner(title, body, level, "plutus-hit")
     }
 
+    /// Watchlist rule alert. Time-sensitive like a hit's first delivery, but
+    /// in its own `plutus-watch` group so it never stacks with key hits.
+    pub fn send_watch_alert(&self, title: &str, body: &str) -> Result<(), String> {
+        if !self.enabled() {
+            return Err("notify is not enabled or credentials are missing".into());
+        }
+        self.send_inner(title, body, AlertLevel::TimeSensitive, "plutus-watch")
+    }
+
     fn send_inner(
         &self,
         title: &str,
@@ -205,3 +232,76 @@ impl Notifier {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// Accepts `requests` Bark posts and returns their form bodies.
+    fn bark_server(requests: usize) -> (String, thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/device-key", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            let mut bodies = Vec::new();
+            for stream in listener.incoming().take(requests) {
+                let mut reader = BufReader::new(stream.unwrap());
+                let mut length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    let line = line.trim_end().to_ascii_lowercase();
+                    if line.is_empty() {
+                        break;
+                    }
+                    if let Some(value) = line.strip_prefix("content-length:") {
+                        length = value.trim().parse().unwrap();
+                    }
+                }
+                let mut body = vec![0u8; length];
+                reader.read_exact(&mut body).unwrap();
+                bodies.push(String::from_utf8(body).unwrap());
+                reader
+                    .get_mut()
+                    .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                    .unwrap();
+            }
+            bodies
+        });
+        (url, server)
+    }
+
+    #[test]
+    fn watch_alerts_are_grouped_apart_from_hits() {
+        let (url, server) = bark_server(2);
+        std::env::set_var("PLUTUS_TEST_BARK_GROUPS_URL", url);
+        let mut cfg = Config::default().notify;
+        cfg.token_env = "PLUTUS_TEST_BARK_GROUPS_URL".to_owned();
+        let notifier = Notifier::from_config(&cfg);
+
+        notifier.send_watch_alert("watch", "rule fired").unwrap();
+        notifier.send_hit("hit", "key found", 1).unwrap();
+        let bodies = server.join().unwrap();
+        assert!(bodies[0].contains("group=plutus-watch"), "{}", bodies[0]);
+        assert!(bodies[1].contains("group=plutus-hit"), "{}", bodies[1]);
+    }
+
+    #[test]
+    fn routes_need_their_own_secret() {
+        let mut route = Config::default().notify;
+        route.token_env = "PLUTUS_TEST_ROUTE_KEY_UNSET".to_owned();
+        let error = Notifier::for_route(&route).err().unwrap();
+        assert!(error.contains("PLUTUS_TEST_ROUTE_KEY_UNSET"), "{error}");
+
+        route.provider = NotifyProvider::ServerChan;
+        route.serverchan_key_env = "PLUTUS_TEST_ROUTE_SCT_UNSET".to_owned();
+        let error = Notifier::for_route(&route).err().unwrap();
+        assert!(error.contains("PLUTUS_TEST_ROUTE_SCT_UNSET"), "{error}");
+
+        route.provider = NotifyProvider::Disabled;
+        assert!(Notifier::for_route(&route).is_ok());
+    }
+}
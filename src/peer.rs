@@ -0,0 +1,495 @@
+//! Snapshot sharing between nodes on a LAN.
+//!
+//! `data serve` exposes the local PLH2 file read-only over plain HTTP:
+//!
+//! * `GET /manifest.json` — count, size, creation time and SHA-256.
+//! * `GET /snapshot.h160` — the file itself.
+//!
+//! `data update --from-peer` streams the file next to the local snapshot,
+//! checks size, header count and hash against the manifest, and only then
+//! renames it into place. A node that fetches from a peer skips the download
+//! and the external sort entirely.
+
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use reqwest::blocking::Client;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::config::Config;
+use crate::db::{self, LoadReport};
+use crate::source;
+
+pub const DEFAULT_BIND: &str = "0.0.0.0:8470";
+/// Requests handled at once; more are turned away with 503 instead of
+/// each getting a thread.
+const MAX_CLIENTS: usize = 16;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub magic: String,
+    pub count: u64,
+    pub created_unix: u64,
+    pub size_bytes: u64,
+    pub sha256: String,
+}
+
+/// Manifest plus the file identity it was computed from, so a snapshot that
+/// `data update` replaced underneath the server gets re-hashed.
+struct Cached {
+    manifest: Manifest,
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+pub fn manifest_for(path: &Path) -> io::Result<Manifest> {
+    let header = db::read_header(path)?;
+    if header.magic != "PLH2" {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "{}: only PLH2 snapshots can be served (found {})",
+                path.display(),
+                header.magic
+            ),
+        ));
+    }
+    let mut file = File::open(path)?;
+    let size_bytes = file.metadata()?.len();
+    let sha256 = sha256_of(&mut file)?;
+    Ok(Manifest {
+        magic: header.magic,
+        count: header.count,
+        created_unix: header.created_unix,
+        size_bytes,
+        sha256,
+    })
+}
+
+/// Serve `snapshot` until the process is killed.
+pub fn serve(snapshot: &Path, bind: &str) -> io::Result<()> {
+    let listener = TcpListener::bind(bind)?;
+    println!(
+        "serving {} on http://{} (manifest.json, snapshot.h160)",
+        snapshot.display(),
+        listener.local_addr()?
+    );
+    serve_on(listener, snapshot)
+}
+
+pub fn serve_on(listener: TcpListener, snapshot: &Path) -> io::Result<()> {
+    let cache = Arc::new(Mutex::new(None::<Cached>));
+    current_manifest(snapshot, &cache)?;
+    let active = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming() {
+        let mut stream = match stream {
+            Ok(stream) => stream,
+            Err(error) => {
+                eprintln!("accept failed: {error}");
+                continue;
+            }
+        };
+        let peer = stream
+            .peer_addr()
+            .map(|a| a.to_string())
+            .unwrap_or_default();
+        let Some(slot) = Slot::take(&active) else {
+            let _ = stream.set_write_timeout(Some(Duration::from_secs(5)));
+            let _ = respond(
+                &mut stream,
+                "503 Service Unavailable",
+                "text/plain",
+                b"busy\n",
+            );
+            eprintln!("peer {peer}: turned away, {MAX_CLIENTS} requests in progress");
+            continue;
+        };
+        let snapshot = snapshot.to_path_buf();
+        let cache = Arc::clone(&cache);
+        thread::spawn(move || {
+            let _slot = slot;
+            if let Err(error) = handle(stream, &snapshot, &cache) {
+                eprintln!("peer {peer}: {error}");
+            }
+        });
+    }
+    Ok(())
+}
+
+/// One of the [`MAX_CLIENTS`] request slots, released on drop.
+struct Slot(Arc<AtomicUsize>);
+
+impl Slot {
+    fn take(active: &Arc<AtomicUsize>) -> Option<Self> {
+        active
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| {
+                (n < MAX_CLIENTS).then_some(n + 1)
+            })
+            .ok()
+            .map(|_| Slot(Arc::clone(active)))
+    }
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+fn current_manifest(path: &Path, cache: &Mutex<Option<Cached>>) -> io::Result<Manifest> {
+    let meta = fs::metadata(path)?;
+    let modified = meta.modified().ok();
+    let mut slot = cache.lock().unwrap_or_else(|p| p.into_inner());
+    if let Some(cached) = slot.as_ref() {
+        if cached.len == meta.len() && cached.modified == modified {
+            return Ok(cached.manifest.clone());
+        }
+    }
+    let timer = Instant::now();
+    let manifest = manifest_for(path)?;
+    println!(
+        "hashed {} ({} records) in {:.2?}",
+        path.display(),
+        manifest.count,
+        timer.elapsed()
+    );
+    *slot = Some(Cached {
+        manifest: manifest.clone(),
+        len: meta.len(),
+        modified,
+    });
+    Ok(manifest)
+}
+
+fn handle(stream: TcpStream, snapshot: &Path, cache: &Mutex<Option<Cached>>) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(30)))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+    }
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or("");
+    let target = parts.next().unwrap_or("");
+    let mut out = stream;
+    if method != "GET" && method != "HEAD" {
+        return respond(
+            &mut out,
+            "405 Method Not Allowed",
+            "text/plain",
+            b"read-only\n",
+        );
+    }
+    let head_only = method == "HEAD";
+    match target {
+        "/manifest.json" => {
+            let manifest = current_manifest(snapshot, cache)?;
+            let body = serde_json::to_vec_pretty(&manifest)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            let body: &[u8] = if head_only { &[] } else { &body };
+            respond(&mut out, "200 OK", "application/json", body)
+        }
+        "/snapshot.h160" => {
+            // The length comes from the open handle, which a rename of a new
+            // snapshot into place does not change. If that happens after the
+            // client read the manifest, its hash check rejects the file and
+            // `data update --from-peer` has to be run again.
+            let file = File::open(snapshot)?;
+            let len = file.metadata()?.len();
+            write!(
+                out,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/octet-stream\r\nContent-Length: {len}\r\nConnection: close\r\n\r\n"
+            )?;
+            if !head_only {
+                io::copy(&mut file.take(len), &mut out)?;
+            }
+            out.flush()
+        }
+        _ => respond(&mut out, "404 Not Found", "text/plain", b"not found\n"),
+    }
+}
+
+fn respond(out: &mut TcpStream, status: &str, content_type: &str, body: &[u8]) -> io::Result<()> {
+    write!(
+        out,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    )?;
+    out.write_all(body)?;
+    out.flush()
+}
+
+/// Stream a peer's snapshot into `cfg.snapshot`. Nothing is replaced unless
+/// size, record count and SHA-256 all match the peer's manifest and the header
+/// names the configured chain.
+pub fn fetch_from_peer(cfg: &Config, base_url: &str) -> io::Result<LoadReport> {
+    let timer = Instant::now();
+    let base = base_url.trim_end_matches('/');
+    let client = Client::builder()
+        .timeout(None)
+        .build()
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+    let raw = client
+        .get(format!("{base}/manifest.json"))
+        .send()
+        .and_then(|r| r.error_for_status())
+        .and_then(|r| r.bytes())
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+    let manifest: Manifest =
+        serde_json::from_slice(&raw).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    println!(
+        "peer {base} offers {} records ({:.1} MB, sha256 {})",
+        manifest.count,
+        manifest.size_bytes as f64 / (1024.0 * 1024.0),
+        manifest.sha256.get(..16).unwrap_or(&manifest.sha256)
+    );
+    let response = client
+        .get(format!("{base}/snapshot.h160"))
+        .send()
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+
+    let tmp = peer_tmp_path(&cfg.snapshot);
+    if let Some(parent) = tmp.parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent)?;
+        }
+    }
+    let result = receive(response, &tmp, &manifest).and_then(|()| {
+        db::require_chain(&tmp, cfg.data_chain)
+            .map_err(|error| io::Error::new(error.kind(), format!("peer {base}: {error}")))
+    });
+    if let Err(error) = result {
+        let _ = fs::remove_file(&tmp);
+        return Err(error);
+    }
+    fs::rename(&tmp, &cfg.snapshot)?;
+    let mut report = db::load_snapshot_with(&cfg.snapshot, cfg.lookup, cfg.bits_per_key)?;
+    report.source = format!("{} (from peer {base})", cfg.snapshot.display());
+    report.elapsed = timer.elapsed();
+    Ok(report)
+}
+
+fn peer_tmp_path(snapshot: &Path) -> PathBuf {
+    snapshot.with_extension("h160.peer.tmp")
+}
+
+fn receive(mut body: impl Read, tmp: &Path, manifest: &Manifest) -> io::Result<()> {
+    let mut file = File::create(tmp)?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 1 << 20];
+    let mut received = 0u64;
+    loop {
+        let n = body.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        file.write_all(&buf[..n])?;
+        received += n as u64;
+    }
+    file.sync_all()?;
+    drop(file);
+    verify(tmp, manifest, received, &source::hex(&hasher.finalize()))
+}
+
+fn verify(tmp: &Path, manifest: &Manifest, received: u64, sha256: &str) -> io::Result<()> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+    if received != manifest.size_bytes {
+        return Err(invalid(format!(
+            "peer sent {received} bytes, manifest says {}",
+            manifest.size_bytes
+        )));
+    }
+    if !sha256.eq_ignore_ascii_case(&manifest.sha256) {
+        return Err(invalid(format!(
+            "sha256 mismatch: got {sha256}, manifest says {}",
+            manifest.sha256
+        )));
+    }
+    let header = db::read_header(tmp)?;
+    if header.magic != "PLH2" || header.count != manifest.count {
+        return Err(invalid(format!(
+            "header {} count={} does not match manifest count={}",
+            header.magic, header.count, manifest.count
+        )));
+    }
+    Ok(())
+}
+
+fn sha256_of(file: &mut File) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 1 << 20];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(source::hex(&hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Lookup;
+    use crate::test_util::unique_dir;
+
+    fn sample_hashes() -> Vec<[u8; 20]> {
+        let mut hashes: Vec<[u8; 20]> = (0..500u32)
+            .map(|i| {
+                let mut h = [0u8; 20];
+                h[0..4].copy_from_slice(&i.wrapping_mul(0x9e37_79b9).to_be_bytes());
+                h
+            })
+            .collect();
+        hashes.sort_unstable();
+        hashes
+    }
+
+    #[test]
+    fn peer_snapshot_streams_and_activates() {
+        let served = unique_dir("plutus-peer-serve");
+        let snapshot = served.join("addresses.h160");
+        let hashes = sample_hashes();
+        db::write_snapshot(
+            &snapshot,
+            &db::Db::Sorted(hashes.clone()),
+            16,
+            crate::chain::BITCOIN,
+        )
+        .unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        {
+            let snapshot = snapshot.clone();
+            thread::spawn(move || serve_on(listener, &snapshot));
+        }
+
+        let local = unique_dir("plutus-peer-fetch");
+        let cfg = Config {
+            data_dir: local.clone(),
+            snapshot: local.join("addresses.h160"),
+            lookup: Lookup::Mmap,
+            ..Config::default()
+        };
+        let report = fetch_from_peer(&cfg, &format!("http://127.0.0.1:{port}/")).unwrap();
+        assert_eq!(report.db.len(), hashes.len());
+        assert!(report.db.contains(&hashes[123]));
+        assert_eq!(
+            fs::read(&snapshot).unwrap(),
+            fs::read(&cfg.snapshot).unwrap()
+        );
+        assert!(!peer_tmp_path(&cfg.snapshot).exists());
+        let _ = fs::remove_dir_all(served);
+        let _ = fs::remove_dir_all(local);
+    }
+
+    #[test]
+    fn peer_snapshot_for_another_chain_is_refused() {
+        let served = unique_dir("plutus-peer-chain");
+        let snapshot = served.join("addresses.h160");
+        let testnet = crate::chain::find("bitcoin", bitcoin::Network::Testnet).unwrap();
+        db::write_snapshot(&snapshot, &db::Db::Sorted(sample_hashes()), 16, testnet).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        {
+            let snapshot = snapshot.clone();
+            thread::spawn(move || serve_on(listener, &snapshot));
+        }
+
+        let local = unique_dir("plutus-peer-chain-fetch");
+        let cfg = Config {
+            data_dir: local.clone(),
+            snapshot: local.join("addresses.h160"),
+            ..Config::default()
+        };
+        let Err(error) = fetch_from_peer(&cfg, &format!("http://127.0.0.1:{port}")) else {
+            panic!("a testnet snapshot was accepted");
+        };
+        assert!(error.to_string().contains("testnet"), "{error}");
+        assert!(!cfg.snapshot.exists());
+        assert!(!peer_tmp_path(&cfg.snapshot).exists());
+        let _ = fs::remove_dir_all(served);
+        let _ = fs::remove_dir_all(local);
+    }
+
+    #[test]
+    fn busy_server_turns_extra_clients_away() {
+        let dir = unique_dir("plutus-peer-busy");
+        let snapshot = dir.join("addresses.h160");
+        db::write_snapshot(
+            &snapshot,
+            &db::Db::Sorted(sample_hashes()),
+            16,
+            crate::chain::BITCOIN,
+        )
+        .unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        {
+            let snapshot = snapshot.clone();
+            thread::spawn(move || serve_on(listener, &snapshot));
+        }
+
+        // Connections that never send a request hold their slots.
+        let idle: Vec<_> = (0..MAX_CLIENTS)
+            .map(|_| TcpStream::connect(address).unwrap())
+            .collect();
+        let mut extra = TcpStream::connect(address).unwrap();
+        let mut reply = String::new();
+        extra.read_to_string(&mut reply).unwrap();
+        assert!(reply.starts_with("HTTP/1.1 503"), "{reply}");
+
+        drop(idle);
+        let manifest = loop {
+            let mut client = TcpStream::connect(address).unwrap();
+            write!(client, "GET /manifest.json HTTP/1.1\r\n\r\n").unwrap();
+            let mut reply = String::new();
+            client.read_to_string(&mut reply).unwrap();
+            if reply.starts_with("HTTP/1.1 200") {
+                break reply;
+            }
+            // The idle handlers notice the hang-up a moment later.
+            thread::sleep(Duration::from_millis(20));
+        };
+        assert!(manifest.contains("\"magic\": \"PLH2\""), "{manifest}");
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn verify_rejects_hash_and_count_mismatch() {
+        let dir = unique_dir("plutus-peer-verify");
+        let snapshot = dir.join("addresses.h160");
+        db::write_snapshot(
+            &snapshot,
+            &db::Db::Sorted(sample_hashes()),
+            16,
+            crate::chain::BITCOIN,
+        )
+        .unwrap();
+        let manifest = manifest_for(&snapshot).unwrap();
+        let size = manifest.size_bytes;
+        assert!(verify(&snapshot, &manifest, size, &manifest.sha256).is_ok());
+        assert!(verify(&snapshot, &manifest, size, &"0".repeat(64)).is_err());
+        assert!(verify(&snapshot, &manifest, size - 1, &manifest.sha256).is_err());
+        let wrong_count = Manifest {
+            count: manifest.count + 1,
+            ..manifest.clone()
+        };
+        assert!(verify(&snapshot, &wrong_count, size, &manifest.sha256).is_err());
+        let _ = fs::remove_dir_all(dir);
+    }
+}
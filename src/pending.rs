@@ -168,15 +168,7 @@ fn save_unlocked(data_dir: &Path, items: &[PendingHit]) -> io::Result<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::env;
-
-    fn unique_dir() -> PathBuf {
-        let n = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_nanos();
-        env::temp_dir().join(format!("plutus-pending-{n}"))
-    }
+    use crate::test_util::unique_dir;
 
     fn sample(sent: u32, last_unix: u64) -> PendingHit {
         PendingHit {
@@ -231,7 +223,7 @@ mod tests {
 
     #[test]
     fn enqueue_roundtrip_and_dedup() {
-        let dir = unique_dir();
+        let dir = unique_dir("plutus-pending");
         enqueue(&dir, "addr-a", true, 10).unwrap();
         enqueue(&dir, "addr-a", true, 11).unwrap();
         enqueue(&dir, "addr-a", false, 12).unwrap();
@@ -242,7 +234,7 @@ mod tests {
 
     #[test]
     fn ack_drops_existing_but_keeps_later_hits() {
-        let dir = unique_dir();
+        let dir = unique_dir("plutus-pending");
         enqueue(&dir, "old", true, 10).unwrap();
         fs::write(ack_path(&dir), "50\n").unwrap();
         enqueue(&dir, "new", true, 80).unwrap();
@@ -256,7 +248,7 @@ mod tests {
 
     #[test]
     fn mark_sent_advances_counter() {
-        let dir = unique_dir();
+        let dir = unique_dir("plutus-pending");
         enqueue(&dir, "addr-a", true, 10).unwrap();
         mark_sent(&dir, "addr-a", true, 20).unwrap();
         let waiting = due(&dir, 21, 120, 24).unwrap();
@@ -268,7 +260,7 @@ mod tests {
 
     #[test]
     fn pending_file_never_holds_a_secret() {
-        let dir = unique_dir();
+        let dir = unique_dir("plutus-pending");
         enqueue(&dir, "15x5ugXCVkzTbs24mG2bu1RkpshW3FTYW8", true, 1).unwrap();
         let body = fs::read_to_string(pending_path(&dir)).unwrap();
         assert!(body.contains("15x5ugXCVkzTbs24mG2bu1RkpshW3FTYW8"));
@@ -0,0 +1,226 @@
+//! Proof that this build, on this CPU, would record a hit.
+//!
+//! Plants the hash160s of the well-known keys 1..=[`PLANTED_KEYS`], both
+//! encodings, in a throwaway PLH2 snapshot loaded with the configured lookup,
+//! then runs the real worker pipeline from key 1. Every planted key must come
+//! back through the hit callback, land in the findings file with the right
+//! WIF and address, and sit in the pending alert queue. Everything lives in a
+//! temp directory; the operator's findings and queue are never touched.
+
+use std::collections::BTreeSet;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use bitcoin::secp256k1::{Secp256k1, SecretKey};
+use bitcoin::PrivateKey;
+
+use crate::config::Config;
+use crate::db::{self, Db};
+use crate::engine::{self, Engine, StopToken};
+use crate::pending;
+
+pub const PLANTED_KEYS: u8 = 8;
+
+/// Give up if the planted keys have not all come back by then.
+const DEADLINE: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone)]
+pub struct SelfTest {
+    pub planted: usize,
+    pub found: usize,
+    pub keys_checked: u64,
+    pub simd: &'static str,
+    pub lookup: String,
+    pub elapsed: Duration,
+}
+
+/// One planted key as the engine should report it.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Expected {
+    address: String,
+    compressed: bool,
+    wif: String,
+}
+
+/// Run the self-test. On failure the temp directory is kept and named in the
+/// error for inspection.
+pub fn run(cfg: &Config) -> Result<SelfTest, String> {
+    let unique = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    let dir = env::temp_dir().join(format!("plutus-selftest-{}-{unique}", std::process::id()));
+    match run_in(cfg, &dir) {
+        Ok(report) => {
+            let _ = fs::remove_dir_all(&dir);
+            Ok(report)
+        }
+        Err(error) => Err(format!("{error} (files kept in {})", dir.display())),
+    }
+}
+
+fn run_in(cfg: &Config, dir: &Path) -> Result<SelfTest, String> {
+    let secp = Secp256k1::new();
+    let mut expected = BTreeSet::new();
+    let mut hashes = Vec::new();
+    for n in 1..=PLANTED_KEYS {
+        for compressed in [true, false] {
+            let mut private_key = PrivateKey::new(secret(n), cfg.chain.network);
+            private_key.compressed = compressed;
+            let public_key = bitcoin::PublicKey::from_private_key(&secp, &private_key);
+            let hash = engine::hash160(&public_key.to_bytes());
+            hashes.push(hash);
+            expected.insert(Expected {
+                address: cfg.chain.p2pkh(&hash),
+                compressed,
+                wif: cfg.chain.wif(&secret(n), compressed),
+            });
+        }
+    }
+    hashes.sort_unstable();
+
+    let snapshot = dir.join("selftest.h160");
+    db::write_snapshot(&snapshot, &Db::Sorted(hashes), cfg.bits_per_key, cfg.chain)
+        .map_err(|e| format!("writing {}: {e}", snapshot.display()))?;
+    let loaded = db::load_snapshot_with(&snapshot, cfg.lookup, cfg.bits_per_key)
+        .map_err(|e| format!("loading {}: {e}", snapshot.display()))?;
+    let lookup = loaded.db.lookup_name().to_owned();
+
+    let findings = dir.join("hits.txt");
+    let stop = StopToken::new();
+    let hits = Arc::new(Mutex::new(Vec::new()));
+    let started = Instant::now();
+    let summary = Engine::builder(loaded.db)
+        .threads(1)
+        .chain(cfg.chain)
+        .check_uncompressed(true)
+        .cpu_percent(100)
+        .findings(&findings)
+        .pending_dir(dir)
+        .snapshot(snapshot.display().to_string())
+        .start_key(secret(1))
+        .stop_token(stop.clone())
+        .tick(Duration::from_millis(50))
+        .on_hit({
+            let hits = Arc::clone(&hits);
+            let stop = stop.clone();
+            let planted = expected.len();
+            move |hit| {
+                let mut hits = hits.lock().unwrap_or_else(|p| p.into_inner());
+                hits.push((hit.address.clone(), hit.compressed));
+                if hits.len() >= planted {
+                    stop.stop();
+                }
+            }
+        })
+        .on_progress({
+            let stop = stop.clone();
+            move |progress| {
+                if progress.elapsed >= DEADLINE {
+                    stop.stop();
+                }
+            }
+        })
+        .build()
+        .run();
+
+    let hits = hits.lock().unwrap_or_else(|p| p.into_inner()).clone();
+    let reported: BTreeSet<(String, bool)> = hits.iter().cloned().collect();
+    let wanted: BTreeSet<(String, bool)> = expected
+        .iter()
+        .map(|e| (e.address.clone(), e.compressed))
+        .collect();
+    if let Some((address, compressed)) = wanted.difference(&reported).next() {
+        return Err(format!(
+            "planted key {address} (compressed={compressed}) was not detected; {} of {} found after {} keys",
+            reported.len(),
+            wanted.len(),
+            summary.keys_checked
+        ));
+    }
+
+    let record = fs::read_to_string(&findings)
+        .map_err(|e| format!("reading {}: {e}", findings.display()))?;
+    let lines: BTreeSet<&str> = record.lines().collect();
+    for key in &expected {
+        if !lines.contains(key.address.as_str()) || !lines.contains(key.wif.as_str()) {
+            return Err(format!("findings file is missing {}", key.address));
+        }
+    }
+
+    let queued: BTreeSet<(String, bool)> = pending::due(dir, pending::unix_now(), 0, 0)
+        .map_err(|e| format!("reading the pending queue: {e}"))?
+        .into_iter()
+        .map(|item| (item.address, item.compressed))
+        .collect();
+    if let Some((address, _)) = wanted.difference(&queued).next() {
+        return Err(format!("pending queue is missing {address}"));
+    }
+
+    Ok(SelfTest {
+        planted: wanted.len(),
+        found: reported.len(),
+        keys_checked: summary.keys_checked,
+        simd: engine::simd_name(),
+        lookup,
+        elapsed: started.elapsed(),
+    })
+}
+
+fn secret(n: u8) -> SecretKey {
+    let mut bytes = [0u8; 32];
+    bytes[31] = n;
+    SecretKey::from_slice(&bytes).expect("small nonzero key")
+}
+
+/// One key=value line, as printed by `selftest` and `doctor --deep`.
+pub fn describe(report: &SelfTest) -> String {
+    format!(
+        "selftest=ok planted={} found={} keys={} simd={} lookup={} elapsed={:.2?}",
+        report.planted,
+        report.found,
+        report.keys_checked,
+        report.simd,
+        report.lookup,
+        report.elapsed
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Lookup;
+
+    #[test]
+    fn planted_keys_are_detected_with_every_lookup() {
+        for lookup in [Lookup::Mmap, Lookup::Sorted, Lookup::Hash] {
+            let cfg = Config {
+                lookup,
+                ..Config::default()
+            };
+            let report = run(&cfg).unwrap();
+            assert_eq!(report.found, usize::from(PLANTED_KEYS) * 2);
+            assert_eq!(report.lookup, lookup.as_str());
+        }
+    }
+
+    #[test]
+    fn planted_keys_are_recorded_in_the_configured_chain() {
+        for chain in ["regtest", "litecoin", "dogecoin", "bitcoin-cash"] {
+            let chain = crate::chain::CHAINS
+                .iter()
+                .find(|c| c.name == chain)
+                .copied()
+                .unwrap();
+            let cfg = Config {
+                chain,
+                ..Config::default()
+            };
+            let report = run(&cfg).unwrap();
+            assert_eq!(report.found, usize::from(PLANTED_KEYS) * 2);
+        }
+    }
+}
@@ -1,5 +1,5 @@
-use std::fs;
-use std::io;
+use std::fs::{self, File};
+use std::io::{self, Write};
 use std::path::Path;
 use std::time::{SystemTime, UNIX_EPOCH};
 
@@ -24,21 +24,74 @@ pub struct Status {
     pub cpu_percent: u8,
     pub node: String,
     pub running: bool,
+    /// Workers are parked by a pause request; the set stays loaded.
+    pub paused: bool,
     pub reload_requested: bool,
 }
 
-pub fn write_atomic(path: &Path, status: &Status) -> io::Result<()> {
+/// Written by `run --summary-json` when a run ends, so bounded jobs can be
+/// compared across nodes and commits.
+#[derive(Debug, Serialize)]
+pub struct RunSummary {
+    pub version: &'static str,
+    pub node: String,
+    pub outcome: &'static str,
+    pub started_unix: u64,
+    pub finished_unix: u64,
+    pub elapsed_secs: f64,
+    pub duration_limit_secs: Option<f64>,
+    pub max_keys: Option<u64>,
+    pub keys_checked: u64,
+    pub hits: u64,
+    pub keys_per_sec_avg: f64,
+    pub threads: usize,
+    pub per_thread_keys: Vec<u64>,
+    pub per_thread_keys_per_sec: Vec<f64>,
+    pub check_uncompressed: bool,
+    pub cpu_percent: u8,
+    pub simd: &'static str,
+    pub lookup: String,
+    pub ram_bytes: usize,
+    pub db_size: usize,
+    pub snapshots: Vec<SnapshotIdentity>,
+}
+
+/// Enough of a snapshot to tell two runs used the same one.
+#[derive(Debug, Serialize)]
+pub struct SnapshotIdentity {
+    pub path: String,
+    pub magic: String,
+    pub version: u16,
+    pub count: u64,
+    pub created_unix: u64,
+    pub size_bytes: u64,
+    pub chain: &'static str,
+    pub network: String,
+}
+
+/// Write `value` as pretty JSON with [`write_atomic_bytes`], so readers
+/// never observe a half-written file.
+pub fn write_atomic<T: Serialize>(path: &Path, value: &T) -> io::Result<()> {
+    let body = serde_json::to_vec_pretty(value)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    write_atomic_bytes(path, &body)
+}
+
+/// Replace `path` with `body` via a synced sibling temp file and a rename,
+/// so readers never see a partial file.
+pub fn write_atomic_bytes(path: &Path, body: &[u8]) -> io::Result<()> {
     if let Some(parent) = path.parent() {
         if !parent.as_os_str().is_empty() {
             fs::create_dir_all(parent)?;
         }
     }
-    let body = serde_json::to_vec_pretty(status)
-        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
-    let tmp = path.with_extension("json.tmp");
-    fs::write(&tmp, body)?;
-    fs::rename(tmp, path)?;
-    Ok(())
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let mut file = File::create(&tmp)?;
+    file.write_all(body)?;
+    file.sync_all()?;
+    drop(file);
+    fs::rename(tmp, path)
 }
 
 pub fn unix_now() -> u64 {